@@ -0,0 +1,271 @@
+//! Parsers for cgroup v2 interface files and per-network-namespace counters, and
+//! resolution of container cgroup paths.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use serde::Serialize;
+
+pub const CGROUP_ROOT: &str = "/sys/fs/cgroup";
+
+/// Map an OCI `linux.cgroupsPath` to its directory under the unified hierarchy. Handles
+/// plain paths (cgroupfs driver) and `slice:prefix:name` (systemd driver).
+pub fn resolve_cgroups_path(root: &Path, cgroups_path: &str) -> Option<PathBuf> {
+    if cgroups_path.starts_with('/') {
+        return Some(root.join(cgroups_path.trim_start_matches('/')));
+    }
+    let mut parts = cgroups_path.splitn(3, ':');
+    let (slice, prefix, name) = (parts.next()?, parts.next()?, parts.next()?);
+    let slice = if slice.is_empty() {
+        "system.slice"
+    } else {
+        slice
+    };
+    // Nested slices live inside their parents: a-b.slice is under a.slice.
+    let mut dir = root.to_path_buf();
+    let stem = slice.strip_suffix(".slice")?;
+    let mut parent = String::new();
+    for component in stem.split('-') {
+        if !parent.is_empty() {
+            parent.push('-');
+        }
+        parent.push_str(component);
+        dir.push(format!("{}.slice", parent));
+    }
+    let unit = if prefix.is_empty() {
+        format!("{}.scope", name)
+    } else {
+        format!("{}-{}.scope", prefix, name)
+    };
+    Some(dir.join(unit))
+}
+
+/// Parse a flat-keyed file such as `cpu.stat` or `memory.stat` (`key value` per line).
+pub fn parse_flat_keyed(content: &str) -> HashMap<&str, u64> {
+    content
+        .lines()
+        .filter_map(|line| {
+            let mut parts = line.split_whitespace();
+            let key = parts.next()?;
+            let value = parts.next()?.parse().ok()?;
+            Some((key, value))
+        })
+        .collect()
+}
+
+/// Parse a single-value limit file such as `memory.max`; `max` means unlimited.
+pub fn parse_limit(content: &str) -> Option<u64> {
+    match content.trim() {
+        "" | "max" => None,
+        value => value.parse().ok(),
+    }
+}
+
+/// Parse `cpu.max` (`$QUOTA $PERIOD`) into the number of CPUs the quota allows.
+pub fn parse_cpu_max(content: &str) -> Option<f64> {
+    let mut parts = content.split_whitespace();
+    let quota = parts.next()?;
+    let period: f64 = parts.next().unwrap_or("100000").parse().ok()?;
+    if quota == "max" || period <= 0.0 {
+        return None;
+    }
+    Some(quota.parse::<f64>().ok()? / period)
+}
+
+/// Sum read and written bytes across all devices in `io.stat`.
+pub fn parse_io_stat(content: &str) -> (u64, u64) {
+    let mut read = 0u64;
+    let mut written = 0u64;
+    for line in content.lines() {
+        for field in line.split_whitespace().skip(1) {
+            let Some((key, value)) = field.split_once('=') else {
+                continue;
+            };
+            let Ok(value) = value.parse::<u64>() else {
+                continue;
+            };
+            match key {
+                "rbytes" => read = read.saturating_add(value),
+                "wbytes" => written = written.saturating_add(value),
+                _ => {}
+            }
+        }
+    }
+    (read, written)
+}
+
+/// Sum received and transmitted bytes over all non-loopback interfaces in `/proc/<pid>/net/dev`.
+pub fn parse_net_dev(content: &str) -> (u64, u64) {
+    let mut rx = 0u64;
+    let mut tx = 0u64;
+    for line in content.lines().skip(2) {
+        let Some((iface, counters)) = line.split_once(':') else {
+            continue;
+        };
+        if iface.trim() == "lo" {
+            continue;
+        }
+        let fields: Vec<u64> = counters
+            .split_whitespace()
+            .filter_map(|value| value.parse().ok())
+            .collect();
+        if fields.len() >= 9 {
+            rx = rx.saturating_add(fields[0]);
+            tx = tx.saturating_add(fields[8]);
+        }
+    }
+    (rx, tx)
+}
+
+/// One line of a PSI file: share of wall time stalled over 10s, 60s and 300s windows,
+/// plus the cumulative stall time.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PressureLine {
+    pub avg10: f64,
+    pub avg60: f64,
+    pub avg300: f64,
+    pub total_usec: u64,
+}
+
+/// Pressure stall information from a `*.pressure` file. `some` counts time at least one
+/// task stalled; `full` time all tasks did (not reported for CPU on older kernels).
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize)]
+pub struct Pressure {
+    pub some: PressureLine,
+    pub full: Option<PressureLine>,
+}
+
+/// Parse a PSI file such as `cpu.pressure` or `/proc/pressure/io`.
+pub fn parse_pressure(content: &str) -> Option<Pressure> {
+    let mut some = None;
+    let mut full = None;
+    for line in content.lines() {
+        let mut fields = line.split_whitespace();
+        let kind = fields.next();
+        let mut parsed = PressureLine::default();
+        for field in fields {
+            let Some((key, value)) = field.split_once('=') else {
+                continue;
+            };
+            match key {
+                "avg10" => parsed.avg10 = value.parse().ok()?,
+                "avg60" => parsed.avg60 = value.parse().ok()?,
+                "avg300" => parsed.avg300 = value.parse().ok()?,
+                "total" => parsed.total_usec = value.parse().ok()?,
+                _ => {}
+            }
+        }
+        match kind {
+            Some("some") => some = Some(parsed),
+            Some("full") => full = Some(parsed),
+            _ => {}
+        }
+    }
+    Some(Pressure { some: some?, full })
+}
+
+/// Total and steal jiffies from the aggregate `cpu` line of `/proc/stat`.
+pub fn parse_cpu_steal(stat: &str) -> Option<(u64, u64)> {
+    let line = stat.lines().find(|line| line.starts_with("cpu "))?;
+    let fields: Vec<u64> = line
+        .split_whitespace()
+        .skip(1)
+        .filter_map(|value| value.parse().ok())
+        .collect();
+    // guest and guest_nice are already included in user and nice.
+    let total = fields.iter().take(8).sum();
+    Some((total, *fields.get(7)?))
+}
+
+/// Process start time in clock ticks after boot, field 22 of `/proc/<pid>/stat`.
+pub fn parse_proc_start_ticks(stat: &str) -> Option<u64> {
+    // The command name may contain spaces and parentheses; fields resume after the last ')'.
+    let (_, fields) = stat.rsplit_once(')')?;
+    fields.split_whitespace().nth(19)?.parse().ok()
+}
+
+/// Boot time in seconds since the epoch, the `btime` line of `/proc/stat`.
+pub fn parse_boot_time(stat: &str) -> Option<u64> {
+    stat.lines()
+        .find_map(|line| line.strip_prefix("btime "))
+        .and_then(|value| value.trim().parse().ok())
+}
+
+/// CPU usage as a percentage of `cpus`, from two cumulative `usage_usec` samples.
+pub fn cpu_percent(previous_usec: u64, current_usec: u64, elapsed_usec: u64, cpus: f64) -> f64 {
+    if elapsed_usec == 0 || cpus <= 0.0 || current_usec < previous_usec {
+        return 0.0;
+    }
+    let percent = (current_usec - previous_usec) as f64 / (elapsed_usec as f64 * cpus) * 100.0;
+    percent.clamp(0.0, 100.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_cpu_and_memory_files() {
+        let stat = parse_flat_keyed("usage_usec 1500000\nuser_usec 1000000\nsystem_usec 500000\n");
+        assert_eq!(stat.get("usage_usec"), Some(&1_500_000));
+        assert_eq!(parse_limit("max\n"), None);
+        assert_eq!(parse_limit("1073741824\n"), Some(1 << 30));
+        assert_eq!(parse_cpu_max("200000 100000\n"), Some(2.0));
+        assert_eq!(parse_cpu_max("max 100000\n"), None);
+        assert_eq!(cpu_percent(0, 1_000_000, 1_000_000, 2.0), 50.0);
+    }
+
+    #[test]
+    fn resolves_cgroups_path() {
+        let root = Path::new(CGROUP_ROOT);
+        assert_eq!(
+            resolve_cgroups_path(root, "/catalyst/srv1"),
+            Some(PathBuf::from("/sys/fs/cgroup/catalyst/srv1"))
+        );
+        assert_eq!(
+            resolve_cgroups_path(root, "machine-catalyst.slice:cri:srv1"),
+            Some(PathBuf::from(
+                "/sys/fs/cgroup/machine.slice/machine-catalyst.slice/cri-srv1.scope"
+            ))
+        );
+    }
+
+    #[test]
+    fn parses_io_and_net_counters() {
+        let io = "259:0 rbytes=4096 wbytes=8192 rios=1 wios=2 dbytes=0 dios=0\n\
+                  7:3 rbytes=1024 wbytes=0 rios=1 wios=0 dbytes=0 dios=0\n";
+        assert_eq!(parse_io_stat(io), (5120, 8192));
+
+        let net = "Inter-|   Receive                                                |  Transmit\n \
+                   face |bytes    packets errs drop fifo frame compressed multicast|bytes    packets errs drop fifo colls carrier compressed\n    \
+                   lo:     100       1    0    0    0     0          0         0      100       1    0    0    0     0       0          0\n  \
+                   eth0:    2048      10    0    0    0     0          0         0      512       5    0    0    0     0       0          0\n";
+        assert_eq!(parse_net_dev(net), (2048, 512));
+    }
+
+    #[test]
+    fn parses_pressure_and_steal() {
+        let psi = "some avg10=1.50 avg60=0.75 avg300=0.10 total=123456\n\
+                   full avg10=0.00 avg60=0.00 avg300=0.00 total=42\n";
+        let pressure = parse_pressure(psi).unwrap();
+        assert_eq!(pressure.some.avg10, 1.5);
+        assert_eq!(pressure.some.total_usec, 123_456);
+        assert_eq!(pressure.full.unwrap().total_usec, 42);
+        assert_eq!(parse_pressure(""), None);
+
+        let stat = "cpu  100 0 50 800 10 0 5 35 0 0\ncpu0 50 0 25 400 5 0 2 18 0 0\n";
+        assert_eq!(parse_cpu_steal(stat), Some((1000, 35)));
+    }
+
+    #[test]
+    fn parses_process_start_time() {
+        let stat = "4242 (java (srv) x) S 4200 4242 4242 0 -1 4194560 1 0 0 0 5 3 0 0 20 0 30 0 \
+                    987654 1000 100 18446744073709551615\n";
+        assert_eq!(parse_proc_start_ticks(stat), Some(987_654));
+        assert_eq!(
+            parse_boot_time("cpu  1 2 3\nbtime 1760000000\nprocesses 10\n"),
+            Some(1_760_000_000)
+        );
+    }
+}
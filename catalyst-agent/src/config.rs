@@ -1,4 +1,5 @@
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::path::PathBuf;
 
 #[derive(Debug, Clone, Deserialize, Serialize)]
@@ -7,6 +8,10 @@ pub struct AgentConfig {
     pub containerd: ContainerdConfig,
     #[serde(default)]
     pub networking: NetworkingConfig,
+    #[serde(default)]
+    pub images: ImageConfig,
+    #[serde(default)]
+    pub security: SecurityConfig,
     pub logging: LoggingConfig,
 }
 
@@ -67,6 +72,221 @@ fn default_dns_servers() -> Vec<String> {
     vec!["1.1.1.1".to_string(), "8.8.8.8".to_string()]
 }
 
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct ImageConfig {
+    /// Upper bound for a single image pull, including unpacking.
+    #[serde(default = "default_pull_timeout_secs")]
+    pub pull_timeout_secs: u64,
+    /// How often unreferenced images and orphaned snapshots are pruned; 0 disables it.
+    #[serde(default = "default_gc_interval_secs")]
+    pub gc_interval_secs: u64,
+    /// Images pulled more recently than this are never pruned.
+    #[serde(default = "default_gc_min_age_secs")]
+    pub gc_min_age_secs: u64,
+    /// Whether a server start pulls its image when one with the same name exists.
+    #[serde(default)]
+    pub pull_policy: PullPolicy,
+    #[serde(default)]
+    pub policy: ImagePolicyConfig,
+    /// Credentials for private registries, matched by host.
+    #[serde(default)]
+    pub registries: Vec<RegistryCredentials>,
+}
+
+impl Default for ImageConfig {
+    fn default() -> Self {
+        Self {
+            pull_timeout_secs: default_pull_timeout_secs(),
+            gc_interval_secs: default_gc_interval_secs(),
+            gc_min_age_secs: default_gc_min_age_secs(),
+            pull_policy: PullPolicy::default(),
+            policy: ImagePolicyConfig::default(),
+            registries: Vec::new(),
+        }
+    }
+}
+
+fn default_pull_timeout_secs() -> u64 {
+    1800
+}
+
+fn default_gc_interval_secs() -> u64 {
+    86400
+}
+
+fn default_gc_min_age_secs() -> u64 {
+    3600
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum PullPolicy {
+    /// Use a local image with the same name; digest-pinned references never go stale.
+    #[default]
+    IfNotPresent,
+    /// Re-resolve the tag against the registry on every start. Unchanged layers are not
+    /// downloaded again, and the local image is used if the registry is unreachable.
+    Always,
+}
+
+/// Node-wide rules every runtime and install image must pass. Repository patterns are
+/// a registry host (`ghcr.io`), a namespace (`ghcr.io/acme/*`) or a full repository.
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct ImagePolicyConfig {
+    /// Repositories images may come from; empty allows any.
+    #[serde(default)]
+    pub allowed_repositories: Vec<String>,
+    /// Reject references that are not pinned by digest.
+    #[serde(default)]
+    pub require_digest: bool,
+    /// Cosign signatures required for matching repositories.
+    #[serde(default)]
+    pub signatures: Vec<SignaturePolicy>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct SignaturePolicy {
+    pub repositories: Vec<String>,
+    /// Cosign public key (PEM) the images must be signed with.
+    pub public_key: PathBuf,
+}
+
+/// Credentials for one registry host. `username` and `password` are exchanged for a token
+/// by the registry (basic auth); `token` is sent as a bearer token as-is.
+#[derive(Clone, Default, PartialEq, Eq, Hash, Deserialize, Serialize)]
+pub struct RegistryCredentials {
+    pub host: String,
+    #[serde(default)]
+    pub username: Option<String>,
+    #[serde(default)]
+    pub password: Option<String>,
+    #[serde(default)]
+    pub token: Option<String>,
+}
+
+impl RegistryCredentials {
+    /// Whether these credentials belong to `host`. Docker Hub answers under several names.
+    pub fn matches(&self, host: &str) -> bool {
+        canonical_registry_host(&self.host) == canonical_registry_host(host)
+    }
+}
+
+impl std::fmt::Debug for RegistryCredentials {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("RegistryCredentials")
+            .field("host", &self.host)
+            .field("username", &self.username)
+            .field("password", &self.password.as_ref().map(|_| "[REDACTED]"))
+            .field("token", &self.token.as_ref().map(|_| "[REDACTED]"))
+            .finish()
+    }
+}
+
+fn canonical_registry_host(host: &str) -> String {
+    let host = host.trim();
+    let host = host
+        .strip_prefix("https://")
+        .or_else(|| host.strip_prefix("http://"))
+        .unwrap_or(host)
+        .trim_end_matches('/')
+        .to_ascii_lowercase();
+    match host.as_str() {
+        "registry-1.docker.io" | "index.docker.io" => "docker.io".to_string(),
+        _ => host,
+    }
+}
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct SecurityConfig {
+    /// Named profiles templates select with `securityProfile`.
+    #[serde(default)]
+    pub profiles: HashMap<String, SecurityProfile>,
+    #[serde(default)]
+    pub user_namespaces: UserNamespaceConfig,
+}
+
+/// Run every server in its own user namespace. Each server gets `range_size` host ids,
+/// allocated one range after another from `uid_base`, which should lie above any ids
+/// used by the host or listed in /etc/subuid.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct UserNamespaceConfig {
+    #[serde(default)]
+    pub enabled: bool,
+    #[serde(default = "default_userns_uid_base")]
+    pub uid_base: u32,
+    #[serde(default = "default_userns_range_size")]
+    pub range_size: u32,
+}
+
+impl Default for UserNamespaceConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            uid_base: default_userns_uid_base(),
+            range_size: default_userns_range_size(),
+        }
+    }
+}
+
+fn default_userns_uid_base() -> u32 {
+    1_000_000
+}
+
+fn default_userns_range_size() -> u32 {
+    65536
+}
+
+/// Security settings of a server container. Every unset field keeps the node's hardened
+/// default: uid/gid 1000, only `CAP_NET_BIND_SERVICE`, the built-in seccomp filter, a
+/// writable rootfs and the standard character devices.
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct SecurityProfile {
+    #[serde(default)]
+    pub uid: Option<u32>,
+    #[serde(default)]
+    pub gid: Option<u32>,
+    /// Capabilities granted to the server process, replacing the default set.
+    #[serde(default)]
+    pub capabilities: Option<Vec<String>>,
+    /// Seccomp profile JSON file, or `unconfined` to run without a filter.
+    #[serde(default)]
+    pub seccomp_profile: Option<String>,
+    /// Mount the image rootfs read-only; `/data` and the `tmpfs` mounts stay writable.
+    #[serde(default)]
+    pub read_only_rootfs: bool,
+    #[serde(default)]
+    pub tmpfs: Vec<TmpfsMount>,
+    /// Paths masked in addition to the default set.
+    #[serde(default)]
+    pub masked_paths: Vec<String>,
+    /// Device cgroup allowlist, replacing the default one.
+    #[serde(default)]
+    pub devices: Option<Vec<DeviceRule>>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct TmpfsMount {
+    pub path: String,
+    #[serde(default)]
+    pub size_mb: Option<u64>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct DeviceRule {
+    /// `c` for character or `b` for block devices.
+    #[serde(rename = "type")]
+    pub kind: String,
+    pub major: i64,
+    pub minor: i64,
+    /// Any of `r`, `w` and `m`.
+    #[serde(default = "default_device_access")]
+    pub access: String,
+}
+
+fn default_device_access() -> String {
+    "rwm".to_string()
+}
+
 #[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct CniNetworkConfig {
     pub name: String,
@@ -112,6 +332,8 @@ impl AgentConfig {
                     .unwrap_or_else(|_| "catalyst".to_string()),
             },
             networking: NetworkingConfig::default(),
+            images: ImageConfig::default(),
+            security: SecurityConfig::default(),
             logging: LoggingConfig {
                 level: std::env::var("LOG_LEVEL").unwrap_or_else(|_| "info".to_string()),
                 format: "json".to_string(),
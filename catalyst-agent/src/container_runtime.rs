@@ -0,0 +1,145 @@
+use std::collections::HashMap;
+use std::path::Path;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use tokio::sync::mpsc;
+
+use crate::config::{PullPolicy, RegistryCredentials};
+use crate::errors::AgentResult;
+use crate::runtime_manager::{
+    ContainerConfig, ContainerInfo, ContainerStats, ContainerStatus, ImagePullProgress,
+    ImageRecord, InteractiveExec, LogStream, MemoryEvents, ResourceLimits,
+};
+
+/// A lifecycle event for one container, named by containerd-style topic such as
+/// `/tasks/start`, `/tasks/exit`, `/tasks/oom` or `/containers/delete`.
+#[derive(Clone, Debug)]
+pub struct RuntimeEvent {
+    pub topic: String,
+    /// Empty when the runtime could not attribute the event to a container.
+    pub container_id: String,
+}
+
+/// Events from a runtime subscription; ends when the runtime closes it.
+pub struct EventStream {
+    receiver: mpsc::Receiver<RuntimeEvent>,
+}
+
+impl EventStream {
+    pub fn new(receiver: mpsc::Receiver<RuntimeEvent>) -> Self {
+        Self { receiver }
+    }
+
+    pub async fn next(&mut self) -> Option<RuntimeEvent> {
+        self.receiver.recv().await
+    }
+}
+
+/// An ephemeral install-script container.
+#[async_trait]
+pub trait InstallerProcess: Send + Sync {
+    fn stdout_path(&self) -> &Path;
+    fn stderr_path(&self) -> &Path;
+    /// Wait for the script to finish and return its exit status.
+    async fn wait(&self) -> AgentResult<i32>;
+    async fn cleanup(&self) -> AgentResult<()>;
+}
+
+/// Everything the agent needs from a container backend. `ContainerdRuntime` is the
+/// production implementation; the handler and managers only depend on this trait.
+#[async_trait]
+pub trait ContainerRuntime: Send + Sync {
+    // -- Lifecycle --
+    async fn create_container(&self, config: ContainerConfig<'_>) -> AgentResult<String>;
+    async fn start_container(&self, container_id: &str) -> AgentResult<()>;
+    async fn stop_container(&self, container_id: &str, timeout_secs: u64) -> AgentResult<()>;
+    async fn stop_container_with_signal(
+        &self,
+        container_id: &str,
+        signal: &str,
+        timeout_secs: u64,
+    ) -> AgentResult<()>;
+    async fn kill_container(&self, container_id: &str, signal: &str) -> AgentResult<()>;
+    async fn force_kill_container(&self, container_id: &str) -> AgentResult<()>;
+    async fn remove_container(&self, container_id: &str) -> AgentResult<()>;
+    async fn pause_container(&self, container_id: &str) -> AgentResult<()>;
+    async fn resume_container(&self, container_id: &str) -> AgentResult<()>;
+    async fn update_resources(
+        &self,
+        container_id: &str,
+        memory_mb: u64,
+        cpu_millicores: u64,
+        limits: &ResourceLimits,
+        data_dir: &Path,
+    ) -> AgentResult<()>;
+    async fn spawn_installer_container(
+        &self,
+        image: &str,
+        script: &str,
+        env: &HashMap<String, String>,
+        data_dir: &str,
+        uid: u32,
+        gid: u32,
+    ) -> AgentResult<Box<dyn InstallerProcess>>;
+
+    // -- Status --
+    async fn container_exists(&self, container_id: &str) -> bool;
+    async fn is_container_running(&self, container_id: &str) -> AgentResult<bool>;
+    async fn container_status(&self, container_id: &str) -> AgentResult<ContainerStatus>;
+    async fn get_container_exit_code(&self, container_id: &str) -> AgentResult<Option<i32>>;
+    async fn list_containers(&self) -> AgentResult<Vec<ContainerInfo>>;
+    async fn get_container_ip(&self, container_id: &str) -> AgentResult<String>;
+
+    // -- Console and logs --
+    async fn send_input(&self, container_id: &str, input: &str) -> AgentResult<()>;
+    async fn send_input_bytes(&self, container_id: &str, input: &[u8]) -> AgentResult<()>;
+    async fn resize_console(&self, container_id: &str, cols: u32, rows: u32) -> AgentResult<()>;
+    fn is_tty(&self, container_id: &str) -> bool;
+    async fn restore_console_writers(&self) -> AgentResult<()>;
+    async fn spawn_log_stream(&self, container_id: &str) -> AgentResult<LogStream>;
+    async fn get_logs(&self, container_id: &str, lines: Option<u32>) -> AgentResult<String>;
+
+    // -- Exec --
+    async fn start_interactive_exec(
+        &self,
+        container_id: &str,
+        args: &[String],
+        cols: u32,
+        rows: u32,
+    ) -> AgentResult<InteractiveExec>;
+    async fn resize_exec(
+        &self,
+        container_id: &str,
+        exec_id: &str,
+        cols: u32,
+        rows: u32,
+    ) -> AgentResult<()>;
+    async fn kill_exec(&self, container_id: &str, exec_id: &str, signal: &str) -> AgentResult<()>;
+    async fn wait_exec(&self, container_id: &str, exec_id: &str) -> AgentResult<i32>;
+    async fn cleanup_exec(&self, container_id: &str, exec_id: &str);
+
+    // -- Stats --
+    async fn get_stats(&self, container_id: &str) -> AgentResult<ContainerStats>;
+    async fn cpu_usage_usec(&self, container_id: &str) -> Option<u64>;
+    async fn memory_events(&self, container_id: &str) -> Option<MemoryEvents>;
+
+    // -- Events --
+    async fn subscribe_to_container_events(&self, container_id: &str) -> AgentResult<EventStream>;
+    async fn subscribe_to_all_events(&self) -> AgentResult<EventStream>;
+
+    // -- Images --
+    async fn pull_image(
+        &self,
+        image: &str,
+        credentials: &[RegistryCredentials],
+        pull_policy: PullPolicy,
+        progress: Option<mpsc::UnboundedSender<ImagePullProgress>>,
+    ) -> AgentResult<()>;
+    async fn image_names(&self) -> AgentResult<Vec<String>>;
+    async fn list_images(&self) -> AgentResult<Vec<ImageRecord>>;
+    async fn delete_image(&self, name: &str) -> AgentResult<()>;
+    async fn pulls_in_flight(&self) -> Vec<String>;
+    async fn orphaned_snapshots(&self, min_age: Duration) -> AgentResult<Vec<(String, u64)>>;
+    async fn remove_snapshot(&self, key: &str) -> AgentResult<()>;
+}
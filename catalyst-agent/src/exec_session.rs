@@ -0,0 +1,226 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use serde_json::{json, Value};
+use tokio::fs;
+use tokio::io::AsyncWriteExt;
+use tokio::net::unix::pipe;
+use tokio::sync::{Mutex, RwLock};
+use tracing::{info, warn};
+
+use crate::{AgentError, AgentResult, ContainerRuntime};
+
+const MAX_SESSIONS_PER_SERVER: usize = 4;
+
+/// Parameters for opening an interactive shell inside a server container
+pub struct ExecSessionRequest<'a> {
+    pub session_id: &'a str,
+    pub server_id: &'a str,
+    pub container_id: &'a str,
+    pub command: Vec<String>,
+    pub cols: u32,
+    pub rows: u32,
+    /// Identity of the staff member who opened the session, as reported by the backend.
+    pub actor: Option<&'a str>,
+}
+
+struct ExecSession {
+    server_id: String,
+    container_id: String,
+    exec_id: String,
+    actor: Option<String>,
+    command: Vec<String>,
+    stdin: Arc<Mutex<pipe::Sender>>,
+    started_at: chrono::DateTime<chrono::Utc>,
+    bytes_in: u64,
+}
+
+/// Tracks administrator exec sessions and writes an audit trail for each one.
+pub struct ExecSessionManager {
+    runtime: Arc<dyn ContainerRuntime>,
+    audit_log_path: PathBuf,
+    sessions: RwLock<HashMap<String, ExecSession>>,
+}
+
+impl ExecSessionManager {
+    pub fn new(runtime: Arc<dyn ContainerRuntime>, data_dir: PathBuf) -> Self {
+        Self {
+            runtime,
+            audit_log_path: data_dir.join("audit").join("exec_sessions.jsonl"),
+            sessions: RwLock::new(HashMap::new()),
+        }
+    }
+
+    /// Start the process and register the session. Returns the PTY output stream.
+    pub async fn open(&self, request: ExecSessionRequest<'_>) -> AgentResult<pipe::Receiver> {
+        {
+            let sessions = self.sessions.read().await;
+            if sessions.contains_key(request.session_id) {
+                return Err(AgentError::InvalidRequest(format!(
+                    "Exec session {} already exists",
+                    request.session_id
+                )));
+            }
+            let per_server = sessions
+                .values()
+                .filter(|session| session.server_id == request.server_id)
+                .count();
+            if per_server >= MAX_SESSIONS_PER_SERVER {
+                return Err(AgentError::InvalidRequest(format!(
+                    "Too many exec sessions for server {} (max {})",
+                    request.server_id, MAX_SESSIONS_PER_SERVER
+                )));
+            }
+        }
+
+        let exec = self
+            .runtime
+            .start_interactive_exec(
+                request.container_id,
+                &request.command,
+                request.cols,
+                request.rows,
+            )
+            .await?;
+
+        let session = ExecSession {
+            server_id: request.server_id.to_string(),
+            container_id: request.container_id.to_string(),
+            exec_id: exec.exec_id,
+            actor: request.actor.map(str::to_string),
+            command: request.command,
+            stdin: Arc::new(Mutex::new(exec.stdin)),
+            started_at: chrono::Utc::now(),
+            bytes_in: 0,
+        };
+
+        info!(
+            "Exec session {} opened on server {} by {}: {:?}",
+            request.session_id,
+            session.server_id,
+            session.actor.as_deref().unwrap_or("unknown"),
+            session.command
+        );
+        self.append_audit(json!({
+            "event": "exec_session_start",
+            "sessionId": request.session_id,
+            "serverId": session.server_id,
+            "containerId": session.container_id,
+            "actor": session.actor,
+            "command": session.command,
+            "timestamp": session.started_at.timestamp_millis(),
+        }))
+        .await;
+
+        self.sessions
+            .write()
+            .await
+            .insert(request.session_id.to_string(), session);
+        Ok(exec.stdout)
+    }
+
+    pub async fn write_input(&self, session_id: &str, data: &[u8]) -> AgentResult<()> {
+        let stdin = {
+            let mut sessions = self.sessions.write().await;
+            let session = sessions.get_mut(session_id).ok_or_else(|| {
+                AgentError::NotFound(format!("Exec session {} not found", session_id))
+            })?;
+            session.bytes_in += data.len() as u64;
+            session.stdin.clone()
+        };
+        let mut stdin = stdin.lock().await;
+        stdin
+            .write_all(data)
+            .await
+            .map_err(|e| AgentError::ContainerError(format!("exec stdin: {}", e)))
+    }
+
+    pub async fn resize(&self, session_id: &str, cols: u32, rows: u32) -> AgentResult<()> {
+        let (container_id, exec_id) = self.process_ids(session_id).await?;
+        self.runtime
+            .resize_exec(&container_id, &exec_id, cols, rows)
+            .await
+    }
+
+    /// Kill the session's process. The output pump observes the exit and calls `finish`.
+    pub async fn terminate(&self, session_id: &str) -> AgentResult<()> {
+        let (container_id, exec_id) = self.process_ids(session_id).await?;
+        self.runtime
+            .kill_exec(&container_id, &exec_id, "SIGKILL")
+            .await
+    }
+
+    pub async fn terminate_all(&self) {
+        let ids: Vec<String> = self.sessions.read().await.keys().cloned().collect();
+        for session_id in ids {
+            if let Err(e) = self.terminate(&session_id).await {
+                warn!("Failed to terminate exec session {}: {}", session_id, e);
+            }
+        }
+    }
+
+    pub async fn wait(&self, session_id: &str) -> AgentResult<i32> {
+        let (container_id, exec_id) = self.process_ids(session_id).await?;
+        self.runtime.wait_exec(&container_id, &exec_id).await
+    }
+
+    /// Drop the session, release its process and FIFOs, and record the outcome.
+    pub async fn finish(&self, session_id: &str, exit_code: Option<i32>, bytes_out: u64) {
+        let Some(session) = self.sessions.write().await.remove(session_id) else {
+            return;
+        };
+        self.runtime
+            .cleanup_exec(&session.container_id, &session.exec_id)
+            .await;
+
+        let ended_at = chrono::Utc::now();
+        info!(
+            "Exec session {} on server {} ended (exit code {:?})",
+            session_id, session.server_id, exit_code
+        );
+        self.append_audit(json!({
+            "event": "exec_session_end",
+            "sessionId": session_id,
+            "serverId": session.server_id,
+            "containerId": session.container_id,
+            "actor": session.actor,
+            "exitCode": exit_code,
+            "bytesIn": session.bytes_in,
+            "bytesOut": bytes_out,
+            "durationMs": (ended_at - session.started_at).num_milliseconds(),
+            "timestamp": ended_at.timestamp_millis(),
+        }))
+        .await;
+    }
+
+    async fn process_ids(&self, session_id: &str) -> AgentResult<(String, String)> {
+        self.sessions
+            .read()
+            .await
+            .get(session_id)
+            .map(|session| (session.container_id.clone(), session.exec_id.clone()))
+            .ok_or_else(|| AgentError::NotFound(format!("Exec session {} not found", session_id)))
+    }
+
+    async fn append_audit(&self, record: Value) {
+        if let Err(e) = self.write_audit_line(&record).await {
+            warn!("Failed to write exec session audit record: {}", e);
+        }
+    }
+
+    async fn write_audit_line(&self, record: &Value) -> AgentResult<()> {
+        if let Some(parent) = self.audit_log_path.parent() {
+            fs::create_dir_all(parent).await?;
+        }
+        let mut file = fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.audit_log_path)
+            .await?;
+        let mut line = record.to_string();
+        line.push('\n');
+        file.write_all(line.as_bytes()).await?;
+        Ok(())
+    }
+}
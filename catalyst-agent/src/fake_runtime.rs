@@ -0,0 +1,694 @@
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime};
+
+use async_trait::async_trait;
+use tokio::io::AsyncWriteExt;
+use tokio::sync::{mpsc, Notify};
+use tokio::task::{AbortHandle, JoinHandle};
+
+use crate::config::{PullPolicy, RegistryCredentials};
+use crate::container_runtime::{ContainerRuntime, EventStream, InstallerProcess, RuntimeEvent};
+use crate::runtime_manager::{
+    ContainerConfig, ContainerInfo, ContainerStats, ContainerStatus, ImagePullProgress,
+    ImageRecord, InteractiveExec, LogStream, MemoryEvents, ResourceLimits, CONSOLE_BASE_DIR,
+};
+use crate::{AgentError, AgentResult, ContainerdRuntime};
+
+/// In-process runtime for tests. Containers are plain records that only change through
+/// trait calls and the helpers below, so every run sees the same sequence of events.
+#[derive(Default)]
+pub struct FakeRuntime {
+    state: Mutex<FakeState>,
+    subscribed: Notify,
+}
+
+#[derive(Default)]
+struct FakeState {
+    containers: HashMap<String, FakeContainer>,
+    /// Pulled images and when they were pulled.
+    images: BTreeMap<String, SystemTime>,
+    /// Images whose pull the agent should consider still running.
+    pulls_in_flight: Vec<String>,
+    /// Trait calls that change state, e.g. `remove_container srv-1`.
+    calls: Vec<String>,
+    /// Console lines that make the server process exit with the given code.
+    exit_on_input: HashMap<String, i32>,
+    subscribers: Vec<(Option<String>, mpsc::Sender<RuntimeEvent>)>,
+    /// Progress every pull reports, and the error it then fails with.
+    pull_progress: Vec<ImagePullProgress>,
+    pull_error: Option<String>,
+    /// Echo task of each exec, until someone waits for it, and a handle to kill it.
+    execs: HashMap<String, JoinHandle<()>>,
+    exec_kills: HashMap<String, AbortHandle>,
+}
+
+struct FakeContainer {
+    image: String,
+    status: ContainerStatus,
+    exit_code: Option<i32>,
+    tty: bool,
+    stdin: Vec<u8>,
+    oom_kills: u64,
+}
+
+impl FakeContainer {
+    fn new(image: &str, tty: bool) -> Self {
+        Self {
+            image: image.to_string(),
+            status: ContainerStatus::Running,
+            exit_code: None,
+            tty,
+            stdin: Vec::new(),
+            oom_kills: 0,
+        }
+    }
+}
+
+impl FakeState {
+    fn container(&mut self, container_id: &str) -> AgentResult<&mut FakeContainer> {
+        self.containers
+            .get_mut(container_id)
+            .ok_or_else(|| AgentError::NotFound(format!("Container {}", container_id)))
+    }
+
+    fn emit(&mut self, topic: &str, container_id: &str) {
+        self.subscribers.retain(|(filter, sender)| {
+            if filter
+                .as_deref()
+                .is_some_and(|filter| filter != container_id)
+            {
+                return !sender.is_closed();
+            }
+            sender
+                .try_send(RuntimeEvent {
+                    topic: topic.to_string(),
+                    container_id: container_id.to_string(),
+                })
+                .is_ok()
+        });
+    }
+
+    fn set_status(&mut self, container_id: &str, status: ContainerStatus) -> AgentResult<()> {
+        self.container(container_id)?.status = status;
+        let topic = match status {
+            ContainerStatus::Created => "/tasks/create",
+            ContainerStatus::Running => "/tasks/start",
+            ContainerStatus::Paused => "/tasks/paused",
+            ContainerStatus::Stopped => "/tasks/exit",
+        };
+        self.emit(topic, container_id);
+        Ok(())
+    }
+
+    fn exit(&mut self, container_id: &str, code: i32) -> AgentResult<()> {
+        let container = self.container(container_id)?;
+        if container.status == ContainerStatus::Stopped {
+            return Ok(());
+        }
+        container.exit_code = Some(code);
+        self.set_status(container_id, ContainerStatus::Stopped)
+    }
+}
+
+/// Empty the console files of a container, as starting its task does.
+fn clear_console(container_id: &str) {
+    let dir = Path::new(CONSOLE_BASE_DIR).join(container_id);
+    for name in ["stdout", "stderr"] {
+        let path = dir.join(name);
+        if path.exists() {
+            let _ = std::fs::File::create(path);
+        }
+    }
+}
+
+/// Exit status of a process ended by `signal`, as a shell reports it.
+fn signal_exit_code(signal: &str) -> i32 {
+    let number = match signal.trim_start_matches("SIG") {
+        "INT" | "2" => 2,
+        "KILL" | "9" => 9,
+        _ => 15,
+    };
+    128 + number
+}
+
+impl FakeRuntime {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a running container, as if the agent had started it earlier.
+    pub fn insert_container(&self, container_id: &str, image: &str) {
+        self.state
+            .lock()
+            .unwrap()
+            .containers
+            .insert(container_id.to_string(), FakeContainer::new(image, false));
+    }
+
+    /// Add a running container whose console is a PTY.
+    pub fn insert_tty_container(&self, container_id: &str, image: &str) {
+        self.state
+            .lock()
+            .unwrap()
+            .containers
+            .insert(container_id.to_string(), FakeContainer::new(image, true));
+    }
+
+    /// Add an image, as if it had been pulled `age` ago.
+    pub fn insert_image(&self, image: &str, age: Duration) {
+        self.state
+            .lock()
+            .unwrap()
+            .images
+            .insert(image.to_string(), SystemTime::now() - age);
+    }
+
+    /// Report a pull of `image` as in flight.
+    pub fn start_pull(&self, image: &str) {
+        self.state
+            .lock()
+            .unwrap()
+            .pulls_in_flight
+            .push(image.to_string());
+    }
+
+    /// Make the server process exit with `code` when it reads `line` on its console.
+    pub fn exit_on_input(&self, line: &str, code: i32) {
+        self.state
+            .lock()
+            .unwrap()
+            .exit_on_input
+            .insert(line.to_string(), code);
+    }
+
+    /// Make pulls report `progress`, then fail with `error` if given.
+    pub fn script_pull(&self, progress: Vec<ImagePullProgress>, error: Option<&str>) {
+        let mut state = self.state.lock().unwrap();
+        state.pull_progress = progress;
+        state.pull_error = error.map(str::to_string);
+    }
+
+    /// End the server process on its own, as a crash would.
+    pub fn exit(&self, container_id: &str, code: i32) {
+        let _ = self.state.lock().unwrap().exit(container_id, code);
+    }
+
+    /// Stop and start the server between two polls of its status, as a quick restart does.
+    pub fn restart(&self, container_id: &str) {
+        let mut state = self.state.lock().unwrap();
+        if state.exit(container_id, 0).is_ok() {
+            clear_console(container_id);
+            let _ = state.set_status(container_id, ContainerStatus::Running);
+        }
+    }
+
+    /// Kill the server process the way the kernel OOM killer does.
+    pub fn oom_kill(&self, container_id: &str) {
+        let mut state = self.state.lock().unwrap();
+        if let Ok(container) = state.container(container_id) {
+            container.oom_kills += 1;
+            state.emit("/tasks/oom", container_id);
+            let _ = state.exit(container_id, signal_exit_code("SIGKILL"));
+        }
+    }
+
+    /// Everything written to the container's console so far.
+    pub fn stdin(&self, container_id: &str) -> String {
+        self.state
+            .lock()
+            .unwrap()
+            .containers
+            .get(container_id)
+            .map(|container| String::from_utf8_lossy(&container.stdin).into_owned())
+            .unwrap_or_default()
+    }
+
+    /// Append a line to the container's console output, as the server process would.
+    pub async fn print(&self, container_id: &str, line: &str) {
+        let dir = Path::new(CONSOLE_BASE_DIR).join(container_id);
+        tokio::fs::create_dir_all(&dir).await.unwrap();
+        let mut stdout = tokio::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(dir.join("stdout"))
+            .await
+            .unwrap();
+        stdout
+            .write_all(format!("{}\n", line).as_bytes())
+            .await
+            .unwrap();
+    }
+
+    pub fn calls(&self) -> Vec<String> {
+        self.state.lock().unwrap().calls.clone()
+    }
+
+    /// Wait until something subscribed to the events of `container_id`.
+    pub async fn wait_for_subscriber(&self, container_id: &str) {
+        loop {
+            let subscribed = self.subscribed.notified();
+            let found = self
+                .state
+                .lock()
+                .unwrap()
+                .subscribers
+                .iter()
+                .any(|(filter, sender)| {
+                    filter.as_deref() == Some(container_id) && !sender.is_closed()
+                });
+            if found {
+                return;
+            }
+            subscribed.await;
+        }
+    }
+
+    fn record(&self, call: &str, container_id: &str) -> std::sync::MutexGuard<'_, FakeState> {
+        let mut state = self.state.lock().unwrap();
+        state.calls.push(format!("{} {}", call, container_id));
+        state
+    }
+
+    fn subscribe(&self, filter: Option<&str>) -> EventStream {
+        let (sender, receiver) = mpsc::channel(64);
+        self.state
+            .lock()
+            .unwrap()
+            .subscribers
+            .push((filter.map(str::to_string), sender));
+        self.subscribed.notify_waiters();
+        EventStream::new(receiver)
+    }
+}
+
+/// Installer that finishes immediately with a fixed status and no output.
+struct FakeInstaller {
+    dir: PathBuf,
+    stdout_path: PathBuf,
+    stderr_path: PathBuf,
+}
+
+#[async_trait]
+impl InstallerProcess for FakeInstaller {
+    fn stdout_path(&self) -> &Path {
+        &self.stdout_path
+    }
+
+    fn stderr_path(&self) -> &Path {
+        &self.stderr_path
+    }
+
+    async fn wait(&self) -> AgentResult<i32> {
+        Ok(0)
+    }
+
+    async fn cleanup(&self) -> AgentResult<()> {
+        let _ = tokio::fs::remove_dir_all(&self.dir).await;
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl ContainerRuntime for FakeRuntime {
+    async fn create_container(&self, config: ContainerConfig<'_>) -> AgentResult<String> {
+        let mut state = self.record("create_container", config.container_id);
+        if state.containers.contains_key(config.container_id) {
+            return Err(AgentError::ContainerError(format!(
+                "Container {} already exists",
+                config.container_id
+            )));
+        }
+        state.containers.insert(
+            config.container_id.to_string(),
+            FakeContainer::new(config.image, config.tty),
+        );
+        state.emit("/tasks/start", config.container_id);
+        Ok(config.container_id.to_string())
+    }
+
+    async fn start_container(&self, container_id: &str) -> AgentResult<()> {
+        let mut state = self.record("start_container", container_id);
+        state.container(container_id)?.exit_code = None;
+        clear_console(container_id);
+        state.set_status(container_id, ContainerStatus::Running)
+    }
+
+    async fn stop_container(&self, container_id: &str, timeout_secs: u64) -> AgentResult<()> {
+        self.stop_container_with_signal(container_id, "SIGTERM", timeout_secs)
+            .await
+    }
+
+    async fn stop_container_with_signal(
+        &self,
+        container_id: &str,
+        signal: &str,
+        _timeout_secs: u64,
+    ) -> AgentResult<()> {
+        let mut state = self.record(&format!("stop_container {}", signal), container_id);
+        state.exit(container_id, signal_exit_code(signal))
+    }
+
+    async fn kill_container(&self, container_id: &str, signal: &str) -> AgentResult<()> {
+        let mut state = self.record(&format!("kill_container {}", signal), container_id);
+        state.exit(container_id, signal_exit_code(signal))
+    }
+
+    async fn force_kill_container(&self, container_id: &str) -> AgentResult<()> {
+        let mut state = self.record("force_kill_container", container_id);
+        state.exit(container_id, signal_exit_code("SIGKILL"))
+    }
+
+    async fn remove_container(&self, container_id: &str) -> AgentResult<()> {
+        let mut state = self.record("remove_container", container_id);
+        if state.container(container_id)?.status != ContainerStatus::Stopped {
+            return Err(AgentError::ContainerError(format!(
+                "Container {} is still running",
+                container_id
+            )));
+        }
+        state.containers.remove(container_id);
+        state.emit("/containers/delete", container_id);
+        Ok(())
+    }
+
+    async fn pause_container(&self, container_id: &str) -> AgentResult<()> {
+        let mut state = self.record("pause_container", container_id);
+        state.set_status(container_id, ContainerStatus::Paused)
+    }
+
+    async fn resume_container(&self, container_id: &str) -> AgentResult<()> {
+        let mut state = self.record("resume_container", container_id);
+        state.container(container_id)?.status = ContainerStatus::Running;
+        state.emit("/tasks/resumed", container_id);
+        Ok(())
+    }
+
+    async fn update_resources(
+        &self,
+        container_id: &str,
+        memory_mb: u64,
+        cpu_millicores: u64,
+        _limits: &ResourceLimits,
+        _data_dir: &Path,
+    ) -> AgentResult<()> {
+        let mut state = self.record(
+            &format!("update_resources {}MB {}m", memory_mb, cpu_millicores),
+            container_id,
+        );
+        state.container(container_id).map(|_| ())
+    }
+
+    async fn spawn_installer_container(
+        &self,
+        image: &str,
+        _script: &str,
+        _env: &HashMap<String, String>,
+        _data_dir: &str,
+        _uid: u32,
+        _gid: u32,
+    ) -> AgentResult<Box<dyn InstallerProcess>> {
+        drop(self.record("spawn_installer_container", image));
+        let dir = std::env::temp_dir().join(format!("catalyst-fake-{}", uuid::Uuid::new_v4()));
+        tokio::fs::create_dir_all(&dir).await?;
+        let stdout_path = dir.join("stdout");
+        let stderr_path = dir.join("stderr");
+        tokio::fs::write(&stdout_path, b"").await?;
+        tokio::fs::write(&stderr_path, b"").await?;
+        Ok(Box::new(FakeInstaller {
+            dir,
+            stdout_path,
+            stderr_path,
+        }))
+    }
+
+    async fn container_exists(&self, container_id: &str) -> bool {
+        self.state
+            .lock()
+            .unwrap()
+            .containers
+            .contains_key(container_id)
+    }
+
+    async fn is_container_running(&self, container_id: &str) -> AgentResult<bool> {
+        Ok(self.container_status(container_id).await? == ContainerStatus::Running)
+    }
+
+    async fn container_status(&self, container_id: &str) -> AgentResult<ContainerStatus> {
+        Ok(self.state.lock().unwrap().container(container_id)?.status)
+    }
+
+    async fn get_container_exit_code(&self, container_id: &str) -> AgentResult<Option<i32>> {
+        Ok(self
+            .state
+            .lock()
+            .unwrap()
+            .container(container_id)?
+            .exit_code)
+    }
+
+    async fn list_containers(&self) -> AgentResult<Vec<ContainerInfo>> {
+        let state = self.state.lock().unwrap();
+        let mut containers: Vec<ContainerInfo> = state
+            .containers
+            .iter()
+            .map(|(id, container)| ContainerInfo {
+                id: id.clone(),
+                names: id.clone(),
+                managed: true,
+                status: container.status,
+                exit_code: container.exit_code,
+                started_at: None,
+                command: String::new(),
+                image: container.image.clone(),
+            })
+            .collect();
+        containers.sort_by(|a, b| a.id.cmp(&b.id));
+        Ok(containers)
+    }
+
+    async fn get_container_ip(&self, container_id: &str) -> AgentResult<String> {
+        self.state.lock().unwrap().container(container_id)?;
+        Ok("127.0.0.1".to_string())
+    }
+
+    async fn send_input(&self, container_id: &str, input: &str) -> AgentResult<()> {
+        self.send_input_bytes(container_id, input.as_bytes()).await
+    }
+
+    async fn send_input_bytes(&self, container_id: &str, input: &[u8]) -> AgentResult<()> {
+        let mut state = self.state.lock().unwrap();
+        let container = state.container(container_id)?;
+        if container.status != ContainerStatus::Running {
+            return Err(AgentError::ContainerError(format!(
+                "Container {} is not running",
+                container_id
+            )));
+        }
+        container.stdin.extend_from_slice(input);
+        let line = String::from_utf8_lossy(input);
+        let exit_code = state.exit_on_input.get(line.trim_end()).copied();
+        if let Some(code) = exit_code {
+            state.exit(container_id, code)?;
+        }
+        Ok(())
+    }
+
+    async fn resize_console(&self, container_id: &str, cols: u32, rows: u32) -> AgentResult<()> {
+        self.record(&format!("resize_console {}x{}", cols, rows), container_id)
+            .container(container_id)
+            .map(|_| ())
+    }
+
+    fn is_tty(&self, container_id: &str) -> bool {
+        self.state
+            .lock()
+            .unwrap()
+            .containers
+            .get(container_id)
+            .is_some_and(|container| container.tty)
+    }
+
+    async fn restore_console_writers(&self) -> AgentResult<()> {
+        Ok(())
+    }
+
+    async fn spawn_log_stream(&self, container_id: &str) -> AgentResult<LogStream> {
+        self.state.lock().unwrap().container(container_id)?;
+        let dir = Path::new(CONSOLE_BASE_DIR).join(container_id);
+        Ok(LogStream::new(
+            container_id,
+            tokio::fs::File::open(dir.join("stdout")).await.ok(),
+            tokio::fs::File::open(dir.join("stderr")).await.ok(),
+        ))
+    }
+
+    async fn get_logs(&self, container_id: &str, _lines: Option<u32>) -> AgentResult<String> {
+        self.state.lock().unwrap().container(container_id)?;
+        Ok(String::new())
+    }
+
+    /// Every exec behaves like `cat`: it echoes its input until stdin is closed.
+    async fn start_interactive_exec(
+        &self,
+        container_id: &str,
+        _args: &[String],
+        _cols: u32,
+        _rows: u32,
+    ) -> AgentResult<InteractiveExec> {
+        let (stdin, mut input) = tokio::net::unix::pipe::pipe()?;
+        let (mut output, stdout) = tokio::net::unix::pipe::pipe()?;
+        let exec_id = format!("exec-{}", uuid::Uuid::new_v4());
+        let echo = tokio::spawn(async move {
+            let _ = tokio::io::copy(&mut input, &mut output).await;
+            let _ = output.shutdown().await;
+        });
+        let mut state = self.record("start_interactive_exec", container_id);
+        state.container(container_id)?;
+        state
+            .exec_kills
+            .insert(exec_id.clone(), echo.abort_handle());
+        state.execs.insert(exec_id.clone(), echo);
+        Ok(InteractiveExec {
+            exec_id,
+            stdin,
+            stdout,
+        })
+    }
+
+    async fn resize_exec(
+        &self,
+        container_id: &str,
+        _exec_id: &str,
+        cols: u32,
+        rows: u32,
+    ) -> AgentResult<()> {
+        drop(self.record(&format!("resize_exec {}x{}", cols, rows), container_id));
+        Ok(())
+    }
+
+    async fn kill_exec(
+        &self,
+        _container_id: &str,
+        exec_id: &str,
+        _signal: &str,
+    ) -> AgentResult<()> {
+        if let Some(kill) = self.state.lock().unwrap().exec_kills.get(exec_id) {
+            kill.abort();
+        }
+        Ok(())
+    }
+
+    async fn wait_exec(&self, _container_id: &str, exec_id: &str) -> AgentResult<i32> {
+        let echo = self
+            .state
+            .lock()
+            .unwrap()
+            .execs
+            .remove(exec_id)
+            .ok_or_else(|| AgentError::NotFound(format!("Exec {}", exec_id)))?;
+        match echo.await {
+            Ok(()) => Ok(0),
+            Err(_) => Ok(signal_exit_code("SIGKILL")),
+        }
+    }
+
+    async fn cleanup_exec(&self, _container_id: &str, exec_id: &str) {
+        let mut state = self.state.lock().unwrap();
+        state.execs.remove(exec_id);
+        if let Some(kill) = state.exec_kills.remove(exec_id) {
+            kill.abort();
+        }
+    }
+
+    async fn get_stats(&self, container_id: &str) -> AgentResult<ContainerStats> {
+        self.state.lock().unwrap().container(container_id)?;
+        Ok(ContainerStats {
+            container_id: container_id.to_string(),
+            ..ContainerStats::default()
+        })
+    }
+
+    async fn cpu_usage_usec(&self, _container_id: &str) -> Option<u64> {
+        None
+    }
+
+    async fn memory_events(&self, container_id: &str) -> Option<MemoryEvents> {
+        let state = self.state.lock().unwrap();
+        state
+            .containers
+            .get(container_id)
+            .map(|container| MemoryEvents {
+                oom_kills: container.oom_kills,
+                ..MemoryEvents::default()
+            })
+    }
+
+    async fn subscribe_to_container_events(&self, container_id: &str) -> AgentResult<EventStream> {
+        Ok(self.subscribe(Some(container_id)))
+    }
+
+    async fn subscribe_to_all_events(&self) -> AgentResult<EventStream> {
+        Ok(self.subscribe(None))
+    }
+
+    async fn pull_image(
+        &self,
+        image: &str,
+        _credentials: &[RegistryCredentials],
+        _pull_policy: PullPolicy,
+        progress: Option<mpsc::UnboundedSender<ImagePullProgress>>,
+    ) -> AgentResult<()> {
+        let image = ContainerdRuntime::qualify_image_ref(image);
+        let mut state = self.record("pull_image", &image);
+        if let Some(progress) = progress {
+            for update in &state.pull_progress {
+                let _ = progress.send(update.clone());
+            }
+        }
+        if let Some(error) = &state.pull_error {
+            return Err(AgentError::ContainerError(error.clone()));
+        }
+        state.images.insert(image, SystemTime::now());
+        Ok(())
+    }
+
+    async fn image_names(&self) -> AgentResult<Vec<String>> {
+        Ok(self.state.lock().unwrap().images.keys().cloned().collect())
+    }
+
+    async fn list_images(&self) -> AgentResult<Vec<ImageRecord>> {
+        Ok(self
+            .state
+            .lock()
+            .unwrap()
+            .images
+            .iter()
+            .map(|(name, pulled_at)| ImageRecord {
+                name: name.clone(),
+                updated_at: Some(*pulled_at),
+                blobs: Vec::new(),
+            })
+            .collect())
+    }
+
+    async fn delete_image(&self, name: &str) -> AgentResult<()> {
+        let mut state = self.record("delete_image", name);
+        state.images.remove(name);
+        Ok(())
+    }
+
+    async fn pulls_in_flight(&self) -> Vec<String> {
+        self.state.lock().unwrap().pulls_in_flight.clone()
+    }
+
+    async fn orphaned_snapshots(&self, _min_age: Duration) -> AgentResult<Vec<(String, u64)>> {
+        Ok(Vec::new())
+    }
+
+    async fn remove_snapshot(&self, key: &str) -> AgentResult<()> {
+        drop(self.record("remove_snapshot", key));
+        Ok(())
+    }
+}
@@ -1,19 +1,77 @@
-use std::os::unix::fs::PermissionsExt;
-use std::path::PathBuf;
+use std::collections::HashMap;
+use std::os::unix::fs::{MetadataExt, PermissionsExt};
+use std::path::{Path, PathBuf};
 use tokio::fs;
 use tracing::{debug, info, warn};
 
+use crate::security_profile::{DEFAULT_CONTAINER_GID, DEFAULT_CONTAINER_UID};
 use crate::{AgentError, AgentResult};
 
 const MAX_FILE_SIZE: u64 = 100 * 1024 * 1024; // 100MB
 
 pub struct FileManager {
     data_dir: PathBuf,
+    /// Host uid and gid of each server's files, as of its last install or start.
+    owners: std::sync::RwLock<HashMap<String, (u32, u32)>>,
 }
 
 impl FileManager {
     pub fn new(data_dir: PathBuf) -> Self {
-        Self { data_dir }
+        Self {
+            data_dir,
+            owners: std::sync::RwLock::new(HashMap::new()),
+        }
+    }
+
+    /// Hand files the agent creates for a server to `owner` instead of root.
+    pub fn set_owner(&self, server_id: &str, owner: (u32, u32)) {
+        if let Ok(mut owners) = self.owners.write() {
+            owners.insert(server_id.to_string(), owner);
+        }
+    }
+
+    /// The server's registered owner. Until the server is installed or started again
+    /// after an agent restart, the owner of its directory stands in, unless that is root.
+    fn owner(&self, server_id: &str) -> (u32, u32) {
+        if let Some(owner) = self
+            .owners
+            .read()
+            .ok()
+            .and_then(|owners| owners.get(server_id).copied())
+        {
+            return owner;
+        }
+        match std::fs::metadata(self.data_dir.join(server_id)) {
+            Ok(metadata) if metadata.uid() != 0 => (metadata.uid(), metadata.gid()),
+            _ => (DEFAULT_CONTAINER_UID, DEFAULT_CONTAINER_GID),
+        }
+    }
+
+    /// Give a path the agent just created to the server's owner.
+    pub async fn apply_owner(&self, server_id: &str, path: &Path) -> AgentResult<()> {
+        let (uid, gid) = self.owner(server_id);
+        let path = path.to_path_buf();
+        tokio::task::spawn_blocking(move || std::os::unix::fs::lchown(&path, Some(uid), Some(gid)))
+            .await
+            .map_err(|e| AgentError::InternalError(format!("chown task failed: {}", e)))?
+            .map_err(|e| AgentError::FileSystemError(format!("Failed to set owner: {}", e)))
+    }
+
+    /// `create_dir_all`, handing each directory it creates to the server's owner.
+    async fn create_dirs(&self, server_id: &str, dir: &Path) -> AgentResult<()> {
+        let mut missing = Vec::new();
+        let mut current = Some(dir);
+        while let Some(path) = current.filter(|path| !path.exists()) {
+            missing.push(path.to_path_buf());
+            current = path.parent();
+        }
+        fs::create_dir_all(dir)
+            .await
+            .map_err(|e| AgentError::FileSystemError(format!("Failed to create dir: {}", e)))?;
+        for path in missing.iter().rev() {
+            self.apply_owner(server_id, path).await?;
+        }
+        Ok(())
     }
 
     /// Validate and resolve a path within the container's data directory
@@ -92,13 +150,43 @@ impl FileManager {
     ) -> AgentResult<std::path::PathBuf> {
         let full_path = self.resolve_path(server_id, path)?;
         if let Some(parent) = full_path.parent() {
-            fs::create_dir_all(parent)
-                .await
-                .map_err(|e| AgentError::FileSystemError(format!("Failed to create dir: {}", e)))?;
+            self.create_dirs(server_id, parent).await?;
         }
         Ok(full_path)
     }
 
+    /// Give everything under `path` to the server's owner, after an extraction or restore
+    /// wrote it as root.
+    pub async fn apply_owner_recursive(&self, server_id: &str, path: &Path) -> AgentResult<()> {
+        let owner = self.owner(server_id);
+        let path = path.to_path_buf();
+        tokio::task::spawn_blocking(move || repair_tree(&path, owner, false))
+            .await
+            .map_err(|e| AgentError::InternalError(format!("chown task failed: {}", e)))?
+            .map_err(|e| AgentError::FileSystemError(format!("Failed to set owner: {}", e)))?;
+        Ok(())
+    }
+
+    /// Recursively give a server's files to `owner` and make them readable and writable
+    /// by it: directories get `u+rwx`, files `u+rw` without setuid and setgid. Returns
+    /// the number of entries changed.
+    pub async fn fix_permissions(&self, server_id: &str, owner: (u32, u32)) -> AgentResult<u64> {
+        let root = self.resolve_path(server_id, "/")?;
+        self.set_owner(server_id, owner);
+        info!(
+            "Repairing permissions of {:?} for {}:{}",
+            root, owner.0, owner.1
+        );
+        let changed = tokio::task::spawn_blocking(move || repair_tree(&root, owner, true))
+            .await
+            .map_err(|e| AgentError::InternalError(format!("Repair task failed: {}", e)))?
+            .map_err(|e| {
+                AgentError::FileSystemError(format!("Failed to repair permissions: {}", e))
+            })?;
+        info!("Repaired {} entries for server {}", changed, server_id);
+        Ok(changed)
+    }
+
     pub async fn read_file(&self, server_id: &str, path: &str) -> AgentResult<Vec<u8>> {
         let full_path = self.resolve_path(server_id, path)?;
 
@@ -137,9 +225,7 @@ impl FileManager {
 
         // Create parent directories if needed
         if let Some(parent) = full_path.parent() {
-            fs::create_dir_all(parent)
-                .await
-                .map_err(|e| AgentError::FileSystemError(format!("Failed to create dir: {}", e)))?;
+            self.create_dirs(server_id, parent).await?;
         }
 
         // Check size limit before writing
@@ -154,6 +240,7 @@ impl FileManager {
         fs::write(&full_path, data.as_bytes())
             .await
             .map_err(|e| AgentError::FileSystemError(format!("Failed to write file: {}", e)))?;
+        self.apply_owner(server_id, &full_path).await?;
 
         info!("File written successfully: {:?}", full_path);
 
@@ -187,9 +274,7 @@ impl FileManager {
         debug!("Renaming {:?} -> {:?}", from_path, to_path);
 
         if let Some(parent) = to_path.parent() {
-            fs::create_dir_all(parent)
-                .await
-                .map_err(|e| AgentError::FileSystemError(format!("Failed to create dir: {}", e)))?;
+            self.create_dirs(server_id, parent).await?;
         }
 
         fs::rename(&from_path, &to_path)
@@ -279,20 +364,17 @@ impl FileManager {
         debug!("Creating entry: {:?} (dir={})", full_path, is_directory);
 
         if is_directory {
-            fs::create_dir_all(&full_path)
-                .await
-                .map_err(|e| AgentError::FileSystemError(format!("Failed to create dir: {}", e)))?;
+            self.create_dirs(server_id, &full_path).await?;
         } else {
             if let Some(parent) = full_path.parent() {
-                fs::create_dir_all(parent).await.map_err(|e| {
-                    AgentError::FileSystemError(format!("Failed to create parent dir: {}", e))
-                })?;
+                self.create_dirs(server_id, parent).await?;
             }
             fs::write(&full_path, content.as_bytes())
                 .await
                 .map_err(|e| {
                     AgentError::FileSystemError(format!("Failed to create file: {}", e))
                 })?;
+            self.apply_owner(server_id, &full_path).await?;
         }
 
         info!("Entry created: {:?}", full_path);
@@ -322,14 +404,13 @@ impl FileManager {
         }
 
         if let Some(parent) = full_path.parent() {
-            fs::create_dir_all(parent)
-                .await
-                .map_err(|e| AgentError::FileSystemError(format!("Failed to create dir: {}", e)))?;
+            self.create_dirs(server_id, parent).await?;
         }
 
         fs::write(&full_path, data)
             .await
             .map_err(|e| AgentError::FileSystemError(format!("Failed to write file: {}", e)))?;
+        self.apply_owner(server_id, &full_path).await?;
 
         info!("File bytes written: {:?} ({} bytes)", full_path, data.len());
         Ok(())
@@ -366,9 +447,7 @@ impl FileManager {
         debug!("Compressing to {:?}", archive_full);
 
         if let Some(parent) = archive_full.parent() {
-            fs::create_dir_all(parent)
-                .await
-                .map_err(|e| AgentError::FileSystemError(format!("Failed to create dir: {}", e)))?;
+            self.create_dirs(server_id, parent).await?;
         }
 
         // Resolve each source path relative to server base
@@ -422,6 +501,8 @@ impl FileManager {
             }
         }
 
+        self.apply_owner(server_id, &archive_full).await?;
+
         info!("Archive created: {:?}", archive_full);
         Ok(())
     }
@@ -438,9 +519,7 @@ impl FileManager {
 
         debug!("Decompressing {:?} to {:?}", archive_full, target_full);
 
-        fs::create_dir_all(&target_full).await.map_err(|e| {
-            AgentError::FileSystemError(format!("Failed to create target dir: {}", e))
-        })?;
+        self.create_dirs(server_id, &target_full).await?;
 
         let archive_lower = archive_path.to_lowercase();
         if archive_lower.ends_with(".zip") {
@@ -486,6 +565,7 @@ impl FileManager {
         // pointing outside the server directory (e.g., to /etc/cron.d).
         self.validate_extracted_symlinks(&target_full, server_id)
             .await?;
+        self.apply_owner_recursive(server_id, &target_full).await?;
 
         info!(
             "Archive decompressed: {:?} -> {:?}",
@@ -720,6 +800,57 @@ impl FileManager {
     }
 }
 
+/// Walk `root` without leaving its filesystem or following symlinks, giving every entry
+/// to `owner` and, with `repair_modes`, the owner access described at `fix_permissions`.
+/// Entries removed during the walk are skipped.
+fn repair_tree(root: &Path, (uid, gid): (u32, u32), repair_modes: bool) -> std::io::Result<u64> {
+    let device = std::fs::symlink_metadata(root)?.dev();
+    let mut changed = 0;
+    let mut pending = vec![root.to_path_buf()];
+    while let Some(path) = pending.pop() {
+        let metadata = match std::fs::symlink_metadata(&path) {
+            Ok(metadata) => metadata,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => continue,
+            Err(e) => return Err(e),
+        };
+        if metadata.dev() != device {
+            continue;
+        }
+
+        let mut touched = false;
+        if (metadata.uid(), metadata.gid()) != (uid, gid) {
+            std::os::unix::fs::lchown(&path, Some(uid), Some(gid))?;
+            touched = true;
+        }
+        if repair_modes && !metadata.file_type().is_symlink() {
+            let mode = metadata.mode() & 0o7777;
+            let wanted = if metadata.is_dir() {
+                mode | 0o700
+            } else {
+                (mode | 0o600) & !0o6000
+            };
+            if wanted != mode {
+                std::fs::set_permissions(&path, std::fs::Permissions::from_mode(wanted))?;
+                touched = true;
+            }
+        }
+        if touched {
+            changed += 1;
+        }
+
+        if metadata.is_dir() {
+            match std::fs::read_dir(&path) {
+                Ok(entries) => {
+                    pending.extend(entries.filter_map(|entry| entry.ok()).map(|e| e.path()))
+                }
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+                Err(e) => return Err(e),
+            }
+        }
+    }
+    Ok(changed)
+}
+
 #[derive(serde::Serialize, serde::Deserialize, Debug)]
 pub struct FileEntry {
     pub name: String,
@@ -736,3 +867,34 @@ pub struct ArchiveEntry {
     pub is_dir: bool,
     pub modified: Option<String>,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn repairs_owner_access() {
+        let root = std::env::temp_dir().join(format!("catalyst-repair-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(root.join("plugins")).unwrap();
+        std::fs::write(root.join("plugins/config.yml"), "a: 1").unwrap();
+        std::fs::write(root.join("start.sh"), "#!/bin/sh").unwrap();
+        let mode = |path: &str| std::fs::metadata(root.join(path)).unwrap().mode() & 0o7777;
+        let set_mode = |path: &str, mode: u32| {
+            std::fs::set_permissions(root.join(path), std::fs::Permissions::from_mode(mode))
+                .unwrap()
+        };
+        set_mode("plugins/config.yml", 0o044);
+        set_mode("start.sh", 0o4755);
+        set_mode("plugins", 0o555);
+        let metadata = std::fs::metadata(&root).unwrap();
+        let owner = (metadata.uid(), metadata.gid());
+
+        assert_eq!(repair_tree(&root, owner, true).unwrap(), 3);
+        assert_eq!(mode("plugins"), 0o755);
+        assert_eq!(mode("plugins/config.yml"), 0o644);
+        assert_eq!(mode("start.sh"), 0o755);
+        assert_eq!(repair_tree(&root, owner, true).unwrap(), 0);
+
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+}
@@ -796,6 +796,11 @@ async fn handle_install_url(ctx: &TunnelCtx<'_>, fm: &FileManager, req: &TunnelR
             send_json_response(ctx, false, None, Some(format!("Write failed: {}", e))).await;
             return;
         }
+        drop(file);
+        if let Err(e) = fm.apply_owner(&req.server_uuid, &target_path).await {
+            send_json_response(ctx, false, None, Some(e.to_string())).await;
+            return;
+        }
 
         send_json_response(ctx, true, None, None).await;
         return;
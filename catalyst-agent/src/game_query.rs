@@ -0,0 +1,465 @@
+use std::collections::HashMap;
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use serde_json::Value;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpStream, UdpSocket};
+use tokio::sync::RwLock;
+use tokio::time::timeout;
+use tracing::debug;
+
+use crate::{AgentError, AgentResult, ContainerRuntime};
+
+const QUERY_TIMEOUT: Duration = Duration::from_secs(3);
+const MAX_SLP_RESPONSE_BYTES: usize = 256 * 1024;
+const A2S_INFO_REQUEST: &[u8] = b"\xFF\xFF\xFF\xFFTSource Engine Query\0";
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum QueryProtocol {
+    /// Minecraft Java Edition Server List Ping
+    Minecraft,
+    /// Source engine A2S_INFO
+    Source,
+    /// Plain TCP connect
+    Tcp,
+    /// UDP datagram that only fails on ICMP port unreachable
+    Udp,
+}
+
+impl QueryProtocol {
+    fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "minecraft" | "slp" => Some(Self::Minecraft),
+            "source" | "a2s" => Some(Self::Source),
+            "tcp" => Some(Self::Tcp),
+            "udp" => Some(Self::Udp),
+            _ => None,
+        }
+    }
+}
+
+/// How to probe a server, from the template's `features.queryProtocol`
+/// (`minecraft`, `source`, `tcp`, `udp` or `none`) and optional `features.queryPort`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct QueryTarget {
+    pub protocol: QueryProtocol,
+    pub port: u16,
+}
+
+impl QueryTarget {
+    /// Returns `None` when the template does not ask for queries. There is no default
+    /// protocol: a TCP probe never succeeds against UDP-only games.
+    pub fn from_template(template: &Value, primary_port: u16) -> AgentResult<Option<Self>> {
+        let features = template.get("features");
+        let protocol = match features
+            .and_then(|features| features.get("queryProtocol"))
+            .and_then(Value::as_str)
+        {
+            None => return Ok(None),
+            Some(value) if value.eq_ignore_ascii_case("none") => return Ok(None),
+            Some(value) => QueryProtocol::parse(value).ok_or_else(|| {
+                AgentError::InvalidRequest(format!("Unknown queryProtocol '{}'", value))
+            })?,
+        };
+        let port = features
+            .and_then(|features| features.get("queryPort"))
+            .and_then(Value::as_u64)
+            .filter(|port| (1..=u16::MAX as u64).contains(port))
+            .map(|port| port as u16)
+            .unwrap_or(primary_port);
+        Ok(Some(Self { protocol, port }))
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct QueryInfo {
+    pub players: Option<u32>,
+    pub max_players: Option<u32>,
+    pub motd: Option<String>,
+    pub map: Option<String>,
+    pub latency_ms: Option<u64>,
+}
+
+/// Latest probe outcome for a server.
+#[derive(Clone, Debug, Default)]
+pub struct QueryStatus {
+    pub online: bool,
+    pub info: QueryInfo,
+    pub error: Option<String>,
+    /// Probes failed in a row; a live process with a growing count is likely hung.
+    pub consecutive_failures: u32,
+    pub checked_at: i64,
+}
+
+pub async fn query(protocol: QueryProtocol, addr: SocketAddr) -> AgentResult<QueryInfo> {
+    timeout(QUERY_TIMEOUT, async {
+        match protocol {
+            QueryProtocol::Minecraft => query_minecraft(addr).await,
+            QueryProtocol::Source => query_source(addr).await,
+            QueryProtocol::Tcp => probe_tcp(addr).await,
+            QueryProtocol::Udp => probe_udp(addr).await,
+        }
+    })
+    .await
+    .unwrap_or_else(|_| {
+        Err(AgentError::NetworkError(format!(
+            "Query of {} timed out",
+            addr
+        )))
+    })
+}
+
+async fn probe_tcp(addr: SocketAddr) -> AgentResult<QueryInfo> {
+    let started = Instant::now();
+    TcpStream::connect(addr)
+        .await
+        .map_err(|e| AgentError::NetworkError(format!("TCP probe of {}: {}", addr, e)))?;
+    Ok(QueryInfo {
+        latency_ms: Some(started.elapsed().as_millis() as u64),
+        ..QueryInfo::default()
+    })
+}
+
+async fn probe_udp(addr: SocketAddr) -> AgentResult<QueryInfo> {
+    let socket = bind_udp(addr).await?;
+    let started = Instant::now();
+    socket.send(&[0]).await?;
+    let mut buffer = [0u8; 64];
+    // Silence means open or filtered; only an ICMP unreachable (refused) is a failure.
+    match timeout(Duration::from_millis(750), socket.recv(&mut buffer)).await {
+        Ok(Ok(_)) => Ok(QueryInfo {
+            latency_ms: Some(started.elapsed().as_millis() as u64),
+            ..QueryInfo::default()
+        }),
+        Ok(Err(e)) => Err(AgentError::NetworkError(format!(
+            "UDP probe of {}: {}",
+            addr, e
+        ))),
+        Err(_) => Ok(QueryInfo::default()),
+    }
+}
+
+async fn bind_udp(addr: SocketAddr) -> AgentResult<UdpSocket> {
+    let local: SocketAddr = if addr.is_ipv4() {
+        (Ipv4Addr::UNSPECIFIED, 0).into()
+    } else {
+        (std::net::Ipv6Addr::UNSPECIFIED, 0).into()
+    };
+    let socket = UdpSocket::bind(local).await?;
+    socket.connect(addr).await?;
+    Ok(socket)
+}
+
+async fn query_source(addr: SocketAddr) -> AgentResult<QueryInfo> {
+    let socket = bind_udp(addr).await?;
+    let started = Instant::now();
+    socket.send(A2S_INFO_REQUEST).await?;
+    let mut buffer = vec![0u8; 1400];
+    let mut read = socket.recv(&mut buffer).await?;
+
+    // Newer servers answer with a challenge that must be echoed back.
+    if read >= 9 && buffer[..5] == [0xFF, 0xFF, 0xFF, 0xFF, b'A'] {
+        let mut request = A2S_INFO_REQUEST.to_vec();
+        request.extend_from_slice(&buffer[5..9]);
+        socket.send(&request).await?;
+        read = socket.recv(&mut buffer).await?;
+    }
+
+    let mut info = parse_a2s_info(&buffer[..read])?;
+    info.latency_ms = Some(started.elapsed().as_millis() as u64);
+    Ok(info)
+}
+
+fn parse_a2s_info(packet: &[u8]) -> AgentResult<QueryInfo> {
+    let invalid = || AgentError::NetworkError("Malformed A2S_INFO response".to_string());
+    if packet.len() < 6 || packet[..4] != [0xFF, 0xFF, 0xFF, 0xFF] || packet[4] != b'I' {
+        return Err(invalid());
+    }
+    let mut rest = &packet[6..];
+    let mut read_cstr = || -> AgentResult<String> {
+        let end = rest.iter().position(|b| *b == 0).ok_or_else(invalid)?;
+        let value = String::from_utf8_lossy(&rest[..end]).into_owned();
+        rest = &rest[end + 1..];
+        Ok(value)
+    };
+    let name = read_cstr()?;
+    let map = read_cstr()?;
+    let _folder = read_cstr()?;
+    let _game = read_cstr()?;
+    // app id (u16), then players, max players and bots
+    if rest.len() < 5 {
+        return Err(invalid());
+    }
+    Ok(QueryInfo {
+        players: Some(rest[2] as u32),
+        max_players: Some(rest[3] as u32),
+        motd: Some(name),
+        map: Some(map),
+        latency_ms: None,
+    })
+}
+
+async fn query_minecraft(addr: SocketAddr) -> AgentResult<QueryInfo> {
+    let mut stream = TcpStream::connect(addr).await?;
+
+    let mut handshake = Vec::new();
+    write_varint(&mut handshake, 0x00);
+    write_varint(&mut handshake, -1);
+    let host = addr.ip().to_string();
+    write_varint(&mut handshake, host.len() as i32);
+    handshake.extend_from_slice(host.as_bytes());
+    handshake.extend_from_slice(&addr.port().to_be_bytes());
+    write_varint(&mut handshake, 1);
+    let mut frame = Vec::new();
+    write_varint(&mut frame, handshake.len() as i32);
+    frame.extend_from_slice(&handshake);
+    // Status request: length 1, packet id 0
+    frame.extend_from_slice(&[0x01, 0x00]);
+    stream.write_all(&frame).await?;
+
+    let length = read_varint_async(&mut stream).await?;
+    if length <= 0 || length as usize > MAX_SLP_RESPONSE_BYTES {
+        return Err(AgentError::NetworkError(format!(
+            "Invalid status response length {}",
+            length
+        )));
+    }
+    let mut payload = vec![0u8; length as usize];
+    stream.read_exact(&mut payload).await?;
+    let mut cursor = payload.as_slice();
+    let packet_id = read_varint(&mut cursor)?;
+    let json_len = read_varint(&mut cursor)?;
+    if packet_id != 0 || json_len < 0 || json_len as usize > cursor.len() {
+        return Err(AgentError::NetworkError(
+            "Malformed status response".to_string(),
+        ));
+    }
+    let status: Value = serde_json::from_slice(&cursor[..json_len as usize])?;
+
+    // Ping/pong round trip for latency
+    let started = Instant::now();
+    let mut ping = vec![0x09, 0x01];
+    ping.extend_from_slice(&chrono::Utc::now().timestamp_millis().to_be_bytes());
+    stream.write_all(&ping).await?;
+    let mut pong = [0u8; 10];
+    let latency_ms = match stream.read_exact(&mut pong).await {
+        Ok(_) => Some(started.elapsed().as_millis() as u64),
+        Err(e) => {
+            debug!("Minecraft ping to {} failed: {}", addr, e);
+            None
+        }
+    };
+
+    Ok(QueryInfo {
+        players: status["players"]["online"].as_u64().map(|v| v as u32),
+        max_players: status["players"]["max"].as_u64().map(|v| v as u32),
+        motd: Some(minecraft_text(&status["description"])).filter(|motd| !motd.is_empty()),
+        map: None,
+        latency_ms,
+    })
+}
+
+/// Flatten a Minecraft chat component (string, object with `text`/`extra`, or array).
+fn minecraft_text(component: &Value) -> String {
+    match component {
+        Value::String(text) => text.clone(),
+        Value::Array(parts) => parts.iter().map(minecraft_text).collect(),
+        Value::Object(object) => {
+            let mut text = object
+                .get("text")
+                .and_then(Value::as_str)
+                .unwrap_or_default()
+                .to_string();
+            if let Some(extra) = object.get("extra") {
+                text.push_str(&minecraft_text(extra));
+            }
+            text
+        }
+        _ => String::new(),
+    }
+}
+
+fn write_varint(out: &mut Vec<u8>, value: i32) {
+    let mut value = value as u32;
+    loop {
+        if value & !0x7F == 0 {
+            out.push(value as u8);
+            return;
+        }
+        out.push((value & 0x7F) as u8 | 0x80);
+        value >>= 7;
+    }
+}
+
+fn read_varint(cursor: &mut &[u8]) -> AgentResult<i32> {
+    let mut result = 0u32;
+    for shift in 0..5 {
+        let (&byte, rest) = cursor
+            .split_first()
+            .ok_or_else(|| AgentError::NetworkError("Truncated VarInt".to_string()))?;
+        *cursor = rest;
+        result |= ((byte & 0x7F) as u32) << (7 * shift);
+        if byte & 0x80 == 0 {
+            return Ok(result as i32);
+        }
+    }
+    Err(AgentError::NetworkError("VarInt too long".to_string()))
+}
+
+async fn read_varint_async(stream: &mut TcpStream) -> AgentResult<i32> {
+    let mut result = 0u32;
+    for shift in 0..5 {
+        let byte = stream.read_u8().await?;
+        result |= ((byte & 0x7F) as u32) << (7 * shift);
+        if byte & 0x80 == 0 {
+            return Ok(result as i32);
+        }
+    }
+    Err(AgentError::NetworkError("VarInt too long".to_string()))
+}
+
+/// Periodically probes running servers and keeps the latest result for stats reporting.
+pub struct GameQueryManager {
+    runtime: Arc<dyn ContainerRuntime>,
+    targets: RwLock<HashMap<String, QueryTarget>>,
+    statuses: RwLock<HashMap<String, QueryStatus>>,
+}
+
+impl GameQueryManager {
+    pub fn new(runtime: Arc<dyn ContainerRuntime>) -> Self {
+        Self {
+            runtime,
+            targets: RwLock::new(HashMap::new()),
+            statuses: RwLock::new(HashMap::new()),
+        }
+    }
+
+    pub async fn register(&self, server_id: &str, target: Option<QueryTarget>) {
+        self.statuses.write().await.remove(server_id);
+        let mut targets = self.targets.write().await;
+        match target {
+            Some(target) => {
+                targets.insert(server_id.to_string(), target);
+            }
+            None => {
+                targets.remove(server_id);
+            }
+        }
+    }
+
+    /// Forget the last result, e.g. after the server stopped.
+    pub async fn reset(&self, server_id: &str) {
+        self.statuses.write().await.remove(server_id);
+    }
+
+    pub async fn status(&self, server_id: &str) -> Option<QueryStatus> {
+        self.statuses.read().await.get(server_id).cloned()
+    }
+
+    /// Probe one server and record the result.
+    pub async fn probe(&self, server_id: &str) -> Option<QueryStatus> {
+        let target = *self.targets.read().await.get(server_id)?;
+        let ip = self
+            .runtime
+            .get_container_ip(server_id)
+            .await
+            .ok()
+            .and_then(|ip| ip.parse::<IpAddr>().ok())
+            .unwrap_or(IpAddr::V4(Ipv4Addr::LOCALHOST));
+        let result = query(target.protocol, SocketAddr::new(ip, target.port)).await;
+
+        let mut statuses = self.statuses.write().await;
+        let previous_failures = statuses
+            .get(server_id)
+            .map(|status| status.consecutive_failures)
+            .unwrap_or(0);
+        let status = match result {
+            Ok(info) => QueryStatus {
+                online: true,
+                info,
+                error: None,
+                consecutive_failures: 0,
+                checked_at: chrono::Utc::now().timestamp_millis(),
+            },
+            Err(err) => QueryStatus {
+                online: false,
+                info: QueryInfo::default(),
+                error: Some(err.to_string()),
+                consecutive_failures: previous_failures.saturating_add(1),
+                checked_at: chrono::Utc::now().timestamp_millis(),
+            },
+        };
+        statuses.insert(server_id.to_string(), status.clone());
+        Some(status)
+    }
+
+    /// Probe every registered server whose container is running.
+    pub async fn probe_all(&self) {
+        let server_ids: Vec<String> = self.targets.read().await.keys().cloned().collect();
+        let probes = server_ids.iter().map(|server_id| async move {
+            if self
+                .runtime
+                .is_container_running(server_id)
+                .await
+                .unwrap_or(false)
+            {
+                self.probe(server_id).await;
+            }
+        });
+        futures::future::join_all(probes).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn varint_round_trip() {
+        for value in [0, 1, 127, 128, 25565, i32::MAX, -1] {
+            let mut encoded = Vec::new();
+            write_varint(&mut encoded, value);
+            let mut cursor = encoded.as_slice();
+            assert_eq!(read_varint(&mut cursor).unwrap(), value);
+            assert!(cursor.is_empty());
+        }
+    }
+
+    #[test]
+    fn parses_a2s_info() {
+        let mut packet =
+            b"\xFF\xFF\xFF\xFFI\x11My Server\0de_dust2\0csgo\0Counter-Strike\0".to_vec();
+        packet.extend_from_slice(&[0xDA, 0x02, 7, 24, 0, b'd', b'l', 0, 1]);
+        let info = parse_a2s_info(&packet).unwrap();
+        assert_eq!(info.players, Some(7));
+        assert_eq!(info.max_players, Some(24));
+        assert_eq!(info.motd.as_deref(), Some("My Server"));
+        assert_eq!(info.map.as_deref(), Some("de_dust2"));
+    }
+
+    #[test]
+    fn query_target_needs_a_known_protocol() {
+        assert_eq!(QueryTarget::from_template(&json!({}), 25565).unwrap(), None);
+        let template = json!({ "features": { "queryProtocol": "none" } });
+        assert_eq!(QueryTarget::from_template(&template, 25565).unwrap(), None);
+        let template = json!({ "features": { "queryProtocol": "A2S", "queryPort": 27016 } });
+        assert_eq!(
+            QueryTarget::from_template(&template, 27015).unwrap(),
+            Some(QueryTarget {
+                protocol: QueryProtocol::Source,
+                port: 27016,
+            })
+        );
+        let template = json!({ "features": { "queryProtocol": "minecarft" } });
+        assert!(QueryTarget::from_template(&template, 25565).is_err());
+    }
+
+    #[test]
+    fn flattens_minecraft_description() {
+        let description = json!({ "text": "A ", "extra": [{ "text": "Minecraft" }, " Server"] });
+        assert_eq!(minecraft_text(&description), "A Minecraft Server");
+    }
+}
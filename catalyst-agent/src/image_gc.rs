@@ -0,0 +1,259 @@
+use std::collections::HashSet;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+
+use serde::Serialize;
+use tokio::sync::Mutex;
+use tracing::{info, warn};
+
+use crate::runtime_manager::ImageRecord;
+use crate::{AgentResult, ContainerRuntime, ContainerdRuntime, StorageManager};
+
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PrunedItem {
+    pub name: String,
+    pub size_bytes: u64,
+}
+
+/// What a prune removed, or would remove on a dry run. Image sizes count the compressed
+/// blobs no kept image shares; unpacked layers free roughly as much again.
+#[derive(Clone, Debug, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PruneReport {
+    pub dry_run: bool,
+    pub images: Vec<PrunedItem>,
+    pub snapshots: Vec<PrunedItem>,
+    pub reclaimable_bytes: u64,
+    pub errors: Vec<String>,
+}
+
+/// Removes images no server on this node references, and rootfs snapshots whose
+/// container is gone. References are the images recorded per server in the storage
+/// index, prefetched images, and those of existing containers and in-flight pulls.
+pub struct ImageGc {
+    runtime: Arc<dyn ContainerRuntime>,
+    storage: Arc<StorageManager>,
+    /// Images pulled or updated more recently than this are kept regardless.
+    min_age: Duration,
+    running: Mutex<()>,
+}
+
+impl ImageGc {
+    pub fn new(
+        runtime: Arc<dyn ContainerRuntime>,
+        storage: Arc<StorageManager>,
+        min_age: Duration,
+    ) -> Self {
+        Self {
+            runtime,
+            storage,
+            min_age,
+            running: Mutex::new(()),
+        }
+    }
+
+    pub async fn prune(&self, dry_run: bool) -> AgentResult<PruneReport> {
+        let _guard = self.running.lock().await;
+        let mut referenced: HashSet<String> = self
+            .storage
+            .live_server_images(!dry_run)
+            .await?
+            .into_values()
+            .flat_map(|roles| roles.into_values())
+            .map(|image| ContainerdRuntime::qualify_image_ref(&image))
+            .collect();
+        referenced.extend(self.storage.pinned_images().await);
+        referenced.extend(
+            self.runtime
+                .list_containers()
+                .await?
+                .into_iter()
+                .map(|container| container.image),
+        );
+        referenced.extend(self.runtime.pulls_in_flight().await);
+
+        let images = self.runtime.list_images().await?;
+        let (prune, keep) = partition_images(images, &referenced, self.min_age, SystemTime::now());
+        let sizes = exclusive_sizes(&prune, &keep);
+
+        let mut report = PruneReport {
+            dry_run,
+            ..PruneReport::default()
+        };
+        for (image, size_bytes) in prune.into_iter().zip(sizes) {
+            if !dry_run {
+                if let Err(e) = self.runtime.delete_image(&image.name).await {
+                    warn!("Failed to delete image {}: {}", image.name, e);
+                    report.errors.push(format!("{}: {}", image.name, e));
+                    continue;
+                }
+                info!("Pruned image {}", image.name);
+            }
+            report.reclaimable_bytes += size_bytes;
+            report.images.push(PrunedItem {
+                name: image.name,
+                size_bytes,
+            });
+        }
+
+        for (key, size_bytes) in self.runtime.orphaned_snapshots(self.min_age).await? {
+            if !dry_run {
+                if let Err(e) = self.runtime.remove_snapshot(&key).await {
+                    warn!("Failed to remove snapshot {}: {}", key, e);
+                    report.errors.push(format!("{}: {}", key, e));
+                    continue;
+                }
+                info!("Removed orphaned snapshot {}", key);
+            }
+            report.reclaimable_bytes += size_bytes;
+            report.snapshots.push(PrunedItem {
+                name: key,
+                size_bytes,
+            });
+        }
+        Ok(report)
+    }
+}
+
+/// Split images into those to prune and those to keep. Images without a timestamp are
+/// treated as new.
+fn partition_images(
+    images: Vec<ImageRecord>,
+    referenced: &HashSet<String>,
+    min_age: Duration,
+    now: SystemTime,
+) -> (Vec<ImageRecord>, Vec<ImageRecord>) {
+    images.into_iter().partition(|image| {
+        let old_enough = image
+            .updated_at
+            .and_then(|updated| now.duration_since(updated).ok())
+            .is_some_and(|age| age >= min_age);
+        old_enough && !referenced.contains(&image.name)
+    })
+}
+
+/// Bytes each pruned image frees: blobs no kept image shares, with blobs shared between
+/// pruned images counted once.
+fn exclusive_sizes(prune: &[ImageRecord], keep: &[ImageRecord]) -> Vec<u64> {
+    let mut counted: HashSet<&str> = keep
+        .iter()
+        .flat_map(|image| image.blobs.iter().map(|(digest, _)| digest.as_str()))
+        .collect();
+    prune
+        .iter()
+        .map(|image| {
+            image
+                .blobs
+                .iter()
+                .filter(|(digest, _)| counted.insert(digest.as_str()))
+                .map(|(_, size)| size)
+                .sum()
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fake_runtime::FakeRuntime;
+
+    fn image(name: &str, age_secs: u64, blobs: &[(&str, u64)], now: SystemTime) -> ImageRecord {
+        ImageRecord {
+            name: name.to_string(),
+            updated_at: Some(now - Duration::from_secs(age_secs)),
+            blobs: blobs
+                .iter()
+                .map(|(digest, size)| (digest.to_string(), *size))
+                .collect(),
+        }
+    }
+
+    #[tokio::test]
+    async fn prune_keeps_images_in_use() {
+        let runtime = Arc::new(FakeRuntime::new());
+        let old = Duration::from_secs(7200);
+        for image in ["container", "pinned", "pulling", "unused"] {
+            runtime.insert_image(&format!("docker.io/library/{}:1", image), old);
+        }
+        runtime.insert_image("docker.io/library/fresh:1", Duration::from_secs(60));
+        runtime.insert_container("srv-1", "docker.io/library/container:1");
+        runtime.start_pull("docker.io/library/pulling:1");
+        let data_dir = std::env::temp_dir().join(format!("catalyst-test-{}", uuid::Uuid::new_v4()));
+        let storage = Arc::new(StorageManager::new(data_dir.clone()));
+        storage
+            .pin_images(&["docker.io/library/pinned:1".to_string()], true)
+            .await
+            .unwrap();
+        let gc = ImageGc::new(runtime.clone(), storage, Duration::from_secs(3600));
+
+        let report = gc.prune(true).await.unwrap();
+        assert!(report.dry_run);
+        let pruned: Vec<_> = report
+            .images
+            .iter()
+            .map(|item| item.name.as_str())
+            .collect();
+        assert_eq!(pruned, ["docker.io/library/unused:1"]);
+        assert_eq!(runtime.image_names().await.unwrap().len(), 5);
+        assert!(runtime.calls().is_empty());
+
+        let report = gc.prune(false).await.unwrap();
+        let pruned: Vec<_> = report
+            .images
+            .iter()
+            .map(|item| item.name.as_str())
+            .collect();
+        assert_eq!(pruned, ["docker.io/library/unused:1"]);
+        assert_eq!(runtime.calls(), ["delete_image docker.io/library/unused:1"]);
+        assert_eq!(
+            runtime.image_names().await.unwrap(),
+            [
+                "docker.io/library/container:1",
+                "docker.io/library/fresh:1",
+                "docker.io/library/pinned:1",
+                "docker.io/library/pulling:1",
+            ]
+        );
+
+        let _ = tokio::fs::remove_dir_all(&data_dir).await;
+    }
+
+    #[test]
+    fn prunes_old_unreferenced_images_only() {
+        let now = SystemTime::now();
+        let images = vec![
+            image(
+                "docker.io/library/old:1",
+                7200,
+                &[("a", 10), ("base", 100)],
+                now,
+            ),
+            image(
+                "docker.io/library/old:2",
+                7200,
+                &[("b", 20), ("base", 100)],
+                now,
+            ),
+            image("docker.io/library/used:1", 7200, &[("c", 30)], now),
+            image(
+                "docker.io/library/fresh:1",
+                60,
+                &[("d", 40), ("base", 100)],
+                now,
+            ),
+        ];
+        let referenced = HashSet::from(["docker.io/library/used:1".to_string()]);
+
+        let (prune, keep) = partition_images(images, &referenced, Duration::from_secs(3600), now);
+        let names: Vec<_> = prune.iter().map(|image| image.name.as_str()).collect();
+        assert_eq!(
+            names,
+            ["docker.io/library/old:1", "docker.io/library/old:2"]
+        );
+        assert_eq!(keep.len(), 2);
+        // The shared base layer is still held by the fresh image.
+        assert_eq!(exclusive_sizes(&prune, &keep), [10, 20]);
+        assert_eq!(exclusive_sizes(&prune, &[]), [110, 20]);
+    }
+}
@@ -0,0 +1,120 @@
+use std::path::Path;
+
+use crate::config::ImagePolicyConfig;
+use crate::{AgentError, AgentResult};
+
+/// Split a qualified reference into its repository and, when pinned, its digest.
+pub fn split_reference(reference: &str) -> (&str, Option<&str>) {
+    let (name, digest) = match reference.split_once('@') {
+        Some((name, digest)) => (name, Some(digest)),
+        None => (reference, None),
+    };
+    // A tag follows the last ':' after the last '/'; an earlier ':' is a registry port.
+    let repository = match name.rfind(':') {
+        Some(index) if !name[index..].contains('/') => &name[..index],
+        _ => name,
+    };
+    (repository, digest)
+}
+
+/// Match a repository against a host, `namespace/*` or full repository pattern.
+pub fn repository_matches(pattern: &str, repository: &str) -> bool {
+    let pattern = pattern.trim().trim_end_matches('/');
+    if let Some(prefix) = pattern.strip_suffix("/*") {
+        return repository
+            .strip_prefix(prefix)
+            .is_some_and(|rest| rest.starts_with('/'));
+    }
+    if !pattern.contains('/') {
+        return repository.split('/').next() == Some(pattern);
+    }
+    repository == pattern
+}
+
+impl ImagePolicyConfig {
+    /// Reject a qualified reference outside the allowlist, or unpinned when digests are
+    /// required.
+    pub fn check(&self, reference: &str) -> AgentResult<()> {
+        let (repository, digest) = split_reference(reference);
+        if !self.allowed_repositories.is_empty()
+            && !self
+                .allowed_repositories
+                .iter()
+                .any(|pattern| repository_matches(pattern, repository))
+        {
+            return Err(AgentError::PermissionDenied(format!(
+                "Image {} is not allowed by the node image policy",
+                reference
+            )));
+        }
+        if self.require_digest && digest.is_none() {
+            return Err(AgentError::PermissionDenied(format!(
+                "Image {} must be pinned by digest",
+                reference
+            )));
+        }
+        Ok(())
+    }
+
+    /// Public key the image's signature must verify against, if any rule covers it.
+    pub fn signature_key(&self, reference: &str) -> Option<&Path> {
+        let (repository, _) = split_reference(reference);
+        self.signatures
+            .iter()
+            .find(|rule| {
+                rule.repositories
+                    .iter()
+                    .any(|pattern| repository_matches(pattern, repository))
+            })
+            .map(|rule| rule.public_key.as_path())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::SignaturePolicy;
+
+    #[test]
+    fn splits_references() {
+        assert_eq!(
+            split_reference("docker.io/library/alpine:3.19"),
+            ("docker.io/library/alpine", None)
+        );
+        assert_eq!(
+            split_reference("localhost:5000/game@sha256:abc"),
+            ("localhost:5000/game", Some("sha256:abc"))
+        );
+        assert_eq!(
+            split_reference("ghcr.io/acme/mc:1.21@sha256:abc"),
+            ("ghcr.io/acme/mc", Some("sha256:abc"))
+        );
+    }
+
+    #[test]
+    fn enforces_allowlist_digests_and_signatures() {
+        let policy = ImagePolicyConfig {
+            allowed_repositories: vec!["ghcr.io/acme/*".into(), "docker.io/library/alpine".into()],
+            require_digest: false,
+            signatures: vec![SignaturePolicy {
+                repositories: vec!["ghcr.io".into()],
+                public_key: "/etc/catalyst/cosign.pub".into(),
+            }],
+        };
+        assert!(policy.check("ghcr.io/acme/mc:1.21").is_ok());
+        assert!(policy.check("docker.io/library/alpine:3.19").is_ok());
+        assert!(policy.check("ghcr.io/acme-evil/mc:1").is_err());
+        assert!(policy.check("docker.io/library/alpine-evil:1").is_err());
+        assert!(ImagePolicyConfig {
+            require_digest: true,
+            ..ImagePolicyConfig::default()
+        }
+        .check("docker.io/library/alpine:3.19")
+        .is_err());
+
+        assert!(policy.signature_key("ghcr.io/acme/mc:1.21").is_some());
+        assert!(policy
+            .signature_key("docker.io/library/alpine:3.19")
+            .is_none());
+    }
+}
@@ -2,19 +2,34 @@ use std::sync::Arc;
 use tokio::sync::RwLock;
 use tracing::{error, info, warn};
 
+mod cgroup;
 mod config;
+mod container_runtime;
 mod errors;
+mod exec_session;
+#[cfg(test)]
+mod fake_runtime;
 mod file_manager;
 mod file_tunnel;
 mod firewall_manager;
+mod game_query;
+mod image_gc;
+mod image_policy;
 mod network_manager;
+mod rcon;
 mod runtime_manager;
+mod security_profile;
+mod startup_detector;
 mod storage_manager;
 mod system_setup;
+mod user_namespace;
+mod watchdog;
 mod websocket_handler;
 
 pub use config::AgentConfig;
+pub use container_runtime::ContainerRuntime;
 pub use errors::{AgentError, AgentResult};
+pub use exec_session::ExecSessionManager;
 pub use file_manager::FileManager;
 pub use file_tunnel::FileTunnelClient;
 pub use firewall_manager::FirewallManager;
@@ -45,6 +60,7 @@ impl CatalystAgent {
                 config.containerd.socket_path.clone(),
                 config.containerd.namespace.clone(),
                 config.networking.dns_servers.clone(),
+                config.images.clone(),
             )
             .await?,
         );
@@ -100,6 +116,12 @@ impl CatalystAgent {
             agent.start_health_monitoring().await;
         });
 
+        // Start image garbage collection
+        let agent = self.clone_refs();
+        let gc_task = tokio::spawn(async move {
+            agent.start_image_gc().await;
+        });
+
         // Start file tunnel (HTTP-based file operations)
         let file_tunnel = self.file_tunnel.clone();
         let tunnel_task = tokio::spawn(async move {
@@ -111,6 +133,7 @@ impl CatalystAgent {
             _ = ws_task => {},
             _ = health_task => {},
             _ = tunnel_task => {},
+            _ = gc_task => {},
         }
 
         Ok(())
@@ -134,6 +157,22 @@ impl CatalystAgent {
         }
     }
 
+    async fn start_image_gc(&self) {
+        let period = self.config.images.gc_interval_secs;
+        if period == 0 {
+            // Never return: the run loop treats a finished task as fatal.
+            return std::future::pending().await;
+        }
+        let mut interval = tokio::time::interval(std::time::Duration::from_secs(period));
+        // The first tick fires immediately; skip it so startup is not slowed by a prune.
+        interval.tick().await;
+
+        loop {
+            interval.tick().await;
+            self.ws_handler.run_image_gc().await;
+        }
+    }
+
     fn clone_refs(&self) -> Self {
         Self {
             config: self.config.clone(),
@@ -0,0 +1,532 @@
+use std::collections::HashMap;
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use std::sync::Arc;
+use std::time::Duration;
+
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+use tokio::sync::{Mutex, RwLock};
+use tokio::time::timeout;
+use tracing::{debug, info};
+
+use crate::{AgentError, AgentResult, ContainerRuntime};
+
+const DEFAULT_RCON_PORT: u16 = 25575;
+const IO_TIMEOUT: Duration = Duration::from_secs(5);
+/// Largest command body accepted by Minecraft; Source servers allow more but this is safe for both.
+const MAX_COMMAND_BYTES: usize = 1446;
+const MAX_PACKET_BYTES: usize = 64 * 1024;
+
+const SERVERDATA_AUTH: i32 = 3;
+const SERVERDATA_AUTH_RESPONSE: i32 = 2;
+const SERVERDATA_EXECCOMMAND: i32 = 2;
+const SERVERDATA_RESPONSE_VALUE: i32 = 0;
+
+/// A failed RCON command, split by whether the server may already have run it. Only
+/// commands that never reached the server are safe to deliver again another way.
+#[derive(Debug)]
+pub enum RconError {
+    NotSent(AgentError),
+    Sent(AgentError),
+}
+
+impl From<RconError> for AgentError {
+    fn from(err: RconError) -> Self {
+        match err {
+            RconError::NotSent(err) | RconError::Sent(err) => err,
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+struct Packet {
+    id: i32,
+    kind: i32,
+    body: Vec<u8>,
+}
+
+fn encode_packet(id: i32, kind: i32, body: &[u8]) -> Vec<u8> {
+    let length = (body.len() + 10) as i32;
+    let mut out = Vec::with_capacity(body.len() + 14);
+    out.extend_from_slice(&length.to_le_bytes());
+    out.extend_from_slice(&id.to_le_bytes());
+    out.extend_from_slice(&kind.to_le_bytes());
+    out.extend_from_slice(body);
+    out.extend_from_slice(&[0, 0]);
+    out
+}
+
+/// Decode the fields following the length prefix of a packet.
+fn decode_packet(payload: &[u8]) -> AgentResult<Packet> {
+    if payload.len() < 10 {
+        return Err(AgentError::NetworkError(
+            "RCON packet too short".to_string(),
+        ));
+    }
+    let id = i32::from_le_bytes(payload[0..4].try_into().unwrap_or_default());
+    let kind = i32::from_le_bytes(payload[4..8].try_into().unwrap_or_default());
+    let mut body = payload[8..payload.len() - 2].to_vec();
+    // Some servers include the terminator inside the body; never surface NULs to callers.
+    while body.last() == Some(&0) {
+        body.pop();
+    }
+    Ok(Packet { id, kind, body })
+}
+
+/// RCON credentials taken from the server's environment.
+#[derive(Clone, Debug)]
+pub struct RconCredentials {
+    pub port: u16,
+    pub password: String,
+}
+
+impl RconCredentials {
+    /// Reads `RCON_PASSWORD` (or `RCON_PASS`) and `RCON_PORT`. Servers without a password
+    /// have RCON disabled.
+    pub fn from_env(env: &HashMap<String, String>) -> Option<Self> {
+        let password = env
+            .get("RCON_PASSWORD")
+            .or_else(|| env.get("RCON_PASS"))
+            .map(|value| value.trim())
+            .filter(|value| !value.is_empty())?;
+        let port = env
+            .get("RCON_PORT")
+            .and_then(|value| value.trim().parse::<u16>().ok())
+            .filter(|port| *port != 0)
+            .unwrap_or(DEFAULT_RCON_PORT);
+        Some(Self {
+            port,
+            password: password.to_string(),
+        })
+    }
+}
+
+/// A single authenticated Source RCON connection.
+pub struct RconClient {
+    stream: TcpStream,
+    next_id: i32,
+}
+
+impl RconClient {
+    pub async fn connect(addr: SocketAddr, password: &str) -> AgentResult<Self> {
+        let stream = timeout(IO_TIMEOUT, TcpStream::connect(addr))
+            .await
+            .map_err(|_| AgentError::NetworkError(format!("RCON connect to {} timed out", addr)))?
+            .map_err(|e| AgentError::NetworkError(format!("RCON connect to {}: {}", addr, e)))?;
+        let mut client = Self { stream, next_id: 1 };
+
+        let auth_id = client.allocate_id();
+        client
+            .write_packet(auth_id, SERVERDATA_AUTH, password.as_bytes())
+            .await?;
+        // Source servers send an empty RESPONSE_VALUE ahead of the auth result.
+        loop {
+            let packet = client.read_packet().await?;
+            if packet.kind != SERVERDATA_AUTH_RESPONSE {
+                continue;
+            }
+            if packet.id == -1 {
+                return Err(AgentError::PermissionDenied(
+                    "RCON authentication failed".to_string(),
+                ));
+            }
+            if packet.id == auth_id {
+                return Ok(client);
+            }
+        }
+    }
+
+    /// Write a command followed by the sentinel that ends its response, returning both ids.
+    async fn send(&mut self, command: &str) -> Result<(i32, i32), RconError> {
+        if command.len() > MAX_COMMAND_BYTES {
+            return Err(RconError::NotSent(AgentError::InvalidRequest(format!(
+                "RCON command exceeds {} bytes",
+                MAX_COMMAND_BYTES
+            ))));
+        }
+        let command_id = self.allocate_id();
+        self.write_packet(command_id, SERVERDATA_EXECCOMMAND, command.as_bytes())
+            .await
+            .map_err(RconError::NotSent)?;
+        // An empty RESPONSE_VALUE is echoed back (or answered) after the real response,
+        // which marks the end of a fragmented reply.
+        let sentinel_id = self.allocate_id();
+        self.write_packet(sentinel_id, SERVERDATA_RESPONSE_VALUE, &[])
+            .await
+            .map_err(RconError::Sent)?;
+        Ok((command_id, sentinel_id))
+    }
+
+    /// Collect the (possibly multi-packet) response to a command sent with `send`.
+    async fn read_response(
+        &mut self,
+        (command_id, sentinel_id): (i32, i32),
+    ) -> AgentResult<String> {
+        let mut body = Vec::new();
+        loop {
+            let packet = self.read_packet().await?;
+            if packet.id == sentinel_id {
+                break;
+            }
+            if packet.id == command_id && packet.kind == SERVERDATA_RESPONSE_VALUE {
+                body.extend_from_slice(&packet.body);
+            }
+        }
+        Ok(String::from_utf8_lossy(&body).into_owned())
+    }
+
+    /// Whether the server closed the connection or sent something nobody asked for. A
+    /// write to such a socket can still succeed, so this is checked before reusing it.
+    fn is_stale(&self) -> bool {
+        !matches!(
+            self.stream.try_read(&mut [0u8; 1]),
+            Err(ref e) if e.kind() == std::io::ErrorKind::WouldBlock
+        )
+    }
+
+    fn allocate_id(&mut self) -> i32 {
+        let id = self.next_id;
+        self.next_id = if self.next_id == i32::MAX {
+            1
+        } else {
+            self.next_id + 1
+        };
+        id
+    }
+
+    async fn write_packet(&mut self, id: i32, kind: i32, body: &[u8]) -> AgentResult<()> {
+        let packet = encode_packet(id, kind, body);
+        timeout(IO_TIMEOUT, self.stream.write_all(&packet))
+            .await
+            .map_err(|_| AgentError::NetworkError("RCON write timed out".to_string()))?
+            .map_err(|e| AgentError::NetworkError(format!("RCON write: {}", e)))
+    }
+
+    async fn read_packet(&mut self) -> AgentResult<Packet> {
+        timeout(IO_TIMEOUT, async {
+            let length = self.stream.read_i32_le().await?;
+            if !(10..=MAX_PACKET_BYTES as i32).contains(&length) {
+                return Err(AgentError::NetworkError(format!(
+                    "Invalid RCON packet length {}",
+                    length
+                )));
+            }
+            let mut payload = vec![0u8; length as usize];
+            self.stream.read_exact(&mut payload).await?;
+            decode_packet(&payload)
+        })
+        .await
+        .map_err(|_| AgentError::NetworkError("RCON response timed out".to_string()))?
+    }
+}
+
+#[derive(Default)]
+struct RconTarget {
+    credentials: Option<RconCredentials>,
+    prefer: bool,
+    connection: Option<RconClient>,
+}
+
+/// Per-server RCON credentials and cached connections.
+pub struct RconManager {
+    runtime: Arc<dyn ContainerRuntime>,
+    targets: RwLock<HashMap<String, Arc<Mutex<RconTarget>>>>,
+}
+
+impl RconManager {
+    pub fn new(runtime: Arc<dyn ContainerRuntime>) -> Self {
+        Self {
+            runtime,
+            targets: RwLock::new(HashMap::new()),
+        }
+    }
+
+    /// Record credentials for a server at start. `prefer` routes console commands over
+    /// RCON first instead of using it only as a fallback for stdin.
+    pub async fn register(
+        &self,
+        server_id: &str,
+        credentials: Option<RconCredentials>,
+        prefer: bool,
+    ) {
+        let target = RconTarget {
+            prefer: prefer && credentials.is_some(),
+            credentials,
+            connection: None,
+        };
+        self.targets
+            .write()
+            .await
+            .insert(server_id.to_string(), Arc::new(Mutex::new(target)));
+    }
+
+    pub async fn is_configured(&self, server_id: &str) -> bool {
+        match self.target(server_id).await {
+            Some(target) => target.lock().await.credentials.is_some(),
+            None => false,
+        }
+    }
+
+    pub async fn is_preferred(&self, server_id: &str) -> bool {
+        match self.target(server_id).await {
+            Some(target) => target.lock().await.prefer,
+            None => false,
+        }
+    }
+
+    /// Drop the cached connection, e.g. after the server stopped.
+    pub async fn disconnect(&self, server_id: &str) {
+        if let Some(target) = self.target(server_id).await {
+            target.lock().await.connection = None;
+        }
+    }
+
+    /// Run console input over RCON. Each line is its own command packet, since servers only
+    /// execute the first line of a packet; the responses are joined by newlines.
+    pub async fn execute(
+        &self,
+        server_id: &str,
+        container_id: &str,
+        input: &str,
+    ) -> Result<String, RconError> {
+        let mut responses = Vec::new();
+        for (index, command) in input
+            .lines()
+            .map(|line| line.trim_end_matches('\r'))
+            .filter(|line| !line.trim().is_empty())
+            .enumerate()
+        {
+            let response = match self.execute_line(server_id, container_id, command).await {
+                Ok(response) => response,
+                // Earlier lines already ran, so the input as a whole must not be sent again.
+                Err(RconError::NotSent(err)) if index > 0 => return Err(RconError::Sent(err)),
+                Err(err) => return Err(err),
+            };
+            if !response.is_empty() {
+                responses.push(response);
+            }
+        }
+        Ok(responses.join("\n"))
+    }
+
+    async fn execute_line(
+        &self,
+        server_id: &str,
+        container_id: &str,
+        command: &str,
+    ) -> Result<String, RconError> {
+        let not_configured = || {
+            RconError::NotSent(AgentError::InvalidRequest(format!(
+                "RCON is not configured for {}",
+                server_id
+            )))
+        };
+        let target = self.target(server_id).await.ok_or_else(not_configured)?;
+        let mut target = target.lock().await;
+        let credentials = target.credentials.clone().ok_or_else(not_configured)?;
+
+        // A stale cached socket gets one new connection, but only while the command has not
+        // been written: after that the server may have run it, so it is never sent twice.
+        let mut sent = None;
+        if let Some(mut connection) = target.connection.take() {
+            if connection.is_stale() {
+                debug!("Cached RCON connection for {} was closed", server_id);
+            } else {
+                match connection.send(command).await {
+                    Ok(ids) => sent = Some((connection, ids)),
+                    Err(RconError::NotSent(e)) => {
+                        debug!("Cached RCON connection for {} failed: {}", server_id, e)
+                    }
+                    Err(err) => return Err(err),
+                }
+            }
+        }
+        let (mut connection, ids) = match sent {
+            Some(sent) => sent,
+            None => {
+                let addr =
+                    SocketAddr::new(self.server_address(container_id).await, credentials.port);
+                let mut connection = RconClient::connect(addr, &credentials.password)
+                    .await
+                    .map_err(RconError::NotSent)?;
+                info!("RCON connected to server {} at {}", server_id, addr);
+                let ids = connection.send(command).await?;
+                (connection, ids)
+            }
+        };
+        let response = connection
+            .read_response(ids)
+            .await
+            .map_err(RconError::Sent)?;
+        target.connection = Some(connection);
+        Ok(response)
+    }
+
+    async fn target(&self, server_id: &str) -> Option<Arc<Mutex<RconTarget>>> {
+        self.targets.read().await.get(server_id).cloned()
+    }
+
+    /// Containers on host networking have no address of their own.
+    async fn server_address(&self, container_id: &str) -> IpAddr {
+        self.runtime
+            .get_container_ip(container_id)
+            .await
+            .ok()
+            .and_then(|ip| ip.parse::<IpAddr>().ok())
+            .unwrap_or(IpAddr::V4(Ipv4Addr::LOCALHOST))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::net::TcpListener;
+
+    async fn read_raw(stream: &mut TcpStream) -> Packet {
+        let length = stream.read_i32_le().await.unwrap();
+        let mut payload = vec![0u8; length as usize];
+        stream.read_exact(&mut payload).await.unwrap();
+        decode_packet(&payload).unwrap()
+    }
+
+    #[test]
+    fn packet_round_trip() {
+        let encoded = encode_packet(7, SERVERDATA_EXECCOMMAND, b"list");
+        assert_eq!(&encoded[0..4], &14i32.to_le_bytes());
+        let packet = decode_packet(&encoded[4..]).unwrap();
+        assert_eq!(
+            packet,
+            Packet {
+                id: 7,
+                kind: SERVERDATA_EXECCOMMAND,
+                body: b"list".to_vec(),
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn never_resends_a_command_without_a_reply() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let server = tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let auth = read_raw(&mut stream).await;
+            stream
+                .write_all(&encode_packet(auth.id, SERVERDATA_AUTH_RESPONSE, b""))
+                .await
+                .unwrap();
+            let command = read_raw(&mut stream).await;
+            read_raw(&mut stream).await;
+            // Hang up without answering, then check nobody sends the command again.
+            drop(stream);
+            let retried = timeout(Duration::from_millis(300), listener.accept())
+                .await
+                .is_ok();
+            (command.body, retried)
+        });
+
+        let runtime = Arc::new(crate::fake_runtime::FakeRuntime::new());
+        runtime.insert_container("srv-1", "docker.io/library/alpine:3.19");
+        let manager = RconManager::new(runtime);
+        let credentials = RconCredentials {
+            port,
+            password: "secret".to_string(),
+        };
+        manager.register("srv-1", Some(credentials), true).await;
+
+        let result = manager.execute("srv-1", "srv-1", "stop").await;
+        assert!(matches!(result, Err(RconError::Sent(_))));
+        assert_eq!(server.await.unwrap(), (b"stop".to_vec(), false));
+    }
+
+    #[tokio::test]
+    async fn sends_one_command_per_line() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let server = tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let auth = read_raw(&mut stream).await;
+            stream
+                .write_all(&encode_packet(auth.id, SERVERDATA_AUTH_RESPONSE, b""))
+                .await
+                .unwrap();
+            let mut commands = Vec::new();
+            for _ in 0..2 {
+                let command = read_raw(&mut stream).await;
+                let sentinel = read_raw(&mut stream).await;
+                let response = format!("ran {}", String::from_utf8_lossy(&command.body));
+                stream
+                    .write_all(&encode_packet(
+                        command.id,
+                        SERVERDATA_RESPONSE_VALUE,
+                        response.as_bytes(),
+                    ))
+                    .await
+                    .unwrap();
+                stream
+                    .write_all(&encode_packet(sentinel.id, SERVERDATA_RESPONSE_VALUE, b""))
+                    .await
+                    .unwrap();
+                commands.push(command.body);
+            }
+            commands
+        });
+
+        let runtime = Arc::new(crate::fake_runtime::FakeRuntime::new());
+        runtime.insert_container("srv-1", "docker.io/library/alpine:3.19");
+        let manager = RconManager::new(runtime);
+        let credentials = RconCredentials {
+            port,
+            password: "secret".to_string(),
+        };
+        manager.register("srv-1", Some(credentials), true).await;
+
+        let response = manager
+            .execute("srv-1", "srv-1", "say a\r\n\nsay b\n")
+            .await
+            .unwrap();
+        assert_eq!(response, "ran say a\nran say b");
+        assert_eq!(
+            server.await.unwrap(),
+            vec![b"say a".to_vec(), b"say b".to_vec()]
+        );
+    }
+
+    #[tokio::test]
+    async fn joins_fragmented_response() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let auth = read_raw(&mut stream).await;
+            assert_eq!(auth.body, b"secret");
+            stream
+                .write_all(&encode_packet(auth.id, SERVERDATA_RESPONSE_VALUE, b""))
+                .await
+                .unwrap();
+            stream
+                .write_all(&encode_packet(auth.id, SERVERDATA_AUTH_RESPONSE, b""))
+                .await
+                .unwrap();
+
+            let command = read_raw(&mut stream).await;
+            let sentinel = read_raw(&mut stream).await;
+            for part in [&b"There are 2 "[..], &b"players online"[..]] {
+                stream
+                    .write_all(&encode_packet(command.id, SERVERDATA_RESPONSE_VALUE, part))
+                    .await
+                    .unwrap();
+            }
+            stream
+                .write_all(&encode_packet(sentinel.id, SERVERDATA_RESPONSE_VALUE, b""))
+                .await
+                .unwrap();
+        });
+
+        let mut client = RconClient::connect(addr, "secret").await.unwrap();
+        let ids = client.send("list").await.unwrap();
+        let response = client.read_response(ids).await.unwrap();
+        assert_eq!(response, "There are 2 players online");
+        server.await.unwrap();
+    }
+}
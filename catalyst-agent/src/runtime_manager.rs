@@ -6,7 +6,7 @@ use std::os::unix::fs::{OpenOptionsExt, PermissionsExt};
 use std::path::{Path, PathBuf};
 use std::process::Stdio;
 use std::sync::Arc;
-use std::time::{Duration, SystemTime};
+use std::time::{Duration, Instant, SystemTime};
 
 use containerd_client::services::v1::container::Runtime;
 use containerd_client::services::v1::containers_client::ContainersClient;
@@ -15,23 +15,35 @@ use containerd_client::services::v1::events_client::EventsClient;
 use containerd_client::services::v1::images_client::ImagesClient;
 use containerd_client::services::v1::snapshots::snapshots_client::SnapshotsClient;
 use containerd_client::services::v1::snapshots::{
-    MountsRequest, PrepareSnapshotRequest, RemoveSnapshotRequest,
+    Kind as SnapshotKind, ListSnapshotsRequest, MountsRequest, PrepareSnapshotRequest,
+    RemoveSnapshotRequest, UsageRequest,
 };
+use containerd_client::services::v1::streaming_client::StreamingClient;
 use containerd_client::services::v1::tasks_client::TasksClient;
-use containerd_client::services::v1::GetImageRequest;
+use containerd_client::services::v1::transfer_client::TransferClient;
 use containerd_client::services::v1::SubscribeRequest;
 use containerd_client::services::v1::{
     Container, CreateContainerRequest, DeleteContainerRequest, GetContainerRequest, InfoRequest,
     ListContainersRequest, ReadContentRequest,
 };
 use containerd_client::services::v1::{
-    CreateTaskRequest, DeleteTaskRequest, ExecProcessRequest, KillRequest as TaskKillRequest,
-    StartRequest, WaitRequest,
+    CreateImageRequest, DeleteImageRequest, GetImageRequest, Image, ListImagesRequest,
 };
+use containerd_client::services::v1::{
+    CreateTaskRequest, DeleteProcessRequest, DeleteTaskRequest, ExecProcessRequest,
+    KillRequest as TaskKillRequest, ListTasksRequest, PauseTaskRequest, ResizePtyRequest,
+    ResumeTaskRequest, StartRequest, UpdateTaskRequest, WaitRequest,
+};
+use containerd_client::services::v1::{StreamInit, TransferOptions, TransferRequest};
+use containerd_client::types::transfer::{
+    AuthRequest, AuthResponse, AuthType, ImageStore, OciRegistry, Progress as TransferProgress,
+    RegistryResolver, UnpackConfiguration,
+};
+use containerd_client::types::Platform;
 use containerd_client::with_namespace;
 use prost_types::Any;
 use tokio::process::Command;
-use tokio::sync::Mutex;
+use tokio::sync::{broadcast, mpsc, Mutex};
 use tokio::task::spawn_blocking;
 use tonic::Request;
 use tracing::{debug, error, info, warn};
@@ -41,12 +53,27 @@ use nix::fcntl::{fcntl, FcntlArg, OFlag};
 use nix::sys::stat::Mode;
 use nix::unistd::mkfifo;
 
+use crate::cgroup;
+use crate::config::{ImageConfig, PullPolicy, RegistryCredentials, SecurityProfile};
+use crate::container_runtime::{ContainerRuntime, EventStream, InstallerProcess, RuntimeEvent};
 use crate::errors::{AgentError, AgentResult};
 use crate::firewall_manager::FirewallManager;
+use crate::image_policy;
+use crate::security_profile::{
+    default_seccomp_profile, masked_paths, DEFAULT_CONTAINER_GID, DEFAULT_CONTAINER_UID,
+};
+use crate::user_namespace::IdMapping;
 
 const RUNTIME_NAME: &str = "io.containerd.runc.v2";
 const SPEC_TYPE_URL: &str = "types.containerd.io/opencontainers/runtime-spec/1/Spec";
-const CONSOLE_BASE_DIR: &str = "/tmp/catalyst-console";
+const RESOURCES_TYPE_URL: &str = "types.containerd.io/opencontainers/runtime-spec/1/LinuxResources";
+/// A pull is abandoned when containerd reports no progress for this long.
+const IMAGE_PULL_STALL_TIMEOUT: Duration = Duration::from_secs(300);
+pub(crate) const CONSOLE_BASE_DIR: &str = "/tmp/catalyst-console";
+/// Marker file in a container's console dir recording that its task runs with a PTY.
+const TTY_MARKER_FILE: &str = "tty";
+const DEFAULT_CONSOLE_COLS: u32 = 120;
+const DEFAULT_CONSOLE_ROWS: u32 = 40;
 const PORT_FWD_STATE_DIR: &str = "/var/lib/cni/results";
 
 // CNI plugin directories to search, in order of preference
@@ -91,12 +118,113 @@ pub struct ContainerConfig<'a> {
     pub startup_command: &'a str,
     pub env: &'a HashMap<String, String>,
     pub memory_mb: u64,
-    pub cpu_cores: u64,
+    /// CPU allocation in thousandths of a core.
+    pub cpu_millicores: u64,
     pub data_dir: &'a str,
     pub port: u16,
     pub port_bindings: &'a HashMap<u16, u16>,
     pub network_mode: Option<&'a str>,
     pub network_ip: Option<&'a str>,
+    /// Allocate a PTY for the server process instead of plain stdin/stdout pipes.
+    pub tty: bool,
+    pub limits: ResourceLimits,
+    pub security: SecurityProfile,
+    /// Run in a user namespace mapped to this host id range.
+    pub user_namespace: Option<IdMapping>,
+}
+
+/// Limits beyond memory and CPU quota, taken from the start message. Unset fields leave
+/// the runtime defaults in place.
+#[derive(Clone, Debug, Default)]
+pub struct ResourceLimits {
+    /// Maximum number of processes and threads, against fork bombs.
+    pub pids_max: Option<u64>,
+    /// Bandwidth and IOPS caps applied to the server's data volume device.
+    pub io_read_bps: Option<u64>,
+    pub io_write_bps: Option<u64>,
+    pub io_read_iops: Option<u64>,
+    pub io_write_iops: Option<u64>,
+    /// Host CPUs the server may run on, in cpuset list format (`0-3,6`).
+    pub cpuset_cpus: Option<String>,
+    /// Swap allowed on top of the memory limit; `Some(0)` disables swap.
+    pub swap_mb: Option<u64>,
+    /// Memory protected from reclaim under host pressure (`memory.low`).
+    pub memory_reservation_mb: Option<u64>,
+    /// Relative CPU share under contention (`cpu.weight`, 1-10000, kernel default 100).
+    pub cpu_weight: Option<u64>,
+}
+
+/// Smallest CPU quota the kernel accepts is 1ms per 100ms period.
+const MIN_CPU_MILLICORES: u64 = 10;
+
+/// CPU allocation of a start message in millicores: `allocatedCpuMillicores`, or
+/// `allocatedCpuCores`, which may be fractional.
+pub fn parse_cpu_millicores(msg: &serde_json::Value) -> AgentResult<u64> {
+    let millicores = match msg.get("allocatedCpuMillicores").and_then(|v| v.as_u64()) {
+        Some(millicores) => millicores,
+        None => {
+            let cores = msg
+                .get("allocatedCpuCores")
+                .and_then(|v| v.as_f64())
+                .ok_or_else(|| {
+                    AgentError::InvalidRequest("Missing allocatedCpuCores".to_string())
+                })?;
+            if !cores.is_finite() || cores < 0.0 {
+                return Err(AgentError::InvalidRequest(format!(
+                    "Invalid allocatedCpuCores {}",
+                    cores
+                )));
+            }
+            (cores * 1000.0).round() as u64
+        }
+    };
+    if millicores < MIN_CPU_MILLICORES {
+        return Err(AgentError::InvalidRequest(format!(
+            "CPU allocation must be at least {} millicores",
+            MIN_CPU_MILLICORES
+        )));
+    }
+    Ok(millicores)
+}
+
+impl ResourceLimits {
+    /// Reads `pidsLimit`, `ioReadBps`, `ioWriteBps`, `ioReadIops`, `ioWriteIops`,
+    /// `cpusetCpus`, `swapMb`, `memoryReservationMb` and `cpuWeight`.
+    pub fn from_message(msg: &serde_json::Value) -> AgentResult<Self> {
+        let positive = |key: &str| msg.get(key).and_then(|v| v.as_u64()).filter(|v| *v > 0);
+        let cpuset_cpus = match msg.get("cpusetCpus").and_then(|v| v.as_str()) {
+            Some(cpus) if !cpus.trim().is_empty() => {
+                let cpus = cpus.trim();
+                if !is_valid_cpuset(cpus) {
+                    return Err(AgentError::InvalidRequest(format!(
+                        "Invalid cpusetCpus '{}'",
+                        cpus
+                    )));
+                }
+                Some(cpus.to_string())
+            }
+            _ => None,
+        };
+        Ok(Self {
+            pids_max: positive("pidsLimit"),
+            io_read_bps: positive("ioReadBps"),
+            io_write_bps: positive("ioWriteBps"),
+            io_read_iops: positive("ioReadIops"),
+            io_write_iops: positive("ioWriteIops"),
+            cpuset_cpus,
+            swap_mb: msg.get("swapMb").and_then(|v| v.as_u64()),
+            memory_reservation_mb: positive("memoryReservationMb"),
+            cpu_weight: match msg.get("cpuWeight").and_then(|v| v.as_u64()) {
+                Some(weight) if !(1..=10_000).contains(&weight) => {
+                    return Err(AgentError::InvalidRequest(format!(
+                        "cpuWeight must be between 1 and 10000, got {}",
+                        weight
+                    )));
+                }
+                weight => weight,
+            },
+        })
+    }
 }
 
 struct ContainerIo {
@@ -106,24 +234,192 @@ struct ContainerIo {
     stdin_writer: Option<File>,
 }
 
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ContainerStatus {
+    /// The task exists but its process has not been started.
+    Created,
+    Running,
+    Paused,
+    /// The task exited, or the container has no task at all.
+    Stopped,
+}
+
+impl ContainerStatus {
+    /// Map a containerd task status (`containerd.v1.types.Status`).
+    fn from_task_status(status: i32) -> Self {
+        match status {
+            1 => Self::Created,
+            2 => Self::Running,
+            // PAUSED and PAUSING
+            4 | 5 => Self::Paused,
+            _ => Self::Stopped,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Created => "created",
+            Self::Running => "running",
+            Self::Paused => "paused",
+            Self::Stopped => "stopped",
+        }
+    }
+
+    pub fn is_running(&self) -> bool {
+        *self == Self::Running
+    }
+}
+
 #[derive(Debug)]
 pub struct ContainerInfo {
     pub id: String,
     pub names: String,
     pub managed: bool,
-    pub status: String,
+    pub status: ContainerStatus,
+    /// Exit status of a stopped task that has not been deleted yet.
+    pub exit_code: Option<i32>,
+    /// When the task's init process started, for running and paused tasks.
+    pub started_at: Option<SystemTime>,
     pub command: String,
     pub image: String,
 }
 
-#[derive(Debug)]
+/// Memory pressure counters of a container, read from `memory.events` and `memory.peak`.
+#[derive(Debug, Default)]
+pub struct MemoryEvents {
+    pub oom_kills: u64,
+    /// High-water mark of memory usage; `memory.peak` needs Linux 5.19 or newer.
+    pub peak_bytes: Option<u64>,
+    pub limit_bytes: Option<u64>,
+}
+
+#[derive(Debug, Default)]
 pub struct ContainerStats {
     pub container_id: String,
-    pub container_name: String,
-    pub cpu_percent: String,
-    pub memory_usage: String,
-    pub net_io: String,
-    pub block_io: String,
+    /// Usage as a percentage of the CPUs the container may use (its quota, or all host CPUs).
+    pub cpu_percent: f64,
+    pub cpu_usage_usec: u64,
+    pub memory_usage_bytes: u64,
+    /// `None` when the cgroup has no memory limit.
+    pub memory_limit_bytes: Option<u64>,
+    /// Page cache included in `memory_usage_bytes`.
+    pub memory_cache_bytes: u64,
+    pub net_rx_bytes: u64,
+    pub net_tx_bytes: u64,
+    pub block_read_bytes: u64,
+    pub block_write_bytes: u64,
+    /// Periods in which the CPU quota ran out, and the time spent waiting for the next one.
+    pub cpu_nr_throttled: u64,
+    pub cpu_throttled_usec: u64,
+    /// Pressure stall information; absent on kernels without PSI.
+    pub cpu_pressure: Option<cgroup::Pressure>,
+    pub memory_pressure: Option<cgroup::Pressure>,
+    pub io_pressure: Option<cgroup::Pressure>,
+}
+
+/// Progress of one blob (manifest, config or layer) of an image pull.
+#[derive(Clone, Debug)]
+pub struct ImagePullProgress {
+    pub name: String,
+    /// containerd's event for the blob, e.g. `waiting`, `downloading`, `complete`.
+    pub event: String,
+    pub progress: u64,
+    pub total: u64,
+}
+
+/// An image in the namespace with the blobs it holds, as seen by image GC.
+#[derive(Clone, Debug)]
+pub struct ImageRecord {
+    pub name: String,
+    /// When the image was last pulled or updated.
+    pub updated_at: Option<SystemTime>,
+    /// Digest and size of the manifest, config and layers for this platform.
+    pub blobs: Vec<(String, u64)>,
+}
+
+/// Updates broadcast to every caller waiting on the same in-flight pull.
+#[derive(Clone, Debug)]
+enum PullUpdate {
+    Progress(ImagePullProgress),
+    Finished(Result<(), String>),
+}
+
+/// Image and registry credentials of a pull.
+type PullKey = (String, Option<RegistryCredentials>);
+
+/// Pulls in progress. Callers asking for the same image with the same credentials share
+/// one pull, which runs detached so a caller giving up does not cancel it for the others.
+/// Pulls with other credentials run separately, so an anonymous pull of a private image
+/// cannot fail a caller that has access.
+#[derive(Clone, Default)]
+struct SharedPulls {
+    pulls: Arc<Mutex<HashMap<PullKey, broadcast::Sender<PullUpdate>>>>,
+}
+
+impl SharedPulls {
+    /// Start `pull` for `image` unless one with the same credentials is already running,
+    /// relay the progress of the pull serving this caller to `progress`, and return its
+    /// result.
+    async fn run<F, Fut>(
+        &self,
+        image: &str,
+        credentials: Option<RegistryCredentials>,
+        progress: Option<mpsc::UnboundedSender<ImagePullProgress>>,
+        pull: F,
+    ) -> AgentResult<()>
+    where
+        F: FnOnce(broadcast::Sender<PullUpdate>) -> Fut,
+        Fut: std::future::Future<Output = AgentResult<()>> + Send + 'static,
+    {
+        let key = (image.to_string(), credentials);
+        let mut updates = {
+            let mut pulls = self.pulls.lock().await;
+            match pulls.get(&key) {
+                Some(sender) => {
+                    debug!("Joining in-flight pull of {}", image);
+                    sender.subscribe()
+                }
+                None => {
+                    let (sender, receiver) = broadcast::channel(256);
+                    pulls.insert(key.clone(), sender.clone());
+                    let pull = pull(sender.clone());
+                    let pulls = self.pulls.clone();
+                    tokio::spawn(async move {
+                        let result = pull.await.map_err(|e| e.to_string());
+                        pulls.lock().await.remove(&key);
+                        let _ = sender.send(PullUpdate::Finished(result));
+                    });
+                    receiver
+                }
+            }
+        };
+
+        loop {
+            match updates.recv().await {
+                Ok(PullUpdate::Progress(update)) => {
+                    if let Some(progress) = &progress {
+                        let _ = progress.send(update);
+                    }
+                }
+                Ok(PullUpdate::Finished(result)) => {
+                    return result.map_err(AgentError::ContainerError);
+                }
+                Err(broadcast::error::RecvError::Lagged(_)) => continue,
+                Err(broadcast::error::RecvError::Closed) => {
+                    return Err(AgentError::ContainerError(format!(
+                        "Pull of {} ended without a result",
+                        image
+                    )));
+                }
+            }
+        }
+    }
+
+    async fn images(&self) -> Vec<String> {
+        let pulls = self.pulls.lock().await;
+        let images: HashSet<&String> = pulls.keys().map(|(image, _)| image).collect();
+        images.into_iter().cloned().collect()
+    }
 }
 
 /// Log stream providing async file handles for stdout/stderr
@@ -134,14 +430,28 @@ pub struct LogStream {
 }
 
 impl LogStream {
+    pub fn new(
+        container_id: &str,
+        stdout: Option<tokio::fs::File>,
+        stderr: Option<tokio::fs::File>,
+    ) -> Self {
+        Self {
+            stdout,
+            stderr,
+            container_id: container_id.to_string(),
+        }
+    }
+
     pub fn container_id(&self) -> &str {
         &self.container_id
     }
 }
 
-/// Streaming event receiver from containerd events API
-pub struct EventStream {
-    pub receiver: tonic::Streaming<containerd_client::types::Envelope>,
+/// Handle to a PTY-backed process exec'd inside a running container.
+pub struct InteractiveExec {
+    pub exec_id: String,
+    pub stdin: tokio::net::unix::pipe::Sender,
+    pub stdout: tokio::net::unix::pipe::Receiver,
 }
 
 /// Installer container handle for interactive install scripts
@@ -149,12 +459,21 @@ pub struct InstallerHandle {
     container_id: String,
     namespace: String,
     channel: tonic::transport::Channel,
-    pub stdout_path: PathBuf,
-    pub stderr_path: PathBuf,
+    stdout_path: PathBuf,
+    stderr_path: PathBuf,
 }
 
-impl InstallerHandle {
-    pub async fn wait(&self) -> AgentResult<i32> {
+#[async_trait::async_trait]
+impl InstallerProcess for InstallerHandle {
+    fn stdout_path(&self) -> &Path {
+        &self.stdout_path
+    }
+
+    fn stderr_path(&self) -> &Path {
+        &self.stderr_path
+    }
+
+    async fn wait(&self) -> AgentResult<i32> {
         let mut tasks = TasksClient::new(self.channel.clone());
         let req = WaitRequest {
             container_id: self.container_id.clone(),
@@ -165,7 +484,7 @@ impl InstallerHandle {
         Ok(resp.into_inner().exit_status as i32)
     }
 
-    pub async fn cleanup(&self) -> AgentResult<()> {
+    async fn cleanup(&self) -> AgentResult<()> {
         let mut tasks = TasksClient::new(self.channel.clone());
         let req = DeleteTaskRequest {
             container_id: self.container_id.clone(),
@@ -201,6 +520,15 @@ pub struct ContainerdRuntime {
     channel: tonic::transport::Channel,
     container_io: Arc<Mutex<HashMap<String, ContainerIo>>>,
     dns_servers: Vec<String>,
+    /// Last `cpu.stat` usage sample per container, for delta-based CPU percentages.
+    cpu_samples: Arc<std::sync::Mutex<HashMap<String, (Instant, u64)>>>,
+    /// cgroup directory per container, resolved once from its spec's `cgroupsPath`.
+    cgroup_paths: Arc<std::sync::Mutex<HashMap<String, PathBuf>>>,
+    images: ImageConfig,
+    /// In-flight pulls by qualified reference, so concurrent starts share one pull.
+    image_pulls: SharedPulls,
+    /// `repository@digest` references whose signature passed the image policy.
+    verified_images: Arc<Mutex<HashSet<String>>>,
 }
 
 impl ContainerdRuntime {
@@ -209,6 +537,7 @@ impl ContainerdRuntime {
         socket_path: PathBuf,
         namespace: String,
         dns_servers: Vec<String>,
+        images: ImageConfig,
     ) -> AgentResult<Self> {
         let channel = containerd_client::connect(&socket_path)
             .await
@@ -227,6 +556,11 @@ impl ContainerdRuntime {
             channel,
             container_io: Arc::new(Mutex::new(HashMap::new())),
             dns_servers,
+            cpu_samples: Arc::new(std::sync::Mutex::new(HashMap::new())),
+            cgroup_paths: Arc::new(std::sync::Mutex::new(HashMap::new())),
+            images,
+            image_pulls: SharedPulls::default(),
+            verified_images: Arc::new(Mutex::new(HashSet::new())),
         })
     }
 
@@ -239,6 +573,7 @@ impl ContainerdRuntime {
         );
 
         self.ensure_image(config.image).await?;
+        let qualified_image = self.pin_image(&qualified_image).await?;
 
         // Read image's default environment variables (PATH, JAVA_HOME, etc.)
         let image_env = self.get_image_env(&qualified_image).await;
@@ -264,6 +599,14 @@ impl ContainerdRuntime {
         File::create(&stderr_path)
             .map_err(|e| AgentError::ContainerError(format!("stderr: {}", e)))?;
 
+        let tty_marker = io_dir.join(TTY_MARKER_FILE);
+        if config.tty {
+            File::create(&tty_marker)
+                .map_err(|e| AgentError::ContainerError(format!("tty marker: {}", e)))?;
+        } else if tty_marker.exists() {
+            fs::remove_file(&tty_marker).ok();
+        }
+
         let stdin_writer = open_fifo_rdwr(&stdin_path)?;
         {
             let mut io_map = self.container_io.lock().await;
@@ -314,11 +657,17 @@ impl ContainerdRuntime {
         // Get rootfs mounts and create task
         let mounts = self.get_snapshot_mounts(&snap_key).await?;
         let mut tasks = TasksClient::new(self.channel.clone());
+        // With a terminal the shim multiplexes stdout and stderr onto the PTY master.
         let req = CreateTaskRequest {
             container_id: config.container_id.to_string(),
             stdin: stdin_path.to_string_lossy().to_string(),
             stdout: stdout_path.to_string_lossy().to_string(),
-            stderr: stderr_path.to_string_lossy().to_string(),
+            stderr: if config.tty {
+                String::new()
+            } else {
+                stderr_path.to_string_lossy().to_string()
+            },
+            terminal: config.tty,
             rootfs: mounts,
             ..Default::default()
         };
@@ -435,6 +784,8 @@ impl ContainerdRuntime {
         script: &str,
         env: &HashMap<String, String>,
         data_dir: &str,
+        uid: u32,
+        gid: u32,
     ) -> AgentResult<InstallerHandle> {
         let container_id = format!("catalyst-installer-{}", uuid::Uuid::new_v4());
         let qualified_image = Self::qualify_image_ref(image);
@@ -443,6 +794,7 @@ impl ContainerdRuntime {
             container_id, qualified_image
         );
         self.ensure_image(image).await?;
+        let qualified_image = self.pin_image(&qualified_image).await?;
 
         let io_dir = PathBuf::from(CONSOLE_BASE_DIR).join(&container_id);
         fs::create_dir_all(&io_dir)
@@ -500,12 +852,12 @@ impl ContainerdRuntime {
             "options": ["rbind", "rw"]
         }));
 
-        // Wrap the install script so all files are chowned to the runtime user (1000:1000)
-        // after the user-provided script completes. The installer runs as root but the
-        // runtime container runs as 1000:1000, so files must be accessible.
+        // Wrap the install script so all files are chowned to the runtime user after the
+        // user-provided script completes. The installer runs as root but the runtime
+        // container does not, so files must be accessible.
         let wrapped_script = format!(
-            "{}\n\necho '[Catalyst] Fixing file ownership for runtime user...'\nchown -R 1000:1000 /data",
-            script
+            "{}\n\necho '[Catalyst] Fixing file ownership for runtime user...'\nchown -R {}:{} /data",
+            script, uid, gid
         );
 
         let spec = serde_json::json!({
@@ -631,12 +983,27 @@ impl ContainerdRuntime {
             .await
             .unwrap_or_default();
         let io_dir = PathBuf::from(CONSOLE_BASE_DIR).join(container_id);
+        let tty = self.is_tty(container_id);
+        // Start every run with an empty console so the previous run's output, such as its
+        // startup line, is not replayed to the log stream.
+        for name in ["stdout", "stderr"] {
+            let path = io_dir.join(name);
+            if path.exists() {
+                File::create(&path)
+                    .map_err(|e| AgentError::ContainerError(format!("{}: {}", name, e)))?;
+            }
+        }
 
         let req = CreateTaskRequest {
             container_id: container_id.to_string(),
             stdin: io_dir.join("stdin").to_string_lossy().to_string(),
             stdout: io_dir.join("stdout").to_string_lossy().to_string(),
-            stderr: io_dir.join("stderr").to_string_lossy().to_string(),
+            stderr: if tty {
+                String::new()
+            } else {
+                io_dir.join("stderr").to_string_lossy().to_string()
+            },
+            terminal: tty,
             rootfs: mounts,
             ..Default::default()
         };
@@ -807,6 +1174,7 @@ impl ContainerdRuntime {
 
     pub async fn remove_container(&self, container_id: &str) -> AgentResult<()> {
         info!("Removing container: {}", container_id);
+        self.forget_container_caches(container_id);
         let _ = self.teardown_cni_network(container_id).await;
         let mut tasks = TasksClient::new(self.channel.clone());
         let req = TaskKillRequest {
@@ -851,6 +1219,12 @@ impl ContainerdRuntime {
     // -- Console I/O --
 
     pub async fn send_input(&self, container_id: &str, input: &str) -> AgentResult<()> {
+        self.send_input_bytes(container_id, input.as_bytes()).await
+    }
+
+    /// Write raw bytes to the container's stdin. For PTY consoles this carries control
+    /// sequences (Ctrl+C, arrow keys) straight to the terminal line discipline.
+    pub async fn send_input_bytes(&self, container_id: &str, input: &[u8]) -> AgentResult<()> {
         debug!("Sending input to container: {}", container_id);
         if !self
             .is_container_running(container_id)
@@ -870,10 +1244,10 @@ impl ContainerdRuntime {
                 .and_then(|io| io.stdin_writer.as_ref().and_then(|w| w.try_clone().ok()))
         };
         if let Some(h) = handle {
-            let input = input.to_string();
+            let input = input.to_vec();
             spawn_blocking(move || {
                 let mut w = h;
-                w.write_all(input.as_bytes())
+                w.write_all(&input)
                     .map_err(|e| AgentError::ContainerError(format!("stdin: {}", e)))?;
                 let _ = w.flush();
                 Ok::<(), AgentError>(())
@@ -883,6 +1257,15 @@ impl ContainerdRuntime {
             return Ok(());
         }
 
+        if self.is_tty(container_id) {
+            // PID 1's fd 0 is the PTY slave here; writing to it would echo to the
+            // console rather than deliver input, so the exec fallback cannot help.
+            return Err(AgentError::ContainerError(format!(
+                "Cannot send input: console FIFO for {} is unavailable",
+                container_id
+            )));
+        }
+
         if !has_io {
             warn!(
                 "No stdin FIFO found for {}, falling back to exec-based stdin injection",
@@ -924,13 +1307,13 @@ impl ContainerdRuntime {
         let req = with_namespace!(req, &self.namespace);
         tasks.start(req).await.map_err(grpc_err)?;
         let epc = ep.clone();
-        let input_owned = input.to_string();
+        let input_owned = input.to_vec();
         spawn_blocking(move || -> AgentResult<()> {
             let mut f = std::fs::OpenOptions::new()
                 .write(true)
                 .open(&epc)
                 .map_err(|e| AgentError::ContainerError(format!("stdin fallback open: {}", e)))?;
-            f.write_all(input_owned.as_bytes())
+            f.write_all(&input_owned)
                 .map_err(|e| AgentError::ContainerError(format!("stdin fallback write: {}", e)))?;
             Ok(())
         })
@@ -946,7 +1329,7 @@ impl ContainerdRuntime {
         let containers = self.list_containers().await?;
         let mut restored = 0;
         for c in containers {
-            if !c.status.contains("Up") {
+            if !c.status.is_running() {
                 continue;
             }
             if self.ensure_container_io(&c.id).await.is_ok() {
@@ -957,6 +1340,39 @@ impl ContainerdRuntime {
         Ok(())
     }
 
+    /// Whether the container's task was created with a PTY console.
+    pub fn is_tty(&self, container_id: &str) -> bool {
+        PathBuf::from(CONSOLE_BASE_DIR)
+            .join(container_id)
+            .join(TTY_MARKER_FILE)
+            .exists()
+    }
+
+    /// Propagate a console window size change to the task's PTY.
+    pub async fn resize_console(
+        &self,
+        container_id: &str,
+        cols: u32,
+        rows: u32,
+    ) -> AgentResult<()> {
+        if !self.is_tty(container_id) {
+            return Err(AgentError::InvalidRequest(format!(
+                "Container {} does not have a PTY console",
+                container_id
+            )));
+        }
+        let mut tasks = TasksClient::new(self.channel.clone());
+        let req = ResizePtyRequest {
+            container_id: container_id.to_string(),
+            exec_id: String::new(),
+            width: cols,
+            height: rows,
+        };
+        let req = with_namespace!(req, &self.namespace);
+        tasks.resize_pty(req).await.map_err(grpc_err)?;
+        Ok(())
+    }
+
     // -- Logs --
 
     pub async fn get_logs(&self, container_id: &str, lines: Option<u32>) -> AgentResult<String> {
@@ -1038,18 +1454,47 @@ impl ContainerdRuntime {
         };
         let req = with_namespace!(req, &self.namespace);
         let resp = client.list(req).await.map_err(grpc_err)?;
+
+        // One task listing for all containers instead of a lookup per container.
+        let mut tasks = TasksClient::new(self.channel.clone());
+        let req = with_namespace!(ListTasksRequest::default(), &self.namespace);
+        let tasks: HashMap<String, _> = tasks
+            .list(req)
+            .await
+            .map_err(grpc_err)?
+            .into_inner()
+            .tasks
+            .into_iter()
+            .map(|task| (task.container_id.clone(), task))
+            .collect();
+        let boot_time = fs::read_to_string("/proc/stat")
+            .ok()
+            .and_then(|stat| cgroup::parse_boot_time(&stat));
+
         let mut result = Vec::new();
         for c in resp.into_inner().containers {
-            let running = self.is_container_running(&c.id).await.unwrap_or(false);
+            let task = tasks.get(&c.id);
+            let status = task
+                .map(|task| ContainerStatus::from_task_status(task.status))
+                .unwrap_or(ContainerStatus::Stopped);
+            let exit_code = task
+                .filter(|_| status == ContainerStatus::Stopped)
+                .map(|task| task.exit_status as i32);
+            let started_at = match (task, boot_time) {
+                (Some(task), Some(boot_time))
+                    if task.pid > 0 && status != ContainerStatus::Stopped =>
+                {
+                    process_start_time(task.pid, boot_time)
+                }
+                _ => None,
+            };
             result.push(ContainerInfo {
                 id: c.id.clone(),
                 names: c.id.clone(),
                 managed: c.labels.contains_key("catalyst.managed"),
-                status: if running {
-                    "Up".to_string()
-                } else {
-                    "Exited".to_string()
-                },
+                status,
+                exit_code,
+                started_at,
                 image: c.image.clone(),
                 command: String::new(),
             });
@@ -1084,6 +1529,48 @@ impl ContainerdRuntime {
         }
     }
 
+    /// Status of the container's task; `Stopped` when there is no task.
+    pub async fn container_status(&self, container_id: &str) -> AgentResult<ContainerStatus> {
+        let mut tasks = TasksClient::new(self.channel.clone());
+        let req = containerd_client::services::v1::GetRequest {
+            container_id: container_id.to_string(),
+            ..Default::default()
+        };
+        let req = with_namespace!(req, &self.namespace);
+        match tasks.get(req).await {
+            Ok(resp) => Ok(resp
+                .into_inner()
+                .process
+                .map(|p| ContainerStatus::from_task_status(p.status))
+                .unwrap_or(ContainerStatus::Stopped)),
+            Err(e) if e.code() == tonic::Code::NotFound => Ok(ContainerStatus::Stopped),
+            Err(e) => Err(grpc_err(e)),
+        }
+    }
+
+    /// Freeze every process of the task with the cgroup freezer.
+    pub async fn pause_container(&self, container_id: &str) -> AgentResult<()> {
+        let mut tasks = TasksClient::new(self.channel.clone());
+        let req = PauseTaskRequest {
+            container_id: container_id.to_string(),
+        };
+        let req = with_namespace!(req, &self.namespace);
+        tasks.pause(req).await.map_err(grpc_err)?;
+        info!("Paused container {}", container_id);
+        Ok(())
+    }
+
+    pub async fn resume_container(&self, container_id: &str) -> AgentResult<()> {
+        let mut tasks = TasksClient::new(self.channel.clone());
+        let req = ResumeTaskRequest {
+            container_id: container_id.to_string(),
+        };
+        let req = with_namespace!(req, &self.namespace);
+        tasks.resume(req).await.map_err(grpc_err)?;
+        info!("Resumed container {}", container_id);
+        Ok(())
+    }
+
     pub async fn get_container_exit_code(&self, container_id: &str) -> AgentResult<Option<i32>> {
         let mut tasks = TasksClient::new(self.channel.clone());
         let req = containerd_client::services::v1::GetRequest {
@@ -1146,28 +1633,268 @@ impl ContainerdRuntime {
 
     // -- Stats (cgroup v2) --
 
+    /// Cumulative CPU time consumed by the container, in microseconds.
+    pub async fn cpu_usage_usec(&self, container_id: &str) -> Option<u64> {
+        let cg = self.container_cgroup(container_id).await?;
+        read_cpu_usage_usec(&cg).await
+    }
+
+    /// The OCI spec the container was created with.
+    async fn container_spec(&self, container_id: &str) -> Option<serde_json::Value> {
+        let mut client = ContainersClient::new(self.channel.clone());
+        let req = GetContainerRequest {
+            id: container_id.to_string(),
+        };
+        let req = with_namespace!(req, &self.namespace);
+        let spec = client.get(req).await.ok()?.into_inner().container?.spec?;
+        serde_json::from_slice(&spec.value).ok()
+    }
+
+    /// The container's cgroup directory. Resolved from the spec on first use and cached;
+    /// task Metrics would need the cgroups v2 protobuf types, which containerd-client lacks.
+    async fn container_cgroup(&self, container_id: &str) -> Option<PathBuf> {
+        let cached = self
+            .cgroup_paths
+            .lock()
+            .ok()
+            .and_then(|paths| paths.get(container_id).cloned());
+        if let Some(path) = cached {
+            if path.is_dir() {
+                return Some(path);
+            }
+        }
+
+        let spec = self.container_spec(container_id).await?;
+        let path = cgroup::resolve_cgroups_path(
+            Path::new(cgroup::CGROUP_ROOT),
+            spec["linux"]["cgroupsPath"].as_str()?,
+        )?;
+        if !path.is_dir() {
+            return None;
+        }
+        if let Ok(mut paths) = self.cgroup_paths.lock() {
+            paths.insert(container_id.to_string(), path.clone());
+        }
+        Some(path)
+    }
+
+    /// Apply a new memory, CPU, pids and I/O allocation to the running task. Shrinks that
+    /// would immediately starve the server are refused.
+    pub async fn update_resources(
+        &self,
+        container_id: &str,
+        memory_mb: u64,
+        cpu_millicores: u64,
+        limits: &ResourceLimits,
+        data_dir: &Path,
+    ) -> AgentResult<()> {
+        if !self.is_container_running(container_id).await? {
+            return Err(AgentError::InvalidRequest(format!(
+                "Container {} is not running",
+                container_id
+            )));
+        }
+        let memory_bytes = mb_to_bytes(memory_mb).ok_or_else(|| {
+            AgentError::InvalidRequest(format!("Memory limit {}MB is too large", memory_mb))
+        })?;
+        if limits
+            .swap_mb
+            .is_some_and(|swap_mb| mb_to_bytes(memory_mb.saturating_add(swap_mb)).is_none())
+        {
+            return Err(AgentError::InvalidRequest(format!(
+                "Swap limit for {}MB of memory is too large",
+                memory_mb
+            )));
+        }
+        // Without current usage a shrink cannot be checked, so refuse rather than risk
+        // starving the server.
+        let cg = self.container_cgroup(container_id).await.ok_or_else(|| {
+            AgentError::ContainerError(format!(
+                "Cannot read resource usage of {}: cgroup not found",
+                container_id
+            ))
+        })?;
+        let memory_current = read_cgroup_file(&cg, "memory.current")
+            .await
+            .and_then(|content| content.trim().parse::<u64>().ok())
+            .ok_or_else(|| {
+                AgentError::ContainerError(format!("Cannot read memory usage of {}", container_id))
+            })?;
+        if memory_bytes < memory_current {
+            return Err(AgentError::InvalidRequest(format!(
+                "Memory limit {}MB is below current usage of {}MB",
+                memory_mb,
+                memory_current / (1024 * 1024)
+            )));
+        }
+        if let Some(limit) = limits.pids_max {
+            let pids_current = read_cgroup_file(&cg, "pids.current")
+                .await
+                .and_then(|content| content.trim().parse::<u64>().ok())
+                .ok_or_else(|| {
+                    AgentError::ContainerError(format!(
+                        "Cannot read process count of {}",
+                        container_id
+                    ))
+                })?;
+            if limit < pids_current {
+                return Err(AgentError::InvalidRequest(format!(
+                    "Pids limit {} is below the {} processes currently running",
+                    limit, pids_current
+                )));
+            }
+        }
+
+        let resources = oci_resources(memory_mb, cpu_millicores, limits, data_dir);
+        let mut tasks = TasksClient::new(self.channel.clone());
+        let req = UpdateTaskRequest {
+            container_id: container_id.to_string(),
+            resources: Some(Any {
+                type_url: RESOURCES_TYPE_URL.to_string(),
+                value: serde_json::to_vec(&resources)?,
+            }),
+            ..Default::default()
+        };
+        let req = with_namespace!(req, &self.namespace);
+        tasks.update(req).await.map_err(grpc_err)?;
+        info!(
+            "Updated resources for {}: {}MB memory, {}m CPU",
+            container_id, memory_mb, cpu_millicores
+        );
+        Ok(())
+    }
+
+    /// OOM kill count and peak memory of the container's cgroup. Only available until the
+    /// task is deleted, since the cgroup goes away with it.
+    pub async fn memory_events(&self, container_id: &str) -> Option<MemoryEvents> {
+        let cg = self.container_cgroup(container_id).await?;
+        let events = read_cgroup_file(&cg, "memory.events").await?;
+        Some(MemoryEvents {
+            oom_kills: cgroup::parse_flat_keyed(&events)
+                .get("oom_kill")
+                .copied()
+                .unwrap_or(0),
+            peak_bytes: read_cgroup_file(&cg, "memory.peak")
+                .await
+                .and_then(|content| cgroup::parse_limit(&content)),
+            limit_bytes: read_cgroup_file(&cg, "memory.max")
+                .await
+                .and_then(|content| cgroup::parse_limit(&content)),
+        })
+    }
+
+    fn forget_container_caches(&self, container_id: &str) {
+        if let Ok(mut paths) = self.cgroup_paths.lock() {
+            paths.remove(container_id);
+        }
+        if let Ok(mut samples) = self.cpu_samples.lock() {
+            samples.remove(container_id);
+        }
+    }
+
     pub async fn get_stats(&self, container_id: &str) -> AgentResult<ContainerStats> {
-        let cg = find_container_cgroup(container_id).unwrap_or_default();
-        let cpu = if !cg.is_empty() {
-            read_cgroup_cpu_percent(&cg).await.unwrap_or(0.0)
-        } else {
-            0.0
+        let cg = self.container_cgroup(container_id).await.ok_or_else(|| {
+            AgentError::NotFound(format!("cgroup for container {} not found", container_id))
+        })?;
+
+        let mut usage_usec = read_cpu_usage_usec(&cg).await.unwrap_or(0);
+        let mut sampled_at = Instant::now();
+        let previous = self
+            .cpu_samples
+            .lock()
+            .ok()
+            .and_then(|samples| samples.get(container_id).copied())
+            .filter(|(at, _)| sampled_at.duration_since(*at) < Duration::from_secs(300));
+        // Without a recent sample, take a short one so the first report is meaningful.
+        let (previous_at, previous_usec) = match previous {
+            Some(sample) => sample,
+            None => {
+                let first = (sampled_at, usage_usec);
+                tokio::time::sleep(Duration::from_millis(250)).await;
+                usage_usec = read_cpu_usage_usec(&cg).await.unwrap_or(usage_usec);
+                sampled_at = Instant::now();
+                first
+            }
         };
-        let mem = if !cg.is_empty() {
-            read_cgroup_memory(&cg).await.unwrap_or(0)
-        } else {
-            0
+        if let Ok(mut samples) = self.cpu_samples.lock() {
+            samples.insert(container_id.to_string(), (sampled_at, usage_usec));
+        }
+        let cpus = match read_cgroup_file(&cg, "cpu.max").await {
+            Some(content) => cgroup::parse_cpu_max(&content),
+            None => None,
+        }
+        .unwrap_or_else(|| {
+            std::thread::available_parallelism()
+                .map(|n| n.get() as f64)
+                .unwrap_or(1.0)
+        });
+        let cpu_percent = cgroup::cpu_percent(
+            previous_usec,
+            usage_usec,
+            sampled_at.duration_since(previous_at).as_micros() as u64,
+            cpus,
+        );
+
+        let memory_usage_bytes = read_cgroup_file(&cg, "memory.current")
+            .await
+            .and_then(|content| content.trim().parse().ok())
+            .unwrap_or(0);
+        let memory_limit_bytes = read_cgroup_file(&cg, "memory.max")
+            .await
+            .and_then(|content| cgroup::parse_limit(&content));
+        let memory_cache_bytes = read_cgroup_file(&cg, "memory.stat")
+            .await
+            .and_then(|content| cgroup::parse_flat_keyed(&content).get("file").copied())
+            .unwrap_or(0);
+        let (block_read_bytes, block_write_bytes) = read_cgroup_file(&cg, "io.stat")
+            .await
+            .map(|content| cgroup::parse_io_stat(&content))
+            .unwrap_or((0, 0));
+        let (net_rx_bytes, net_tx_bytes) = match self.task_pid(container_id).await {
+            Some(pid) => read_netns_counters(pid).await.unwrap_or((0, 0)),
+            None => (0, 0),
         };
+        let (cpu_nr_throttled, cpu_throttled_usec) = read_cgroup_file(&cg, "cpu.stat")
+            .await
+            .map(|content| {
+                let stat = cgroup::parse_flat_keyed(&content);
+                (
+                    stat.get("nr_throttled").copied().unwrap_or(0),
+                    stat.get("throttled_usec").copied().unwrap_or(0),
+                )
+            })
+            .unwrap_or((0, 0));
+
         Ok(ContainerStats {
             container_id: container_id.to_string(),
-            container_name: container_id.to_string(),
-            cpu_percent: format!("{:.2}%", cpu),
-            memory_usage: format!("{}MiB / 0MiB", mem / (1024 * 1024)),
-            net_io: "0B / 0B".to_string(),
-            block_io: "0B / 0B".to_string(),
+            cpu_percent,
+            cpu_usage_usec: usage_usec,
+            memory_usage_bytes,
+            memory_limit_bytes,
+            memory_cache_bytes,
+            net_rx_bytes,
+            net_tx_bytes,
+            block_read_bytes,
+            block_write_bytes,
+            cpu_nr_throttled,
+            cpu_throttled_usec,
+            cpu_pressure: read_pressure(&cg, "cpu.pressure").await,
+            memory_pressure: read_pressure(&cg, "memory.pressure").await,
+            io_pressure: read_pressure(&cg, "io.pressure").await,
         })
     }
 
+    async fn task_pid(&self, container_id: &str) -> Option<u32> {
+        let mut tasks = TasksClient::new(self.channel.clone());
+        let req = containerd_client::services::v1::GetRequest {
+            container_id: container_id.to_string(),
+            ..Default::default()
+        };
+        let req = with_namespace!(req, &self.namespace);
+        let process = tasks.get(req).await.ok()?.into_inner().process?;
+        (process.pid != 0).then_some(process.pid)
+    }
+
     pub async fn exec(&self, container_id: &str, command: Vec<&str>) -> AgentResult<String> {
         let exec_id = format!("exec-{}", &uuid::Uuid::new_v4().to_string()[..8]);
         let io_dir = PathBuf::from(CONSOLE_BASE_DIR).join(container_id);
@@ -1219,40 +1946,217 @@ impl ContainerdRuntime {
         Ok(out)
     }
 
-    // -- Events --
-
-    pub async fn subscribe_to_container_events(
+    /// Start a process with a PTY inside a running container. Stdin and stdout are FIFOs
+    /// held open read-write by the agent so the stream survives the shim reopening them.
+    pub async fn start_interactive_exec(
         &self,
         container_id: &str,
-    ) -> AgentResult<EventStream> {
-        let mut client = EventsClient::new(self.channel.clone());
-        let req = SubscribeRequest {
-            filters: vec![
-                format!("topic==/tasks/exit,container=={}", container_id),
-                format!("topic==/tasks/start,container=={}", container_id),
-                format!("topic==/tasks/delete,container=={}", container_id),
+        args: &[String],
+        cols: u32,
+        rows: u32,
+    ) -> AgentResult<InteractiveExec> {
+        let exec_id = format!("shell-{}", &uuid::Uuid::new_v4().to_string()[..8]);
+        let io_dir = PathBuf::from(CONSOLE_BASE_DIR).join(container_id);
+        fs::create_dir_all(&io_dir).map_err(|e| {
+            AgentError::ContainerError(format!("Failed to create I/O directory: {}", e))
+        })?;
+        let stdin_path = io_dir.join(format!("{}-in", exec_id));
+        let stdout_path = io_dir.join(format!("{}-out", exec_id));
+        for path in [&stdin_path, &stdout_path] {
+            create_fifo(path)
+                .map_err(|e| AgentError::ContainerError(format!("exec fifo: {}", e)))?;
+        }
+        let stdin = tokio::net::unix::pipe::OpenOptions::new()
+            .read_write(true)
+            .open_sender(&stdin_path)
+            .map_err(|e| AgentError::ContainerError(format!("exec stdin: {}", e)))?;
+        let stdout = tokio::net::unix::pipe::OpenOptions::new()
+            .read_write(true)
+            .open_receiver(&stdout_path)
+            .map_err(|e| AgentError::ContainerError(format!("exec stdout: {}", e)))?;
+
+        // Run as the server process's user, which depends on the container's profile.
+        let user = self
+            .container_spec(container_id)
+            .await
+            .map(|spec| spec["process"]["user"].clone())
+            .filter(|user| user["uid"].is_u64())
+            .unwrap_or_else(
+                || serde_json::json!({"uid": DEFAULT_CONTAINER_UID, "gid": DEFAULT_CONTAINER_GID}),
+            );
+        let spec = serde_json::json!({
+            "terminal": true,
+            "consoleSize": {"height": rows, "width": cols},
+            "user": user,
+            "args": args,
+            "env": [
+                "PATH=/opt/java/openjdk/bin:/usr/local/sbin:/usr/local/bin:/usr/sbin:/usr/bin:/sbin:/bin",
+                "TERM=xterm-256color",
+                "HOME=/data"
             ],
+            "cwd": "/data",
+            "noNewPrivileges": true
+        });
+        let spec_any = Any {
+            type_url: "types.containerd.io/opencontainers/runtime-spec/1/Process".to_string(),
+            value: spec.to_string().into_bytes(),
+        };
+        let mut tasks = TasksClient::new(self.channel.clone());
+        let req = ExecProcessRequest {
+            container_id: container_id.to_string(),
+            exec_id: exec_id.clone(),
+            stdin: stdin_path.to_string_lossy().to_string(),
+            stdout: stdout_path.to_string_lossy().to_string(),
+            stderr: String::new(),
+            terminal: true,
+            spec: Some(spec_any),
+        };
+        let req = with_namespace!(req, &self.namespace);
+        if let Err(e) = tasks.exec(req).await {
+            remove_exec_fifos(container_id, &exec_id);
+            return Err(grpc_err(e));
+        }
+        let req = StartRequest {
+            container_id: container_id.to_string(),
+            exec_id: exec_id.clone(),
         };
         let req = with_namespace!(req, &self.namespace);
-        let resp = client.subscribe(req).await.map_err(grpc_err)?;
-        Ok(EventStream {
-            receiver: resp.into_inner(),
+        if let Err(e) = tasks.start(req).await {
+            self.cleanup_exec(container_id, &exec_id).await;
+            return Err(grpc_err(e));
+        }
+
+        Ok(InteractiveExec {
+            exec_id,
+            stdin,
+            stdout,
         })
     }
 
+    pub async fn resize_exec(
+        &self,
+        container_id: &str,
+        exec_id: &str,
+        cols: u32,
+        rows: u32,
+    ) -> AgentResult<()> {
+        let mut tasks = TasksClient::new(self.channel.clone());
+        let req = ResizePtyRequest {
+            container_id: container_id.to_string(),
+            exec_id: exec_id.to_string(),
+            width: cols,
+            height: rows,
+        };
+        let req = with_namespace!(req, &self.namespace);
+        tasks.resize_pty(req).await.map_err(grpc_err)?;
+        Ok(())
+    }
+
+    pub async fn wait_exec(&self, container_id: &str, exec_id: &str) -> AgentResult<i32> {
+        let mut tasks = TasksClient::new(self.channel.clone());
+        let req = WaitRequest {
+            container_id: container_id.to_string(),
+            exec_id: exec_id.to_string(),
+        };
+        let req = with_namespace!(req, &self.namespace);
+        let resp = tasks.wait(req).await.map_err(grpc_err)?;
+        Ok(resp.into_inner().exit_status as i32)
+    }
+
+    pub async fn kill_exec(
+        &self,
+        container_id: &str,
+        exec_id: &str,
+        signal: &str,
+    ) -> AgentResult<()> {
+        let mut tasks = TasksClient::new(self.channel.clone());
+        let req = TaskKillRequest {
+            container_id: container_id.to_string(),
+            exec_id: exec_id.to_string(),
+            signal: parse_signal(signal),
+            all: false,
+        };
+        let req = with_namespace!(req, &self.namespace);
+        if let Err(e) = tasks.kill(req).await {
+            if !is_not_found(&e) {
+                return Err(grpc_err(e));
+            }
+        }
+        Ok(())
+    }
+
+    /// Remove an exec'd process from the task and delete its FIFOs.
+    pub async fn cleanup_exec(&self, container_id: &str, exec_id: &str) {
+        let mut tasks = TasksClient::new(self.channel.clone());
+        let req = DeleteProcessRequest {
+            container_id: container_id.to_string(),
+            exec_id: exec_id.to_string(),
+        };
+        let req = with_namespace!(req, &self.namespace);
+        let _ = tasks.delete_process(req).await;
+        remove_exec_fifos(container_id, exec_id);
+    }
+
+    // -- Events --
+
+    pub async fn subscribe_to_container_events(
+        &self,
+        container_id: &str,
+    ) -> AgentResult<EventStream> {
+        let req = SubscribeRequest {
+            filters: vec![
+                format!("topic==/tasks/exit,container=={}", container_id),
+                format!("topic==/tasks/oom,container=={}", container_id),
+                format!("topic==/tasks/start,container=={}", container_id),
+                format!("topic==/tasks/delete,container=={}", container_id),
+            ],
+        };
+        self.subscribe(req).await
+    }
+
     pub async fn subscribe_to_all_events(&self) -> AgentResult<EventStream> {
-        let mut client = EventsClient::new(self.channel.clone());
         let req = SubscribeRequest {
             filters: vec![
                 "topic~=/tasks/".to_string(),
                 "topic~=/containers/".to_string(),
             ],
         };
+        self.subscribe(req).await
+    }
+
+    /// Relay a containerd event subscription as runtime events until either side closes.
+    async fn subscribe(&self, req: SubscribeRequest) -> AgentResult<EventStream> {
+        let mut client = EventsClient::new(self.channel.clone());
         let req = with_namespace!(req, &self.namespace);
-        let resp = client.subscribe(req).await.map_err(grpc_err)?;
-        Ok(EventStream {
-            receiver: resp.into_inner(),
-        })
+        let mut envelopes = client.subscribe(req).await.map_err(grpc_err)?.into_inner();
+        let (tx, rx) = mpsc::channel(64);
+        tokio::spawn(async move {
+            loop {
+                // Drop the subscription as soon as the consumer goes away.
+                let envelope = tokio::select! {
+                    message = envelopes.message() => match message {
+                        Ok(Some(envelope)) => envelope,
+                        _ => break,
+                    },
+                    _ = tx.closed() => break,
+                };
+                if envelope.topic.is_empty() {
+                    continue;
+                }
+                let event = RuntimeEvent {
+                    container_id: envelope
+                        .event
+                        .as_ref()
+                        .and_then(extract_container_id_from_event)
+                        .unwrap_or_default(),
+                    topic: envelope.topic,
+                };
+                if tx.send(event).await.is_err() {
+                    break;
+                }
+            }
+        });
+        Ok(EventStream::new(rx))
     }
 
     // -- IP allocation --
@@ -1268,7 +2172,7 @@ impl ContainerdRuntime {
         let mut active_ips = HashSet::new();
         let mut running = 0;
         for c in containers {
-            if !c.status.contains("Up") {
+            if !c.status.is_running() {
                 continue;
             }
             running += 1;
@@ -1353,25 +2257,553 @@ impl ContainerdRuntime {
     }
 
     async fn ensure_image(&self, image: &str) -> AgentResult<()> {
+        self.pull_image(image, &[], PullPolicy::IfNotPresent, None)
+            .await
+    }
+
+    /// Make `image` available under the node image policy, pulling it when missing or
+    /// when `pull_policy` asks to re-resolve its tag, and reporting per-blob progress.
+    /// `credentials` pushed by the backend take precedence over the configured ones.
+    pub async fn pull_image(
+        &self,
+        image: &str,
+        credentials: &[RegistryCredentials],
+        pull_policy: PullPolicy,
+        progress: Option<mpsc::UnboundedSender<ImagePullProgress>>,
+    ) -> AgentResult<()> {
         let qualified = Self::qualify_image_ref(image);
+        self.images.policy.check(&qualified)?;
+        let host = registry_host(&qualified);
+        let credentials = credentials
+            .iter()
+            .chain(&self.images.registries)
+            .find(|credentials| credentials.matches(host))
+            .cloned();
+
+        let exists = self.image_exists(&qualified).await?;
+        let pinned = image_policy::split_reference(&qualified).1.is_some();
+        if !exists || (pull_policy == PullPolicy::Always && !pinned) {
+            match self
+                .fetch_image(&qualified, credentials.clone(), progress)
+                .await
+            {
+                Err(e) if exists => {
+                    warn!(
+                        "Could not re-resolve {}, using the local image: {}",
+                        qualified, e
+                    );
+                }
+                result => result?,
+            }
+        }
+
+        if let Some(public_key) = self.images.policy.signature_key(&qualified) {
+            self.verify_signature(&qualified, public_key, credentials.as_ref())
+                .await?;
+        }
+        Ok(())
+    }
+
+    /// Pull `qualified` from its registry. Concurrent callers for the same image and
+    /// credentials share a single pull and all receive its progress.
+    async fn fetch_image(
+        &self,
+        qualified: &str,
+        credentials: Option<RegistryCredentials>,
+        progress: Option<mpsc::UnboundedSender<ImagePullProgress>>,
+    ) -> AgentResult<()> {
+        let runtime = self.clone();
+        let reference = qualified.to_string();
+        self.image_pulls
+            .run(
+                qualified,
+                credentials.clone(),
+                progress,
+                move |updates| async move {
+                    runtime
+                        .run_pull(&reference, credentials.as_ref(), &updates)
+                        .await
+                },
+            )
+            .await
+    }
+
+    /// Verify the image's cosign signature against `public_key`. The check runs against
+    /// the local digest, so the verified content is what containers will run; verified
+    /// digests are remembered for the life of the agent.
+    async fn verify_signature(
+        &self,
+        qualified: &str,
+        public_key: &Path,
+        credentials: Option<&RegistryCredentials>,
+    ) -> AgentResult<()> {
+        let digest = self.image_digest(qualified).await?;
+        let (repository, _) = image_policy::split_reference(qualified);
+        let pinned = format!("{}@{}", repository, digest);
+        if self.verified_images.lock().await.contains(&pinned) {
+            return Ok(());
+        }
+
+        // Registry secrets reach cosign through a private Docker config rather than its
+        // arguments, which every local user can read.
+        let docker_config = match credentials {
+            Some(credentials) if credentials.token.is_some() || credentials.password.is_some() => {
+                let dir =
+                    std::env::temp_dir().join(format!("catalyst-cosign-{}", uuid::Uuid::new_v4()));
+                write_docker_config(&dir, registry_host(qualified), credentials).await?;
+                Some(dir)
+            }
+            _ => None,
+        };
+        let mut command = Command::new("cosign");
+        command.arg("verify").arg("--key").arg(public_key);
+        if let Some(dir) = &docker_config {
+            command.env("DOCKER_CONFIG", dir);
+        }
+        let output = command.arg(&pinned).output().await;
+        if let Some(dir) = &docker_config {
+            let _ = tokio::fs::remove_dir_all(dir).await;
+        }
+        let output = output.map_err(|e| {
+            AgentError::ContainerError(format!("Failed to run cosign for {}: {}", pinned, e))
+        })?;
+        if !output.status.success() {
+            return Err(AgentError::PermissionDenied(format!(
+                "Signature verification failed for {}: {}",
+                pinned,
+                String::from_utf8_lossy(&output.stderr).trim()
+            )));
+        }
+        info!("Verified signature of {}", pinned);
+        self.verified_images.lock().await.insert(pinned);
+        Ok(())
+    }
+
+    /// Name the content `qualified` points at right now as `repository@digest`, checking
+    /// its signature when the image policy asks for one. Containers are built from that
+    /// name, so a concurrent pull moving the tag cannot swap in unverified content.
+    async fn pin_image(&self, qualified: &str) -> AgentResult<String> {
+        let (repository, digest) = image_policy::split_reference(qualified);
+        let pinned = if digest.is_some() {
+            qualified.to_string()
+        } else {
+            let mut client = ImagesClient::new(self.channel.clone());
+            let req = GetImageRequest {
+                name: qualified.to_string(),
+            };
+            let req = with_namespace!(req, &self.namespace);
+            let image = client.get(req).await.map_err(grpc_err)?.into_inner().image;
+            let (labels, target) = image
+                .and_then(|image| Some((image.labels, image.target?)))
+                .ok_or_else(|| {
+                    AgentError::ContainerError(format!("Image {} has no target", qualified))
+                })?;
+            let pinned = format!("{}@{}", repository, target.digest);
+            let req = CreateImageRequest {
+                image: Some(Image {
+                    name: pinned.clone(),
+                    labels,
+                    target: Some(target),
+                    ..Default::default()
+                }),
+                ..Default::default()
+            };
+            let req = with_namespace!(req, &self.namespace);
+            match client.create(req).await {
+                Ok(_) => {}
+                Err(e) if e.code() == tonic::Code::AlreadyExists => {}
+                Err(e) => return Err(grpc_err(e)),
+            }
+            pinned
+        };
+
+        if let Some(public_key) = self.images.policy.signature_key(qualified) {
+            let host = registry_host(qualified);
+            let credentials = self
+                .images
+                .registries
+                .iter()
+                .find(|credentials| credentials.matches(host));
+            self.verify_signature(&pinned, public_key, credentials)
+                .await?;
+        }
+        Ok(pinned)
+    }
+
+    /// Digest of the manifest (or index) an image name points at.
+    pub async fn image_digest(&self, qualified: &str) -> AgentResult<String> {
         let mut client = ImagesClient::new(self.channel.clone());
         let req = GetImageRequest {
-            name: qualified.clone(),
+            name: qualified.to_string(),
+        };
+        let req = with_namespace!(req, &self.namespace);
+        let image = client.get(req).await.map_err(grpc_err)?.into_inner().image;
+        image
+            .and_then(|image| image.target)
+            .map(|target| target.digest)
+            .ok_or_else(|| AgentError::ContainerError(format!("Image {} has no target", qualified)))
+    }
+
+    /// Images currently being pulled, which must not be collected.
+    pub async fn pulls_in_flight(&self) -> Vec<String> {
+        self.image_pulls.images().await
+    }
+
+    pub async fn image_names(&self) -> AgentResult<Vec<String>> {
+        let mut client = ImagesClient::new(self.channel.clone());
+        let req = with_namespace!(ListImagesRequest::default(), &self.namespace);
+        let images = client
+            .list(req)
+            .await
+            .map_err(grpc_err)?
+            .into_inner()
+            .images;
+        Ok(images.into_iter().map(|image| image.name).collect())
+    }
+
+    pub async fn list_images(&self) -> AgentResult<Vec<ImageRecord>> {
+        let mut client = ImagesClient::new(self.channel.clone());
+        let req = with_namespace!(ListImagesRequest::default(), &self.namespace);
+        let images = client
+            .list(req)
+            .await
+            .map_err(grpc_err)?
+            .into_inner()
+            .images;
+        let mut records = Vec::with_capacity(images.len());
+        for image in images {
+            let blobs = match &image.target {
+                Some(target) => {
+                    self.image_blobs(&target.digest, target.size.max(0) as u64)
+                        .await
+                }
+                None => Vec::new(),
+            };
+            records.push(ImageRecord {
+                name: image.name,
+                updated_at: image
+                    .updated_at
+                    .and_then(|ts| SystemTime::try_from(ts).ok()),
+                blobs,
+            });
+        }
+        Ok(records)
+    }
+
+    /// Blobs reachable from an image's target for the host platform. Unreadable content
+    /// (e.g. platforms never pulled) is skipped, so sizes are a lower bound.
+    async fn image_blobs(&self, digest: &str, size: u64) -> Vec<(String, u64)> {
+        let mut blobs = vec![(digest.to_string(), size)];
+        let Ok(bytes) = self.read_content_blob(digest).await else {
+            return blobs;
+        };
+        let Ok(mut manifest) = serde_json::from_slice::<serde_json::Value>(&bytes) else {
+            return blobs;
+        };
+        if let Some(manifests) = manifest.get("manifests").and_then(|v| v.as_array()) {
+            let architecture = host_platform().architecture;
+            let Some((digest, size)) = manifests
+                .iter()
+                .find(|m| m["platform"]["architecture"].as_str() == Some(architecture.as_str()))
+                .and_then(|m| Some((m["digest"].as_str()?.to_string(), m["size"].as_u64()?)))
+            else {
+                return blobs;
+            };
+            blobs.push((digest.clone(), size));
+            let Ok(bytes) = self.read_content_blob(&digest).await else {
+                return blobs;
+            };
+            let Ok(inner) = serde_json::from_slice(&bytes) else {
+                return blobs;
+            };
+            manifest = inner;
+        }
+        let config = manifest.get("config").into_iter();
+        let layers = manifest
+            .get("layers")
+            .and_then(|v| v.as_array())
+            .into_iter()
+            .flatten();
+        for descriptor in config.chain(layers) {
+            if let (Some(digest), Some(size)) =
+                (descriptor["digest"].as_str(), descriptor["size"].as_u64())
+            {
+                blobs.push((digest.to_string(), size));
+            }
+        }
+        blobs
+    }
+
+    /// Delete an image and wait for containerd's GC to release its content and snapshots.
+    pub async fn delete_image(&self, name: &str) -> AgentResult<()> {
+        let mut client = ImagesClient::new(self.channel.clone());
+        let req = DeleteImageRequest {
+            name: name.to_string(),
+            sync: true,
+            ..Default::default()
+        };
+        let req = with_namespace!(req, &self.namespace);
+        match client.delete(req).await {
+            Ok(_) => Ok(()),
+            Err(e) if e.code() == tonic::Code::NotFound => Ok(()),
+            Err(e) => Err(grpc_err(e)),
+        }
+    }
+
+    /// Container rootfs snapshots (`<id>-snap`) left behind by containers that no longer
+    /// exist, with their disk usage. Snapshots younger than `min_age` may belong to a
+    /// container being created and are left alone.
+    pub async fn orphaned_snapshots(&self, min_age: Duration) -> AgentResult<Vec<(String, u64)>> {
+        let mut containers = ContainersClient::new(self.channel.clone());
+        let req = with_namespace!(ListContainersRequest::default(), &self.namespace);
+        let live: HashSet<String> = containers
+            .list(req)
+            .await
+            .map_err(grpc_err)?
+            .into_inner()
+            .containers
+            .into_iter()
+            .map(|c| c.snapshot_key)
+            .collect();
+
+        let mut snapshots = SnapshotsClient::new(self.channel.clone());
+        let req = ListSnapshotsRequest {
+            snapshotter: "overlayfs".to_string(),
+            ..Default::default()
+        };
+        let req = with_namespace!(req, &self.namespace);
+        let mut stream = snapshots.list(req).await.map_err(grpc_err)?.into_inner();
+        let now = SystemTime::now();
+        let old_enough = |created_at: Option<prost_types::Timestamp>| {
+            created_at
+                .and_then(|ts| SystemTime::try_from(ts).ok())
+                .and_then(|created| now.duration_since(created).ok())
+                .is_some_and(|age| age >= min_age)
+        };
+        let mut orphans = Vec::new();
+        while let Some(page) = stream.message().await.map_err(grpc_err)? {
+            orphans.extend(page.info.into_iter().filter_map(|info| {
+                (info.kind == SnapshotKind::Active as i32
+                    && info.name.ends_with("-snap")
+                    && !live.contains(&info.name)
+                    && old_enough(info.created_at))
+                .then_some(info.name)
+            }));
+        }
+
+        let mut result = Vec::with_capacity(orphans.len());
+        for key in orphans {
+            let req = UsageRequest {
+                snapshotter: "overlayfs".to_string(),
+                key: key.clone(),
+            };
+            let req = with_namespace!(req, &self.namespace);
+            let size = match snapshots.usage(req).await {
+                Ok(resp) => resp.into_inner().size.max(0) as u64,
+                Err(_) => 0,
+            };
+            result.push((key, size));
+        }
+        Ok(result)
+    }
+
+    pub async fn remove_snapshot(&self, key: &str) -> AgentResult<()> {
+        let mut snapshots = SnapshotsClient::new(self.channel.clone());
+        let req = RemoveSnapshotRequest {
+            snapshotter: "overlayfs".to_string(),
+            key: key.to_string(),
+        };
+        let req = with_namespace!(req, &self.namespace);
+        match snapshots.remove(req).await {
+            Ok(_) => Ok(()),
+            Err(e) if e.code() == tonic::Code::NotFound => Ok(()),
+            Err(e) => Err(grpc_err(e)),
+        }
+    }
+
+    async fn image_exists(&self, qualified: &str) -> AgentResult<bool> {
+        let mut client = ImagesClient::new(self.channel.clone());
+        let req = GetImageRequest {
+            name: qualified.to_string(),
         };
         let req = with_namespace!(req, &self.namespace);
         match client.get(req).await {
-            Ok(_) => return Ok(()),
-            Err(e) if e.code() == tonic::Code::NotFound => {
-                info!("Image {} not found, pulling...", qualified)
+            Ok(_) => Ok(true),
+            Err(e) if e.code() == tonic::Code::NotFound => Ok(false),
+            Err(e) => Err(grpc_err(e)),
+        }
+    }
+
+    async fn run_pull(
+        &self,
+        qualified: &str,
+        credentials: Option<&RegistryCredentials>,
+        updates: &broadcast::Sender<PullUpdate>,
+    ) -> AgentResult<()> {
+        info!("Image {} not found, pulling...", qualified);
+        let timeout = Duration::from_secs(self.images.pull_timeout_secs);
+        let pull = async {
+            if !self.transfer_pull(qualified, credentials, updates).await? {
+                // containerd before 1.7 has no transfer service.
+                warn!(
+                    "Transfer service unavailable, pulling {} with ctr",
+                    qualified
+                );
+                self.ctr_pull(qualified, credentials).await?;
             }
+            Ok::<(), AgentError>(())
+        };
+        match tokio::time::timeout(timeout, pull).await {
+            Ok(result) => result?,
+            Err(_) => {
+                return Err(AgentError::ContainerError(format!(
+                    "Pull of {} timed out after {}s",
+                    qualified,
+                    timeout.as_secs()
+                )))
+            }
+        }
+        info!("Image {} pulled", qualified);
+        Ok(())
+    }
+
+    /// Pull and unpack through containerd's transfer service, relaying progress from a
+    /// streaming-service channel. Returns false when containerd lacks these services.
+    async fn transfer_pull(
+        &self,
+        qualified: &str,
+        credentials: Option<&RegistryCredentials>,
+        updates: &broadcast::Sender<PullUpdate>,
+    ) -> AgentResult<bool> {
+        let stream_id = format!("catalyst-pull-{}", uuid::Uuid::new_v4());
+        let (_stream_tx, mut progress) = match self.open_stream(&stream_id).await {
+            Ok(stream) => stream,
+            Err(e) if e.code() == tonic::Code::Unimplemented => return Ok(false),
             Err(e) => return Err(grpc_err(e)),
+        };
+
+        // containerd asks for credentials over a second stream when the registry challenges.
+        let mut resolver = RegistryResolver::default();
+        let mut auth = None;
+        if credentials.is_some() {
+            let auth_id = format!("catalyst-auth-{}", uuid::Uuid::new_v4());
+            auth = Some(self.open_stream(&auth_id).await.map_err(grpc_err)?);
+            resolver.auth_stream = auth_id;
+        }
+
+        let platform = host_platform();
+        let source = OciRegistry {
+            reference: qualified.to_string(),
+            resolver: Some(resolver),
+        };
+        let destination = ImageStore {
+            name: qualified.to_string(),
+            platforms: vec![platform.clone()],
+            unpacks: vec![UnpackConfiguration {
+                platform: Some(platform),
+                snapshotter: "overlayfs".to_string(),
+            }],
+            ..Default::default()
+        };
+        let req = TransferRequest {
+            source: Some(to_any(&source)),
+            destination: Some(to_any(&destination)),
+            options: Some(TransferOptions {
+                progress_stream: stream_id,
+            }),
+        };
+        let mut client = TransferClient::new(self.channel.clone());
+        let req = with_namespace!(req, &self.namespace);
+        let transfer = client.transfer(req);
+        tokio::pin!(transfer);
+
+        let mut progress_open = true;
+        loop {
+            tokio::select! {
+                result = &mut transfer => {
+                    return match result {
+                        Ok(_) => Ok(true),
+                        Err(e) if e.code() == tonic::Code::Unimplemented => Ok(false),
+                        Err(e) => Err(grpc_err(e)),
+                    };
+                }
+                message = progress.message(), if progress_open => match message {
+                    Ok(Some(any)) => {
+                        if let Some(update) = decode_progress(&any) {
+                            let _ = updates.send(PullUpdate::Progress(update));
+                        }
+                    }
+                    // The transfer result still decides the outcome.
+                    Ok(None) | Err(_) => progress_open = false,
+                },
+                request = async { auth.as_mut()?.1.message().await.ok().flatten() },
+                    if auth.is_some() => match (request, credentials) {
+                    (Some(request), Some(credentials)) => {
+                        let response = auth_response(credentials, &request);
+                        if let Some((responses, _)) = &auth {
+                            let _ = responses.send(to_any(&response)).await;
+                        }
+                    }
+                    _ => auth = None,
+                },
+                _ = tokio::time::sleep(IMAGE_PULL_STALL_TIMEOUT) => {
+                    return Err(AgentError::ContainerError(format!(
+                        "Pull of {} stalled for {}s",
+                        qualified,
+                        IMAGE_PULL_STALL_TIMEOUT.as_secs()
+                    )));
+                }
+            }
+        }
+    }
+
+    /// Open a streaming-service channel registered under `id`. Messages sent on the
+    /// returned sender reach containerd; the stream yields what containerd sends back.
+    async fn open_stream(
+        &self,
+        id: &str,
+    ) -> Result<(mpsc::Sender<Any>, tonic::codec::Streaming<Any>), tonic::Status> {
+        let (tx, rx) = mpsc::channel(16);
+        tx.send(to_any(&StreamInit { id: id.to_string() }))
+            .await
+            .map_err(|e| tonic::Status::internal(e.to_string()))?;
+        let mut client = StreamingClient::new(self.channel.clone());
+        let req = with_namespace!(
+            tokio_stream::wrappers::ReceiverStream::new(rx),
+            &self.namespace
+        );
+        let mut stream = client.stream(req).await?.into_inner();
+        // containerd acknowledges the init message before the stream is usable.
+        stream.message().await?;
+        Ok((tx, stream))
+    }
+
+    /// Pull through the `ctr` CLI. It only takes a password on the command line or from a
+    /// terminal, so pulls that need registry credentials are refused here rather than
+    /// exposing the secret in the process list.
+    async fn ctr_pull(
+        &self,
+        qualified: &str,
+        credentials: Option<&RegistryCredentials>,
+    ) -> AgentResult<()> {
+        if let Some(credentials) = credentials {
+            if credentials.password.is_some() || credentials.token.is_some() {
+                return Err(AgentError::ContainerError(format!(
+                    "Cannot pull {} with registry credentials: containerd has no transfer \
+                     service and ctr would expose them in its arguments. Upgrade containerd \
+                     to 1.7 or later, or pre-pull the image",
+                    qualified
+                )));
+            }
         }
         let output = Command::new("ctr")
             .arg("-n")
             .arg(&self.namespace)
             .arg("images")
             .arg("pull")
-            .arg(&qualified)
+            .arg(qualified)
+            // Dropped when the pull times out.
+            .kill_on_drop(true)
             .output()
             .await
             .map_err(|e| AgentError::ContainerError(format!("pull: {}", e)))?;
@@ -1381,21 +2813,23 @@ impl ContainerdRuntime {
                 String::from_utf8_lossy(&output.stderr)
             )));
         }
-        info!("Image {} pulled", qualified);
         Ok(())
     }
 
     /// Normalize a Docker-style short image reference to a fully-qualified containerd reference.
     /// e.g. "eclipse-temurin:21-jre" -> "docker.io/library/eclipse-temurin:21-jre"
+    ///      "user/image@sha256:..."  -> "docker.io/user/image@sha256:..."
     ///      "ghcr.io/org/image:tag"  -> "ghcr.io/org/image:tag" (unchanged)
-    fn qualify_image_ref(image: &str) -> String {
-        let name = image.split(':').next().unwrap_or(image);
-        if name.contains('/') {
-            // Already has a registry or org prefix (e.g. ghcr.io/org/img, user/img)
-            image.to_string()
-        } else {
+    pub fn qualify_image_ref(image: &str) -> String {
+        match image.split_once('/') {
+            // Already has a registry (ghcr.io/org/img, localhost:5000/img)
+            Some((host, _)) if host.contains(['.', ':']) || host == "localhost" => {
+                image.to_string()
+            }
+            // Docker Hub user or org image like "user/img:1"
+            Some(_) => format!("docker.io/{}", image),
             // Bare image name like "alpine:3.19" -> "docker.io/library/alpine:3.19"
-            format!("docker.io/library/{}", image)
+            None => format!("docker.io/library/{}", image),
         }
     }
 
@@ -1624,7 +3058,7 @@ impl ContainerdRuntime {
             );
         }
         env_map.insert("TERM".to_string(), "xterm".to_string());
-        // Runtime container runs as 1000:1000; set HOME to the data dir
+        // The runtime user has no home in the image; use the data dir
         env_map.insert("HOME".to_string(), "/data".to_string());
         let env_list: Vec<String> = env_map
             .into_iter()
@@ -1642,12 +3076,12 @@ impl ContainerdRuntime {
             vec!["/bin/sh".to_string()]
         };
 
-        let mem_limit = (config.memory_mb as i64) * 1024 * 1024;
-        let cpu_quota = (config.cpu_cores as i64) * 100_000;
         let cgroup_path = format!("/{}/{}", self.namespace, config.container_id);
-        // Runtime containers run as non-root (1000:1000) and need minimal capabilities.
-        let caps = ["CAP_NET_BIND_SERVICE"];
+        // Runtime containers run as non-root with the profile's minimal capabilities.
+        let security = &config.security;
+        let caps = security.capabilities()?;
         let mut mounts = base_mounts(config.data_dir);
+        mounts.extend(security.tmpfs_mounts()?);
         mounts.push(serde_json::json!({"destination":io_dir.to_string_lossy().to_string(),"type":"bind","source":io_dir.to_string_lossy().to_string(),"options":["rbind","rw"]}));
 
         // Generate /etc/hosts so the container hostname resolves (Java getLocalHost() etc.)
@@ -1695,21 +3129,35 @@ impl ContainerdRuntime {
         if !use_host_network {
             ns.push(serde_json::json!({"type":"network"}));
         }
+        if config.user_namespace.is_some() {
+            ns.push(serde_json::json!({"type":"user"}));
+        }
+
+        let mut resources = oci_resources(
+            config.memory_mb,
+            config.cpu_millicores,
+            &config.limits,
+            Path::new(config.data_dir),
+        );
+        resources["devices"] = security.device_rules()?;
 
-        Ok(serde_json::json!({
+        let mut spec = serde_json::json!({
             "ociVersion":"1.1.0",
-            "process":{"terminal":false,"user":{"uid":1000,"gid":1000},"args":args,"env":env_list,"cwd":"/data",
+            "process":{"terminal":config.tty,"consoleSize":{"height":DEFAULT_CONSOLE_ROWS,"width":DEFAULT_CONSOLE_COLS},"user":{"uid":security.uid(),"gid":security.gid()},"args":args,"env":env_list,"cwd":"/data",
                 "capabilities":{"bounding":caps,"effective":caps,"permitted":caps,"ambient":caps},
                 "noNewPrivileges":true,"rlimits":[{"type":"RLIMIT_NOFILE","hard":65536u64,"soft":65536u64}]},
-            "root":{"path":"rootfs","readonly":false},"hostname":config.container_id,"mounts":mounts,
-            "linux":{"cgroupsPath":cgroup_path,"resources":{"memory":{"limit":mem_limit},"cpu":{"quota":cpu_quota,"period":100000u64},
-                "devices":[{"allow":false,"access":"rwm"},{"allow":true,"type":"c","major":1,"minor":3,"access":"rwm"},
-                    {"allow":true,"type":"c","major":1,"minor":5,"access":"rwm"},{"allow":true,"type":"c","major":1,"minor":8,"access":"rwm"},
-                    {"allow":true,"type":"c","major":1,"minor":9,"access":"rwm"},{"allow":true,"type":"c","major":5,"minor":0,"access":"rwm"},
-                    {"allow":true,"type":"c","major":5,"minor":1,"access":"rwm"}]},
-                "namespaces":ns,"maskedPaths":masked_paths(),"readonlyPaths":readonly_paths(),
-                "seccomp": default_seccomp_profile()}
-        }))
+            "root":{"path":"rootfs","readonly":security.read_only_rootfs},"hostname":config.container_id,"mounts":mounts,
+            "linux":{"cgroupsPath":cgroup_path,"resources":resources,
+                "namespaces":ns,"maskedPaths":security.masked_paths(),"readonlyPaths":readonly_paths()}
+        });
+        if let Some(seccomp) = security.seccomp()? {
+            spec["linux"]["seccomp"] = seccomp;
+        }
+        if let Some(mapping) = config.user_namespace {
+            spec["linux"]["uidMappings"] = mapping.oci_mappings();
+            spec["linux"]["gidMappings"] = mapping.oci_mappings();
+        }
+        Ok(spec)
     }
 
     async fn setup_cni_network(
@@ -2298,6 +3746,224 @@ impl ContainerdRuntime {
     }
 }
 
+#[async_trait::async_trait]
+impl ContainerRuntime for ContainerdRuntime {
+    async fn create_container(&self, config: ContainerConfig<'_>) -> AgentResult<String> {
+        ContainerdRuntime::create_container(self, config).await
+    }
+
+    async fn start_container(&self, container_id: &str) -> AgentResult<()> {
+        ContainerdRuntime::start_container(self, container_id).await
+    }
+
+    async fn stop_container(&self, container_id: &str, timeout_secs: u64) -> AgentResult<()> {
+        ContainerdRuntime::stop_container(self, container_id, timeout_secs).await
+    }
+
+    async fn stop_container_with_signal(
+        &self,
+        container_id: &str,
+        signal: &str,
+        timeout_secs: u64,
+    ) -> AgentResult<()> {
+        ContainerdRuntime::stop_container_with_signal(self, container_id, signal, timeout_secs)
+            .await
+    }
+
+    async fn kill_container(&self, container_id: &str, signal: &str) -> AgentResult<()> {
+        ContainerdRuntime::kill_container(self, container_id, signal).await
+    }
+
+    async fn force_kill_container(&self, container_id: &str) -> AgentResult<()> {
+        ContainerdRuntime::force_kill_container(self, container_id).await
+    }
+
+    async fn remove_container(&self, container_id: &str) -> AgentResult<()> {
+        ContainerdRuntime::remove_container(self, container_id).await
+    }
+
+    async fn pause_container(&self, container_id: &str) -> AgentResult<()> {
+        ContainerdRuntime::pause_container(self, container_id).await
+    }
+
+    async fn resume_container(&self, container_id: &str) -> AgentResult<()> {
+        ContainerdRuntime::resume_container(self, container_id).await
+    }
+
+    async fn update_resources(
+        &self,
+        container_id: &str,
+        memory_mb: u64,
+        cpu_millicores: u64,
+        limits: &ResourceLimits,
+        data_dir: &Path,
+    ) -> AgentResult<()> {
+        ContainerdRuntime::update_resources(
+            self,
+            container_id,
+            memory_mb,
+            cpu_millicores,
+            limits,
+            data_dir,
+        )
+        .await
+    }
+
+    async fn spawn_installer_container(
+        &self,
+        image: &str,
+        script: &str,
+        env: &HashMap<String, String>,
+        data_dir: &str,
+        uid: u32,
+        gid: u32,
+    ) -> AgentResult<Box<dyn InstallerProcess>> {
+        let handle = ContainerdRuntime::spawn_installer_container(
+            self, image, script, env, data_dir, uid, gid,
+        )
+        .await?;
+        Ok(Box::new(handle))
+    }
+
+    async fn container_exists(&self, container_id: &str) -> bool {
+        ContainerdRuntime::container_exists(self, container_id).await
+    }
+
+    async fn is_container_running(&self, container_id: &str) -> AgentResult<bool> {
+        ContainerdRuntime::is_container_running(self, container_id).await
+    }
+
+    async fn container_status(&self, container_id: &str) -> AgentResult<ContainerStatus> {
+        ContainerdRuntime::container_status(self, container_id).await
+    }
+
+    async fn get_container_exit_code(&self, container_id: &str) -> AgentResult<Option<i32>> {
+        ContainerdRuntime::get_container_exit_code(self, container_id).await
+    }
+
+    async fn list_containers(&self) -> AgentResult<Vec<ContainerInfo>> {
+        ContainerdRuntime::list_containers(self).await
+    }
+
+    async fn get_container_ip(&self, container_id: &str) -> AgentResult<String> {
+        ContainerdRuntime::get_container_ip(self, container_id).await
+    }
+
+    async fn send_input(&self, container_id: &str, input: &str) -> AgentResult<()> {
+        ContainerdRuntime::send_input(self, container_id, input).await
+    }
+
+    async fn send_input_bytes(&self, container_id: &str, input: &[u8]) -> AgentResult<()> {
+        ContainerdRuntime::send_input_bytes(self, container_id, input).await
+    }
+
+    async fn resize_console(&self, container_id: &str, cols: u32, rows: u32) -> AgentResult<()> {
+        ContainerdRuntime::resize_console(self, container_id, cols, rows).await
+    }
+
+    fn is_tty(&self, container_id: &str) -> bool {
+        ContainerdRuntime::is_tty(self, container_id)
+    }
+
+    async fn restore_console_writers(&self) -> AgentResult<()> {
+        ContainerdRuntime::restore_console_writers(self).await
+    }
+
+    async fn spawn_log_stream(&self, container_id: &str) -> AgentResult<LogStream> {
+        ContainerdRuntime::spawn_log_stream(self, container_id).await
+    }
+
+    async fn get_logs(&self, container_id: &str, lines: Option<u32>) -> AgentResult<String> {
+        ContainerdRuntime::get_logs(self, container_id, lines).await
+    }
+
+    async fn start_interactive_exec(
+        &self,
+        container_id: &str,
+        args: &[String],
+        cols: u32,
+        rows: u32,
+    ) -> AgentResult<InteractiveExec> {
+        ContainerdRuntime::start_interactive_exec(self, container_id, args, cols, rows).await
+    }
+
+    async fn resize_exec(
+        &self,
+        container_id: &str,
+        exec_id: &str,
+        cols: u32,
+        rows: u32,
+    ) -> AgentResult<()> {
+        ContainerdRuntime::resize_exec(self, container_id, exec_id, cols, rows).await
+    }
+
+    async fn kill_exec(&self, container_id: &str, exec_id: &str, signal: &str) -> AgentResult<()> {
+        ContainerdRuntime::kill_exec(self, container_id, exec_id, signal).await
+    }
+
+    async fn wait_exec(&self, container_id: &str, exec_id: &str) -> AgentResult<i32> {
+        ContainerdRuntime::wait_exec(self, container_id, exec_id).await
+    }
+
+    async fn cleanup_exec(&self, container_id: &str, exec_id: &str) {
+        ContainerdRuntime::cleanup_exec(self, container_id, exec_id).await
+    }
+
+    async fn get_stats(&self, container_id: &str) -> AgentResult<ContainerStats> {
+        ContainerdRuntime::get_stats(self, container_id).await
+    }
+
+    async fn cpu_usage_usec(&self, container_id: &str) -> Option<u64> {
+        ContainerdRuntime::cpu_usage_usec(self, container_id).await
+    }
+
+    async fn memory_events(&self, container_id: &str) -> Option<MemoryEvents> {
+        ContainerdRuntime::memory_events(self, container_id).await
+    }
+
+    async fn subscribe_to_container_events(&self, container_id: &str) -> AgentResult<EventStream> {
+        ContainerdRuntime::subscribe_to_container_events(self, container_id).await
+    }
+
+    async fn subscribe_to_all_events(&self) -> AgentResult<EventStream> {
+        ContainerdRuntime::subscribe_to_all_events(self).await
+    }
+
+    async fn pull_image(
+        &self,
+        image: &str,
+        credentials: &[RegistryCredentials],
+        pull_policy: PullPolicy,
+        progress: Option<mpsc::UnboundedSender<ImagePullProgress>>,
+    ) -> AgentResult<()> {
+        ContainerdRuntime::pull_image(self, image, credentials, pull_policy, progress).await
+    }
+
+    async fn image_names(&self) -> AgentResult<Vec<String>> {
+        ContainerdRuntime::image_names(self).await
+    }
+
+    async fn list_images(&self) -> AgentResult<Vec<ImageRecord>> {
+        ContainerdRuntime::list_images(self).await
+    }
+
+    async fn delete_image(&self, name: &str) -> AgentResult<()> {
+        ContainerdRuntime::delete_image(self, name).await
+    }
+
+    async fn pulls_in_flight(&self) -> Vec<String> {
+        ContainerdRuntime::pulls_in_flight(self).await
+    }
+
+    async fn orphaned_snapshots(&self, min_age: Duration) -> AgentResult<Vec<(String, u64)>> {
+        ContainerdRuntime::orphaned_snapshots(self, min_age).await
+    }
+
+    async fn remove_snapshot(&self, key: &str) -> AgentResult<()> {
+        ContainerdRuntime::remove_snapshot(self, key).await
+    }
+}
+
 // ---------------------------------------------------------------------------
 // Helpers
 // ---------------------------------------------------------------------------
@@ -2457,6 +4123,12 @@ fn open_fifo_rdwr(path: &Path) -> AgentResult<File> {
     Ok(file)
 }
 
+fn remove_exec_fifos(container_id: &str, exec_id: &str) {
+    let io_dir = PathBuf::from(CONSOLE_BASE_DIR).join(container_id);
+    let _ = fs::remove_file(io_dir.join(format!("{}-in", exec_id)));
+    let _ = fs::remove_file(io_dir.join(format!("{}-out", exec_id)));
+}
+
 fn set_dir_perms(path: &Path, mode: u32) {
     if let Ok(md) = fs::metadata(path) {
         let mut p = md.permissions();
@@ -2465,6 +4137,102 @@ fn set_dir_perms(path: &Path, mode: u32) {
     }
 }
 
+/// OCI `linux.resources` (without devices) for the given allocation and limits.
+/// `mb` in bytes, if that fits the signed limits OCI resources use.
+fn mb_to_bytes(mb: u64) -> Option<u64> {
+    mb.checked_mul(1024 * 1024)
+        .filter(|bytes| i64::try_from(*bytes).is_ok())
+}
+
+fn oci_resources(
+    memory_mb: u64,
+    cpu_millicores: u64,
+    limits: &ResourceLimits,
+    data_dir: &Path,
+) -> serde_json::Value {
+    let mem_limit = (memory_mb as i64) * 1024 * 1024;
+    // 1000 millicores get the full 100ms period.
+    let cpu_quota = (cpu_millicores as i64) * 100;
+    let mut resources = serde_json::json!({
+        "memory": {"limit": mem_limit},
+        "cpu": {"quota": cpu_quota, "period": 100000u64},
+    });
+    if let Some(swap_mb) = limits.swap_mb {
+        // OCI expresses swap as the combined memory+swap limit.
+        resources["memory"]["swap"] = serde_json::json!(mem_limit + (swap_mb as i64) * 1024 * 1024);
+    }
+    if let Some(reservation_mb) = limits.memory_reservation_mb {
+        resources["memory"]["reservation"] =
+            serde_json::json!((reservation_mb.min(memory_mb) as i64) * 1024 * 1024);
+    }
+    if let Some(cpus) = &limits.cpuset_cpus {
+        resources["cpu"]["cpus"] = serde_json::json!(cpus);
+    }
+    if let Some(weight) = limits.cpu_weight {
+        // The OCI cpu section only knows v1 shares; set the v2 file directly.
+        resources["unified"] = serde_json::json!({"cpu.weight": weight.to_string()});
+    }
+    if let Some(pids_max) = limits.pids_max {
+        resources["pids"] = serde_json::json!({"limit": pids_max});
+    }
+
+    let throttles = [
+        ("throttleReadBpsDevice", limits.io_read_bps),
+        ("throttleWriteBpsDevice", limits.io_write_bps),
+        ("throttleReadIOPSDevice", limits.io_read_iops),
+        ("throttleWriteIOPSDevice", limits.io_write_iops),
+    ];
+    if throttles.iter().any(|(_, rate)| rate.is_some()) {
+        match volume_block_device(data_dir) {
+            Some((major, minor)) => {
+                let mut block_io = serde_json::Map::new();
+                for (key, rate) in throttles {
+                    if let Some(rate) = rate {
+                        block_io.insert(
+                            key.to_string(),
+                            serde_json::json!([{"major": major, "minor": minor, "rate": rate}]),
+                        );
+                    }
+                }
+                resources["blockIO"] = serde_json::Value::Object(block_io);
+            }
+            None => warn!(
+                "I/O limits requested but {} is not a mounted volume; skipping io.max",
+                data_dir.display()
+            ),
+        }
+    }
+    resources
+}
+
+/// Device numbers of the loop device mounted at `data_dir`. `None` when the directory is
+/// not a mount point, since io.max only accepts whole block devices.
+fn volume_block_device(data_dir: &Path) -> Option<(u64, u64)> {
+    use std::os::unix::fs::MetadataExt;
+    let dev = fs::metadata(data_dir).ok()?.dev();
+    let parent_dev = fs::metadata(data_dir.parent()?).ok()?.dev();
+    if dev == parent_dev {
+        return None;
+    }
+    Some((
+        nix::sys::stat::major(dev) as u64,
+        nix::sys::stat::minor(dev) as u64,
+    ))
+}
+
+/// Whether `cpus` is a cpuset list such as `0-3,6`.
+fn is_valid_cpuset(cpus: &str) -> bool {
+    cpus.split(',').all(|part| {
+        let mut bounds = part.splitn(2, '-');
+        let start = bounds.next().and_then(|v| v.parse::<u32>().ok());
+        match (start, bounds.next()) {
+            (Some(_), None) => true,
+            (Some(start), Some(end)) => end.parse::<u32>().is_ok_and(|end| end >= start),
+            _ => false,
+        }
+    })
+}
+
 fn shell_escape_value(value: &str) -> String {
     let escaped = value.replace('\'', "'\"'\"'");
     format!("'{}'", escaped)
@@ -2479,6 +4247,163 @@ fn parse_signal(signal: &str) -> u32 {
     }
 }
 
+/// Extract container_id from a containerd event's protobuf Any payload
+fn extract_container_id_from_event(event: &prost_types::Any) -> Option<String> {
+    // containerd task events encode container_id as a field in the protobuf message
+    // The value bytes contain the serialized protobuf; container_id is typically field 1 (tag 0x0a)
+    let data = &event.value;
+    let mut i = 0;
+    while i < data.len() {
+        let tag_byte = data[i];
+        let field_number = tag_byte >> 3;
+        let wire_type = tag_byte & 0x07;
+        i += 1;
+        if wire_type == 2 {
+            // Length-delimited field
+            if i >= data.len() {
+                break;
+            }
+            let len = data[i] as usize;
+            i += 1;
+            if field_number == 1 && i + len <= data.len() {
+                if let Ok(s) = std::str::from_utf8(&data[i..i + len]) {
+                    return Some(s.to_string());
+                }
+            }
+            i += len;
+        } else if wire_type == 0 {
+            // Varint
+            while i < data.len() && data[i] & 0x80 != 0 {
+                i += 1;
+            }
+            i += 1;
+        } else {
+            break;
+        }
+    }
+    None
+}
+
+/// Registry host of a qualified reference; references without one come from Docker Hub.
+fn registry_host(qualified: &str) -> &str {
+    match qualified.split_once('/') {
+        Some((host, _)) if host.contains(['.', ':']) || host == "localhost" => host,
+        _ => "docker.io",
+    }
+}
+
+/// `config.json` contents giving Docker-compatible tools the credentials for `host`.
+fn docker_config(host: &str, credentials: &RegistryCredentials) -> serde_json::Value {
+    use base64::Engine;
+    // Docker Hub credentials are looked up under the legacy index URL.
+    let key = if host == "docker.io" {
+        "https://index.docker.io/v1/"
+    } else {
+        host
+    };
+    let auth = match credentials {
+        RegistryCredentials {
+            token: Some(token), ..
+        } => serde_json::json!({ "registrytoken": token }),
+        RegistryCredentials {
+            username, password, ..
+        } => serde_json::json!({
+            "auth": base64::engine::general_purpose::STANDARD.encode(format!(
+                "{}:{}",
+                username.as_deref().unwrap_or_default(),
+                password.as_deref().unwrap_or_default()
+            )),
+        }),
+    };
+    serde_json::json!({ "auths": { key: auth } })
+}
+
+/// Write `docker_config` into a new directory only the agent can read.
+async fn write_docker_config(
+    dir: &Path,
+    host: &str,
+    credentials: &RegistryCredentials,
+) -> AgentResult<()> {
+    use tokio::io::AsyncWriteExt;
+    tokio::fs::DirBuilder::new().mode(0o700).create(dir).await?;
+    let mut file = tokio::fs::OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .mode(0o600)
+        .open(dir.join("config.json"))
+        .await?;
+    file.write_all(docker_config(host, credentials).to_string().as_bytes())
+        .await?;
+    Ok(())
+}
+
+/// Answer one of containerd's credential callbacks. Hosts the credentials are not for,
+/// and unreadable requests, get an anonymous answer.
+fn auth_response(credentials: &RegistryCredentials, request: &Any) -> AuthResponse {
+    use prost::Message;
+    let mut response = AuthResponse::default();
+    let Ok(request) = AuthRequest::decode(request.value.as_slice()) else {
+        return response;
+    };
+    if !credentials.matches(&request.host) {
+        debug!("No registry credentials for {}", request.host);
+        return response;
+    }
+    if let Some(token) = &credentials.token {
+        response.set_auth_type(AuthType::Header);
+        response.secret = format!("Bearer {}", token);
+    } else if let (Some(username), Some(password)) = (&credentials.username, &credentials.password)
+    {
+        response.set_auth_type(AuthType::Credentials);
+        response.username = username.clone();
+        response.secret = password.clone();
+    }
+    response
+}
+
+/// Wrap a containerd API message the way Go's `typeurl` does, so containerd resolves it.
+fn to_any<M: prost::Message + prost::Name>(message: &M) -> Any {
+    Any {
+        type_url: format!("type.googleapis.com/{}", M::full_name()),
+        value: message.encode_to_vec(),
+    }
+}
+
+fn decode_progress(any: &Any) -> Option<ImagePullProgress> {
+    use prost::Message;
+    if !any.type_url.ends_with("containerd.types.transfer.Progress") {
+        return None;
+    }
+    let progress = TransferProgress::decode(any.value.as_slice()).ok()?;
+    Some(ImagePullProgress {
+        name: progress.name,
+        event: progress.event,
+        progress: progress.progress.max(0) as u64,
+        total: progress.total.max(0) as u64,
+    })
+}
+
+/// The platform to pull and unpack for, in OCI terms.
+fn host_platform() -> Platform {
+    let architecture = match std::env::consts::ARCH {
+        "x86_64" => "amd64",
+        "aarch64" => "arm64",
+        "arm" => "arm",
+        "powerpc64" => "ppc64le",
+        other => other,
+    };
+    Platform {
+        os: "linux".to_string(),
+        architecture: architecture.to_string(),
+        variant: if architecture == "arm64" {
+            "v8".to_string()
+        } else {
+            String::new()
+        },
+        ..Default::default()
+    }
+}
+
 fn grpc_err(e: tonic::Status) -> AgentError {
     AgentError::ContainerError(format!(
         "containerd gRPC error ({}): {}",
@@ -2506,24 +4431,6 @@ fn base_mounts(data_dir: &str) -> Vec<serde_json::Value> {
     ]
 }
 
-fn masked_paths() -> Vec<&'static str> {
-    vec![
-        // Original masked paths
-        "/proc/kcore",
-        "/proc/latency_stats",
-        "/proc/timer_list",
-        "/proc/timer_stats",
-        "/proc/sched_debug",
-        "/sys/firmware",
-        // Additional security-sensitive paths
-        "/proc/kallsyms", // Kernel symbols - useful for exploit development
-        "/proc/self/mem", // Memory manipulation vector
-        "/sys/kernel",    // Kernel parameters and addresses
-        "/sys/class",     // Hardware enumeration for fingerprinting
-        "/proc/slabinfo", // Kernel slab allocator info
-        "/proc/modules",  // Loaded kernel modules
-    ]
-}
 fn readonly_paths() -> Vec<&'static str> {
     vec![
         "/proc/asound",
@@ -2535,100 +4442,287 @@ fn readonly_paths() -> Vec<&'static str> {
     ]
 }
 
-fn seccomp_arches() -> Vec<&'static str> {
-    match std::env::consts::ARCH {
-        "x86_64" => vec!["SCMP_ARCH_X86_64", "SCMP_ARCH_X86", "SCMP_ARCH_X32"],
-        "aarch64" => vec!["SCMP_ARCH_AARCH64", "SCMP_ARCH_ARM"],
-        "arm" => vec!["SCMP_ARCH_ARM"],
-        _ => Vec::new(),
-    }
+async fn read_cgroup_file(path: &Path, file: &str) -> Option<String> {
+    tokio::fs::read_to_string(path.join(file)).await.ok()
 }
 
-fn default_seccomp_profile() -> serde_json::Value {
-    // Deny-list a small set of high-risk syscalls while keeping broad compatibility.
-    // This is intentionally conservative; consumers can harden further via host policy.
-    serde_json::json!({
-        "defaultAction": "SCMP_ACT_ALLOW",
-        "architectures": seccomp_arches(),
-        "syscalls": [
-            {
-                "names": [
-                    "acct",
-                    "add_key",
-                    "bpf",
-                    "delete_module",
-                    "finit_module",
-                    "init_module",
-                    "iopl",
-                    "ioperm",
-                    "kexec_file_load",
-                    "kexec_load",
-                    "keyctl",
-                    "mount",
-                    "open_by_handle_at",
-                    "perf_event_open",
-                    "pivot_root",
-                    "process_vm_readv",
-                    "process_vm_writev",
-                    "ptrace",
-                    "quotactl",
-                    "reboot",
-                    "request_key",
-                    "setns",
-                    "swapoff",
-                    "swapon",
-                    "syslog",
-                    "umount2",
-                    "unshare"
-                ],
-                "action": "SCMP_ACT_ERRNO",
-                "errnoRet": 1
-            }
-        ]
-    })
+async fn read_cpu_usage_usec(path: &Path) -> Option<u64> {
+    let content = read_cgroup_file(path, "cpu.stat").await?;
+    cgroup::parse_flat_keyed(&content)
+        .get("usage_usec")
+        .copied()
 }
 
-fn find_container_cgroup(container_id: &str) -> Option<String> {
-    find_cgroup_recursive("/sys/fs/cgroup", container_id)
+async fn read_pressure(path: &Path, file: &str) -> Option<cgroup::Pressure> {
+    cgroup::parse_pressure(&read_cgroup_file(path, file).await?)
 }
-fn find_cgroup_recursive(dir: &str, cid: &str) -> Option<String> {
-    for entry in fs::read_dir(dir).ok()?.flatten() {
-        let p = entry.path();
-        let n = entry.file_name().to_string_lossy().to_string();
-        if n.contains(cid) && p.is_dir() {
-            return Some(p.to_string_lossy().to_string());
-        }
-        if p.is_dir() && !n.starts_with('.') {
-            if let Some(f) = find_cgroup_recursive(&p.to_string_lossy(), cid) {
-                return Some(f);
-            }
-        }
-    }
-    None
+
+/// Wall-clock start time of a process, from its start ticks after boot. `/proc` reports
+/// ticks in USER_HZ, which is 100 on every architecture Linux supports.
+fn process_start_time(pid: u32, boot_time: u64) -> Option<SystemTime> {
+    const USER_HZ: u64 = 100;
+    let stat = fs::read_to_string(format!("/proc/{}/stat", pid)).ok()?;
+    let ticks = cgroup::parse_proc_start_ticks(&stat)?;
+    let millis = boot_time * 1000 + ticks * 1000 / USER_HZ;
+    Some(SystemTime::UNIX_EPOCH + Duration::from_millis(millis))
 }
 
-async fn read_cgroup_cpu_percent(path: &str) -> Option<f64> {
-    let content = tokio::fs::read_to_string(format!("{}/cpu.stat", path))
+/// Network counters of the task's network namespace. Host-networked containers share the
+/// host namespace, whose totals would be misleading, so they report nothing.
+async fn read_netns_counters(pid: u32) -> Option<(u64, u64)> {
+    let task_ns = tokio::fs::read_link(format!("/proc/{}/ns/net", pid))
         .await
         .ok()?;
-    for line in content.lines() {
-        if line.starts_with("usage_usec") {
-            return line
-                .split_whitespace()
-                .nth(1)?
-                .parse::<u64>()
-                .ok()
-                .map(|u| u as f64 / 1_000_000.0);
-        }
+    let host_ns = tokio::fs::read_link("/proc/1/ns/net").await.ok()?;
+    if task_ns == host_ns {
+        return None;
     }
-    Some(0.0)
+    let content = tokio::fs::read_to_string(format!("/proc/{}/net/dev", pid))
+        .await
+        .ok()?;
+    Some(cgroup::parse_net_dev(&content))
 }
 
-async fn read_cgroup_memory(path: &str) -> Option<u64> {
-    tokio::fs::read_to_string(format!("{}/memory.current", path))
-        .await
-        .ok()?
-        .trim()
-        .parse()
-        .ok()
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn validates_cpusets() {
+        for cpus in ["0", "0-3", "0-3,6", "1,3,5-7"] {
+            assert!(is_valid_cpuset(cpus), "{}", cpus);
+        }
+        for cpus in ["", "a", "3-1", "0-", "-2", "0,,1", "0-2-4", "1.5"] {
+            assert!(!is_valid_cpuset(cpus), "{}", cpus);
+        }
+        assert!(ResourceLimits::from_message(&serde_json::json!({ "cpusetCpus": "4-2" })).is_err());
+    }
+
+    #[test]
+    fn rejects_memory_limits_that_overflow() {
+        assert_eq!(mb_to_bytes(2048), Some(2048 * 1024 * 1024));
+        assert_eq!(mb_to_bytes(u64::MAX), None);
+        assert_eq!(mb_to_bytes(i64::MAX as u64 / (1024 * 1024) + 1), None);
+    }
+
+    #[test]
+    fn translates_limits_to_oci_resources() {
+        let limits = ResourceLimits::from_message(&serde_json::json!({
+            "cpuWeight": 200,
+            "pidsLimit": 512,
+            "swapMb": 0,
+            "memoryReservationMb": 4096,
+            "cpusetCpus": "0-1",
+        }))
+        .unwrap();
+        let resources = oci_resources(2048, 1500, &limits, Path::new("/"));
+        assert_eq!(resources["cpu"]["quota"], 150_000);
+        assert_eq!(resources["cpu"]["period"], 100_000);
+        assert_eq!(resources["cpu"]["cpus"], "0-1");
+        assert_eq!(resources["unified"]["cpu.weight"], "200");
+        assert_eq!(resources["pids"]["limit"], 512);
+        assert_eq!(resources["memory"]["limit"], 2048i64 << 20);
+        assert_eq!(resources["memory"]["swap"], 2048i64 << 20);
+        // A reservation above the limit is capped to it.
+        assert_eq!(resources["memory"]["reservation"], 2048i64 << 20);
+
+        let resources = oci_resources(2048, 1500, &ResourceLimits::default(), Path::new("/"));
+        assert!(resources.get("unified").is_none());
+        assert!(resources["memory"].get("swap").is_none());
+
+        for weight in [0, 10_001] {
+            let msg = serde_json::json!({ "cpuWeight": weight });
+            assert!(ResourceLimits::from_message(&msg).is_err());
+        }
+    }
+
+    #[test]
+    fn throttles_only_mounted_volumes() {
+        let limits = ResourceLimits {
+            io_read_bps: Some(1 << 20),
+            io_write_iops: Some(500),
+            ..ResourceLimits::default()
+        };
+        // /proc is always a mount point of its own.
+        let (major, minor) = volume_block_device(Path::new("/proc")).unwrap();
+        let resources = oci_resources(1024, 1000, &limits, Path::new("/proc"));
+        assert_eq!(
+            resources["blockIO"],
+            serde_json::json!({
+                "throttleReadBpsDevice": [{ "major": major, "minor": minor, "rate": 1 << 20 }],
+                "throttleWriteIOPSDevice": [{ "major": major, "minor": minor, "rate": 500 }],
+            })
+        );
+
+        let dir = std::env::temp_dir().join(format!("catalyst-test-{}", uuid::Uuid::new_v4()));
+        fs::create_dir_all(&dir).unwrap();
+        assert_eq!(volume_block_device(&dir), None);
+        assert!(oci_resources(1024, 1000, &limits, &dir)
+            .get("blockIO")
+            .is_none());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn concurrent_pulls_of_an_image_share_one_pull() {
+        let pulls = SharedPulls::default();
+        let started = Arc::new(std::sync::atomic::AtomicUsize::new(0));
+        let (release, released) = tokio::sync::oneshot::channel::<()>();
+        let (first_tx, mut first_rx) = mpsc::unbounded_channel();
+        let (second_tx, mut second_rx) = mpsc::unbounded_channel();
+
+        let first = {
+            let pulls = pulls.clone();
+            let started = started.clone();
+            tokio::spawn(async move {
+                pulls
+                    .run("img", None, Some(first_tx), move |updates| async move {
+                        started.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+                        let _ = released.await;
+                        let _ = updates.send(PullUpdate::Progress(ImagePullProgress {
+                            name: "layer".to_string(),
+                            event: "complete".to_string(),
+                            progress: 10,
+                            total: 10,
+                        }));
+                        Ok(())
+                    })
+                    .await
+            })
+        };
+        while pulls.images().await.is_empty() {
+            tokio::task::yield_now().await;
+        }
+
+        let second_started = started.clone();
+        let (second, _) = tokio::join!(
+            pulls.run("img", None, Some(second_tx), move |_| async move {
+                second_started.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+                Ok(())
+            }),
+            async { release.send(()).unwrap() },
+        );
+        second.unwrap();
+        first.await.unwrap().unwrap();
+
+        assert_eq!(started.load(std::sync::atomic::Ordering::SeqCst), 1);
+        assert_eq!(first_rx.recv().await.unwrap().progress, 10);
+        assert_eq!(second_rx.recv().await.unwrap().progress, 10);
+        assert!(pulls.images().await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn pulls_with_other_credentials_run_separately() {
+        let pulls = SharedPulls::default();
+        let started = Arc::new(std::sync::atomic::AtomicUsize::new(0));
+        let (release, released) = tokio::sync::oneshot::channel::<()>();
+        let credentials = RegistryCredentials {
+            host: "registry.example.com".to_string(),
+            token: Some("tok".to_string()),
+            ..Default::default()
+        };
+
+        let anonymous = {
+            let pulls = pulls.clone();
+            let started = started.clone();
+            tokio::spawn(async move {
+                pulls
+                    .run("img", None, None, move |_| async move {
+                        started.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+                        let _ = released.await;
+                        Err(AgentError::ContainerError("401 Unauthorized".to_string()))
+                    })
+                    .await
+            })
+        };
+        while pulls.images().await.is_empty() {
+            tokio::task::yield_now().await;
+        }
+
+        let authenticated_started = started.clone();
+        pulls
+            .run("img", Some(credentials), None, move |_| async move {
+                authenticated_started.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+                Ok(())
+            })
+            .await
+            .unwrap();
+        assert_eq!(pulls.images().await, vec!["img".to_string()]);
+        release.send(()).unwrap();
+        assert!(anonymous.await.unwrap().is_err());
+        assert_eq!(started.load(std::sync::atomic::Ordering::SeqCst), 2);
+    }
+
+    fn auth_request(host: &str) -> Any {
+        use prost::Message;
+        Any {
+            type_url: String::new(),
+            value: AuthRequest {
+                host: host.to_string(),
+                ..Default::default()
+            }
+            .encode_to_vec(),
+        }
+    }
+
+    #[test]
+    fn answers_registry_auth_for_matching_hosts() {
+        let token = RegistryCredentials {
+            host: "registry.example.com".to_string(),
+            token: Some("tok".to_string()),
+            ..Default::default()
+        };
+        let response = auth_response(&token, &auth_request("registry.example.com"));
+        assert_eq!(response.auth_type(), AuthType::Header);
+        assert_eq!(response.secret, "Bearer tok");
+
+        let basic = RegistryCredentials {
+            host: "docker.io".to_string(),
+            username: Some("user".to_string()),
+            password: Some("secret".to_string()),
+            token: None,
+        };
+        let response = auth_response(&basic, &auth_request("registry-1.docker.io"));
+        assert_eq!(response.auth_type(), AuthType::Credentials);
+        assert_eq!(response.username, "user");
+        assert_eq!(response.secret, "secret");
+
+        let response = auth_response(&token, &auth_request("other.example.com"));
+        assert_eq!(response.auth_type(), AuthType::None);
+        assert!(response.secret.is_empty());
+
+        let undecodable = Any {
+            type_url: String::new(),
+            value: vec![0xff],
+        };
+        assert_eq!(
+            auth_response(&token, &undecodable).auth_type(),
+            AuthType::None
+        );
+    }
+
+    #[test]
+    fn docker_config_carries_registry_credentials() {
+        let credentials = RegistryCredentials {
+            host: "docker.io".to_string(),
+            username: Some("user".to_string()),
+            password: Some("secret".to_string()),
+            token: None,
+        };
+        assert_eq!(
+            docker_config("docker.io", &credentials),
+            serde_json::json!({ "auths": { "https://index.docker.io/v1/": { "auth": "dXNlcjpzZWNyZXQ=" } } })
+        );
+
+        let credentials = RegistryCredentials {
+            host: "ghcr.io".to_string(),
+            username: None,
+            password: None,
+            token: Some("tok".to_string()),
+        };
+        assert_eq!(
+            docker_config("ghcr.io", &credentials),
+            serde_json::json!({ "auths": { "ghcr.io": { "registrytoken": "tok" } } })
+        );
+    }
 }
@@ -0,0 +1,286 @@
+use std::path::Path;
+
+use serde_json::{json, Value};
+
+use crate::config::{DeviceRule, SecurityProfile};
+use crate::{AgentError, AgentResult};
+
+/// User and group server processes run as unless their profile says otherwise.
+pub const DEFAULT_CONTAINER_UID: u32 = 1000;
+pub const DEFAULT_CONTAINER_GID: u32 = 1000;
+
+const DEFAULT_CAPABILITIES: &[&str] = &["CAP_NET_BIND_SERVICE"];
+
+/// /dev/null, zero, random, urandom, tty and console.
+const DEFAULT_DEVICES: &[(i64, i64)] = &[(1, 3), (1, 5), (1, 8), (1, 9), (5, 0), (5, 1)];
+
+impl SecurityProfile {
+    pub fn uid(&self) -> u32 {
+        self.uid.unwrap_or(DEFAULT_CONTAINER_UID)
+    }
+
+    pub fn gid(&self) -> u32 {
+        self.gid.unwrap_or(DEFAULT_CONTAINER_GID)
+    }
+
+    /// Capability names in OCI form, so `net_bind_service` becomes `CAP_NET_BIND_SERVICE`.
+    pub fn capabilities(&self) -> AgentResult<Vec<String>> {
+        let Some(capabilities) = &self.capabilities else {
+            return Ok(DEFAULT_CAPABILITIES
+                .iter()
+                .map(|cap| cap.to_string())
+                .collect());
+        };
+        capabilities
+            .iter()
+            .map(|cap| {
+                let cap = cap.trim().to_ascii_uppercase();
+                let name = cap.strip_prefix("CAP_").unwrap_or(&cap);
+                if name.is_empty() || !name.chars().all(|c| c.is_ascii_uppercase() || c == '_') {
+                    return Err(AgentError::ConfigError(format!(
+                        "Invalid capability '{}'",
+                        cap
+                    )));
+                }
+                Ok(format!("CAP_{}", name))
+            })
+            .collect()
+    }
+
+    /// The spec's `linux.seccomp` section; `None` runs the container unconfined.
+    pub fn seccomp(&self) -> AgentResult<Option<Value>> {
+        match self.seccomp_profile.as_deref().map(str::trim) {
+            None | Some("") | Some("default") => Ok(Some(default_seccomp_profile())),
+            Some("unconfined") => Ok(None),
+            Some(path) => {
+                let content = std::fs::read_to_string(Path::new(path)).map_err(|e| {
+                    AgentError::ConfigError(format!(
+                        "Failed to read seccomp profile {}: {}",
+                        path, e
+                    ))
+                })?;
+                let profile: Value = serde_json::from_str(&content).map_err(|e| {
+                    AgentError::ConfigError(format!("Invalid seccomp profile {}: {}", path, e))
+                })?;
+                if !profile["defaultAction"].is_string() {
+                    return Err(AgentError::ConfigError(format!(
+                        "Seccomp profile {} has no defaultAction",
+                        path
+                    )));
+                }
+                Ok(Some(profile))
+            }
+        }
+    }
+
+    pub fn masked_paths(&self) -> Vec<String> {
+        let mut paths: Vec<String> = masked_paths().into_iter().map(str::to_string).collect();
+        for path in &self.masked_paths {
+            if !paths.contains(path) {
+                paths.push(path.clone());
+            }
+        }
+        paths
+    }
+
+    /// Writable tmpfs mounts, owned by the container user so it can use them on a
+    /// read-only rootfs.
+    pub fn tmpfs_mounts(&self) -> AgentResult<Vec<Value>> {
+        self.tmpfs
+            .iter()
+            .map(|mount| {
+                let path = mount.path.trim_end_matches('/');
+                if !path.starts_with('/') || path == "/data" || path.split('/').any(|c| c == "..")
+                {
+                    return Err(AgentError::ConfigError(format!(
+                        "Invalid tmpfs path '{}'",
+                        mount.path
+                    )));
+                }
+                let mut options = vec![
+                    "nosuid".to_string(),
+                    "nodev".to_string(),
+                    "mode=1777".to_string(),
+                    format!("uid={}", self.uid()),
+                    format!("gid={}", self.gid()),
+                ];
+                if let Some(size_mb) = mount.size_mb {
+                    options.push(format!("size={}m", size_mb));
+                }
+                Ok(json!({"destination": path, "type": "tmpfs", "source": "tmpfs", "options": options}))
+            })
+            .collect()
+    }
+
+    /// The device cgroup rules: deny everything, then allow the listed devices.
+    pub fn device_rules(&self) -> AgentResult<Value> {
+        let mut rules = vec![json!({"allow": false, "access": "rwm"})];
+        match &self.devices {
+            None => rules.extend(DEFAULT_DEVICES.iter().map(|(major, minor)| {
+                json!({"allow": true, "type": "c", "major": major, "minor": minor, "access": "rwm"})
+            })),
+            Some(devices) => {
+                for device in devices {
+                    rules.push(device_rule(device)?);
+                }
+            }
+        }
+        Ok(Value::Array(rules))
+    }
+}
+
+fn device_rule(device: &DeviceRule) -> AgentResult<Value> {
+    let valid_access =
+        !device.access.is_empty() && device.access.chars().all(|c| matches!(c, 'r' | 'w' | 'm'));
+    if !matches!(device.kind.as_str(), "c" | "b") || !valid_access {
+        return Err(AgentError::ConfigError(format!(
+            "Invalid device rule {} {}:{} {}",
+            device.kind, device.major, device.minor, device.access
+        )));
+    }
+    Ok(json!({
+        "allow": true,
+        "type": device.kind,
+        "major": device.major,
+        "minor": device.minor,
+        "access": device.access,
+    }))
+}
+
+pub fn masked_paths() -> Vec<&'static str> {
+    vec![
+        // Original masked paths
+        "/proc/kcore",
+        "/proc/latency_stats",
+        "/proc/timer_list",
+        "/proc/timer_stats",
+        "/proc/sched_debug",
+        "/sys/firmware",
+        // Additional security-sensitive paths
+        "/proc/kallsyms", // Kernel symbols - useful for exploit development
+        "/proc/self/mem", // Memory manipulation vector
+        "/sys/kernel",    // Kernel parameters and addresses
+        "/sys/class",     // Hardware enumeration for fingerprinting
+        "/proc/slabinfo", // Kernel slab allocator info
+        "/proc/modules",  // Loaded kernel modules
+    ]
+}
+
+fn seccomp_arches() -> Vec<&'static str> {
+    match std::env::consts::ARCH {
+        "x86_64" => vec!["SCMP_ARCH_X86_64", "SCMP_ARCH_X86", "SCMP_ARCH_X32"],
+        "aarch64" => vec!["SCMP_ARCH_AARCH64", "SCMP_ARCH_ARM"],
+        "arm" => vec!["SCMP_ARCH_ARM"],
+        _ => Vec::new(),
+    }
+}
+
+pub fn default_seccomp_profile() -> serde_json::Value {
+    // Deny-list a small set of high-risk syscalls while keeping broad compatibility.
+    // This is intentionally conservative; consumers can harden further via host policy.
+    serde_json::json!({
+        "defaultAction": "SCMP_ACT_ALLOW",
+        "architectures": seccomp_arches(),
+        "syscalls": [
+            {
+                "names": [
+                    "acct",
+                    "add_key",
+                    "bpf",
+                    "delete_module",
+                    "finit_module",
+                    "init_module",
+                    "iopl",
+                    "ioperm",
+                    "kexec_file_load",
+                    "kexec_load",
+                    "keyctl",
+                    "mount",
+                    "open_by_handle_at",
+                    "perf_event_open",
+                    "pivot_root",
+                    "process_vm_readv",
+                    "process_vm_writev",
+                    "ptrace",
+                    "quotactl",
+                    "reboot",
+                    "request_key",
+                    "setns",
+                    "swapoff",
+                    "swapon",
+                    "syslog",
+                    "umount2",
+                    "unshare"
+                ],
+                "action": "SCMP_ACT_ERRNO",
+                "errnoRet": 1
+            }
+        ]
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::TmpfsMount;
+
+    #[test]
+    fn default_profile_keeps_hardened_settings() {
+        let profile = SecurityProfile::default();
+        assert_eq!((profile.uid(), profile.gid()), (1000, 1000));
+        assert_eq!(
+            profile.capabilities().unwrap(),
+            vec!["CAP_NET_BIND_SERVICE"]
+        );
+        assert!(profile.seccomp().unwrap().is_some());
+        assert_eq!(profile.masked_paths().len(), masked_paths().len());
+        assert_eq!(
+            profile.device_rules().unwrap().as_array().unwrap().len(),
+            DEFAULT_DEVICES.len() + 1
+        );
+    }
+
+    #[test]
+    fn applies_profile_overrides() {
+        let profile = SecurityProfile {
+            uid: Some(2000),
+            capabilities: Some(vec!["net_bind_service".into(), "CAP_SYS_NICE".into()]),
+            seccomp_profile: Some("unconfined".into()),
+            tmpfs: vec![TmpfsMount {
+                path: "/tmp".into(),
+                size_mb: Some(64),
+            }],
+            masked_paths: vec!["/proc/cpuinfo".into()],
+            devices: Some(vec![DeviceRule {
+                kind: "c".into(),
+                major: 10,
+                minor: 200,
+                access: "rw".into(),
+            }]),
+            ..SecurityProfile::default()
+        };
+        assert_eq!(
+            profile.capabilities().unwrap(),
+            vec!["CAP_NET_BIND_SERVICE", "CAP_SYS_NICE"]
+        );
+        assert!(profile.seccomp().unwrap().is_none());
+        assert!(profile
+            .masked_paths()
+            .contains(&"/proc/cpuinfo".to_string()));
+        let tmpfs = profile.tmpfs_mounts().unwrap();
+        assert_eq!(tmpfs[0]["options"][3], "uid=2000");
+        assert_eq!(tmpfs[0]["options"][5], "size=64m");
+        assert_eq!(profile.device_rules().unwrap()[1]["minor"], 200);
+
+        let invalid = SecurityProfile {
+            tmpfs: vec![TmpfsMount {
+                path: "/data".into(),
+                size_mb: None,
+            }],
+            capabilities: Some(vec!["CAP_SYS ADMIN".into()]),
+            ..SecurityProfile::default()
+        };
+        assert!(invalid.tmpfs_mounts().is_err());
+        assert!(invalid.capabilities().is_err());
+    }
+}
@@ -0,0 +1,233 @@
+use std::collections::HashMap;
+use std::time::Duration;
+
+use regex::Regex;
+use serde_json::Value;
+use tokio::sync::RwLock;
+
+use crate::{AgentError, AgentResult};
+
+const DEFAULT_STARTUP_TIMEOUT_SECS: u64 = 300;
+const MAX_PARTIAL_LINE_BYTES: usize = 8 * 1024;
+
+/// Console patterns that mark a server as ready, taken from the template's
+/// `features.startupDone` (a regex or list of regexes) and
+/// `features.startupTimeoutSeconds`.
+#[derive(Clone, Debug)]
+pub struct StartupPatterns {
+    patterns: Vec<Regex>,
+    pub timeout: Duration,
+}
+
+impl StartupPatterns {
+    /// Returns `None` when the template does not define any startup pattern.
+    pub fn from_template(template: &Value) -> AgentResult<Option<Self>> {
+        let Some(features) = template.get("features") else {
+            return Ok(None);
+        };
+        let raw: Vec<&str> = match features.get("startupDone") {
+            Some(Value::String(pattern)) => vec![pattern.as_str()],
+            Some(Value::Array(items)) => items.iter().filter_map(Value::as_str).collect(),
+            _ => Vec::new(),
+        };
+        let raw: Vec<&str> = raw
+            .into_iter()
+            .map(str::trim)
+            .filter(|pattern| !pattern.is_empty())
+            .collect();
+        if raw.is_empty() {
+            return Ok(None);
+        }
+
+        let patterns = raw
+            .iter()
+            .map(|pattern| {
+                Regex::new(pattern).map_err(|e| {
+                    AgentError::InvalidRequest(format!(
+                        "Invalid startupDone pattern '{}': {}",
+                        pattern, e
+                    ))
+                })
+            })
+            .collect::<AgentResult<Vec<_>>>()?;
+        let timeout_secs = features
+            .get("startupTimeoutSeconds")
+            .and_then(Value::as_u64)
+            .filter(|secs| *secs > 0)
+            .unwrap_or(DEFAULT_STARTUP_TIMEOUT_SECS);
+
+        Ok(Some(Self {
+            patterns,
+            timeout: Duration::from_secs(timeout_secs),
+        }))
+    }
+
+    fn is_match(&self, line: &str) -> bool {
+        let line = strip_ansi(line);
+        self.patterns.iter().any(|pattern| pattern.is_match(&line))
+    }
+}
+
+struct PendingStartup {
+    patterns: StartupPatterns,
+    partial_line: String,
+    generation: u64,
+}
+
+/// Tracks servers whose process is up but has not yet printed a startup-done line.
+#[derive(Default)]
+pub struct StartupDetector {
+    /// Last known patterns per server, so restarts without a template reuse them.
+    configs: RwLock<HashMap<String, StartupPatterns>>,
+    pending: RwLock<HashMap<String, PendingStartup>>,
+    next_generation: std::sync::atomic::AtomicU64,
+}
+
+impl StartupDetector {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub async fn remember(&self, server_id: &str, patterns: Option<StartupPatterns>) {
+        let mut configs = self.configs.write().await;
+        match patterns {
+            Some(patterns) => {
+                configs.insert(server_id.to_string(), patterns);
+            }
+            None => {
+                configs.remove(server_id);
+            }
+        }
+    }
+
+    /// Start waiting for the server's remembered patterns. Returns the generation and
+    /// timeout of the new watch, or `None` if the server has no startup patterns.
+    pub async fn arm(&self, server_id: &str) -> Option<(u64, Duration)> {
+        let patterns = self.configs.read().await.get(server_id).cloned()?;
+        let generation = self
+            .next_generation
+            .fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+        let timeout = patterns.timeout;
+        self.pending.write().await.insert(
+            server_id.to_string(),
+            PendingStartup {
+                patterns,
+                partial_line: String::new(),
+                generation,
+            },
+        );
+        Some((generation, timeout))
+    }
+
+    pub async fn is_pending(&self, server_id: &str) -> bool {
+        self.pending.read().await.contains_key(server_id)
+    }
+
+    /// Feed console output. Returns true when a pattern matched; the watch is cleared.
+    pub async fn observe(&self, server_id: &str, data: &str) -> bool {
+        if !self.pending.read().await.contains_key(server_id) {
+            return false;
+        }
+        let mut pending = self.pending.write().await;
+        let Some(watch) = pending.get_mut(server_id) else {
+            return false;
+        };
+
+        watch.partial_line.push_str(data);
+        let mut matched = false;
+        while let Some(pos) = watch.partial_line.find(['\n', '\r']) {
+            let line: String = watch.partial_line.drain(..=pos).collect();
+            if watch.patterns.is_match(&line) {
+                matched = true;
+                break;
+            }
+        }
+        // Prompts are sometimes printed without a trailing newline.
+        if !matched && watch.patterns.is_match(&watch.partial_line) {
+            matched = true;
+        }
+        if watch.partial_line.len() > MAX_PARTIAL_LINE_BYTES {
+            watch.partial_line.clear();
+        }
+
+        if matched {
+            pending.remove(server_id);
+        }
+        matched
+    }
+
+    /// Drop the watch if it is still the one identified by `generation`. Returns true
+    /// when the deadline passed without a match.
+    pub async fn expire(&self, server_id: &str, generation: u64) -> bool {
+        let mut pending = self.pending.write().await;
+        if pending
+            .get(server_id)
+            .is_some_and(|watch| watch.generation == generation)
+        {
+            pending.remove(server_id);
+            return true;
+        }
+        false
+    }
+
+    pub async fn clear(&self, server_id: &str) {
+        self.pending.write().await.remove(server_id);
+    }
+}
+
+fn strip_ansi(line: &str) -> std::borrow::Cow<'_, str> {
+    static ANSI: std::sync::OnceLock<Regex> = std::sync::OnceLock::new();
+    let ansi = ANSI.get_or_init(|| {
+        Regex::new(r"\x1b\[[0-9;?]*[ -/]*[@-~]|\x1b\][^\x07]*\x07").expect("valid ANSI regex")
+    });
+    ansi.replace_all(line, "")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[tokio::test]
+    async fn matches_pattern_split_across_chunks() {
+        let template = json!({ "features": { "startupDone": [r"Done \(\d+\.\d+s\)! For help"] } });
+        let patterns = StartupPatterns::from_template(&template).unwrap();
+        let detector = StartupDetector::new();
+        detector.remember("srv", patterns).await;
+        detector.arm("srv").await.unwrap();
+
+        assert!(!detector.observe("srv", "[Server] Preparing level\n").await);
+        assert!(!detector.observe("srv", "\x1b[32mDone (3.1s").await);
+        assert!(
+            detector
+                .observe("srv", ")! For help, type \"help\"\x1b[0m\n")
+                .await
+        );
+        assert!(!detector.is_pending("srv").await);
+    }
+
+    #[tokio::test]
+    async fn expire_ignores_stale_generation() {
+        let template = json!({
+            "features": { "startupDone": "ready", "startupTimeoutSeconds": 5 }
+        });
+        let patterns = StartupPatterns::from_template(&template).unwrap();
+        assert_eq!(patterns.as_ref().unwrap().timeout, Duration::from_secs(5));
+        let detector = StartupDetector::new();
+        detector.remember("srv", patterns).await;
+        let (first, _) = detector.arm("srv").await.unwrap();
+        let (second, _) = detector.arm("srv").await.unwrap();
+
+        assert!(!detector.expire("srv", first).await);
+        assert!(detector.expire("srv", second).await);
+    }
+
+    #[test]
+    fn rejects_invalid_pattern() {
+        let template = json!({ "features": { "startupDone": "(" } });
+        assert!(StartupPatterns::from_template(&template).is_err());
+        assert!(StartupPatterns::from_template(&json!({}))
+            .unwrap()
+            .is_none());
+    }
+}
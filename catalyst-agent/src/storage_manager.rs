@@ -1,3 +1,4 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap};
 use std::path::{Path, PathBuf};
 use tokio::fs;
 use tokio::io::AsyncWriteExt;
@@ -9,11 +10,25 @@ use serde_json::Value;
 
 pub struct StorageManager {
     data_dir: PathBuf,
+    index_lock: tokio::sync::Mutex<()>,
+}
+
+/// Filesystem usage of a server's mounted data volume.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct DiskUsage {
+    pub total_bytes: u64,
+    pub used_bytes: u64,
+    pub available_bytes: u64,
+    pub inodes_total: u64,
+    pub inodes_used: u64,
 }
 
 impl StorageManager {
     pub fn new(data_dir: PathBuf) -> Self {
-        Self { data_dir }
+        Self {
+            data_dir,
+            index_lock: tokio::sync::Mutex::new(()),
+        }
     }
 
     pub async fn ensure_mounted(
@@ -250,6 +265,177 @@ impl StorageManager {
         Ok(false)
     }
 
+    /// Usage of the server's data volume, measured host-side with statvfs. `None` when the
+    /// volume is not mounted, since the directory would then report the host filesystem.
+    pub async fn disk_usage(&self, server_uuid: &str) -> AgentResult<Option<DiskUsage>> {
+        let mount_dir = self.data_dir.join(server_uuid);
+        if !self.is_mounted(&mount_dir).await? {
+            return Ok(None);
+        }
+        let stat = spawn_blocking(move || nix::sys::statvfs::statvfs(&mount_dir))
+            .await
+            .map_err(|e| AgentError::FileSystemError(format!("statvfs task failed: {}", e)))?
+            .map_err(|e| AgentError::FileSystemError(format!("statvfs failed: {}", e)))?;
+        let fragment = stat.fragment_size() as u64;
+        let total_bytes = stat.blocks() as u64 * fragment;
+        Ok(Some(DiskUsage {
+            total_bytes,
+            used_bytes: total_bytes.saturating_sub(stat.blocks_free() as u64 * fragment),
+            available_bytes: stat.blocks_available() as u64 * fragment,
+            inodes_total: stat.files() as u64,
+            inodes_used: (stat.files() as u64).saturating_sub(stat.files_free() as u64),
+        }))
+    }
+
+    // --- Server index ---------------------------------------------------------------
+    // Containers are named by server id while volumes are keyed by server uuid.
+    fn server_index_path(&self) -> PathBuf {
+        self.data_dir.join("servers.json")
+    }
+
+    /// Map of server id to server uuid for every server this node has installed or started.
+    pub async fn server_index(&self) -> HashMap<String, String> {
+        match fs::read_to_string(self.server_index_path()).await {
+            Ok(content) => serde_json::from_str(&content).unwrap_or_else(|e| {
+                tracing::warn!("Ignoring unreadable server index: {}", e);
+                HashMap::new()
+            }),
+            Err(_) => HashMap::new(),
+        }
+    }
+
+    pub async fn record_server(&self, server_id: &str, server_uuid: &str) -> AgentResult<()> {
+        let _guard = self.index_lock.lock().await;
+        let mut index = self.server_index().await;
+        if index.get(server_id).map(String::as_str) == Some(server_uuid) {
+            return Ok(());
+        }
+        index.insert(server_id.to_string(), server_uuid.to_string());
+        self.write_index(&self.server_index_path(), &index).await
+    }
+
+    fn server_images_path(&self) -> PathBuf {
+        self.data_dir.join("server-images.json")
+    }
+
+    /// Image each server last used per role (`runtime`, `installer`); image GC keeps these.
+    pub async fn server_images(&self) -> HashMap<String, HashMap<String, String>> {
+        match fs::read_to_string(self.server_images_path()).await {
+            Ok(content) => serde_json::from_str(&content).unwrap_or_else(|e| {
+                tracing::warn!("Ignoring unreadable server image index: {}", e);
+                HashMap::new()
+            }),
+            Err(_) => HashMap::new(),
+        }
+    }
+
+    pub async fn record_server_image(
+        &self,
+        server_id: &str,
+        role: &str,
+        image: &str,
+    ) -> AgentResult<()> {
+        let _guard = self.index_lock.lock().await;
+        let mut index = self.server_images().await;
+        let roles = index.entry(server_id.to_string()).or_default();
+        if roles.get(role).map(String::as_str) == Some(image) {
+            return Ok(());
+        }
+        roles.insert(role.to_string(), image.to_string());
+        self.write_index(&self.server_images_path(), &index).await
+    }
+
+    /// `server_images` without the servers that are gone from this node: missing from the
+    /// server index, or with neither a data directory nor a volume left. The agent is not
+    /// told about deletions, so this is what lets image GC reclaim their images. With
+    /// `forget`, those entries are also removed from the index.
+    pub async fn live_server_images(
+        &self,
+        forget: bool,
+    ) -> AgentResult<HashMap<String, HashMap<String, String>>> {
+        let _guard = self.index_lock.lock().await;
+        let servers = self.server_index().await;
+        let mut index = self.server_images().await;
+        let recorded = index.len();
+        index.retain(|server_id, _| {
+            servers.get(server_id).is_some_and(|server_uuid| {
+                self.data_dir.join(server_uuid).exists() || self.image_path(server_uuid).exists()
+            })
+        });
+        if forget && index.len() != recorded {
+            info!(
+                "Forgetting images of {} deleted servers",
+                recorded - index.len()
+            );
+            self.write_index(&self.server_images_path(), &index).await?;
+        }
+        Ok(index)
+    }
+
+    fn pinned_images_path(&self) -> PathBuf {
+        self.data_dir.join("pinned-images.json")
+    }
+
+    /// Images prefetched for future servers; image GC keeps these until they are released.
+    pub async fn pinned_images(&self) -> BTreeSet<String> {
+        match fs::read_to_string(self.pinned_images_path()).await {
+            Ok(content) => serde_json::from_str(&content).unwrap_or_else(|e| {
+                tracing::warn!("Ignoring unreadable pinned image list: {}", e);
+                BTreeSet::new()
+            }),
+            Err(_) => BTreeSet::new(),
+        }
+    }
+
+    pub async fn pin_images(&self, images: &[String], pinned: bool) -> AgentResult<()> {
+        let _guard = self.index_lock.lock().await;
+        let mut pins = self.pinned_images().await;
+        for image in images {
+            if pinned {
+                pins.insert(image.clone());
+            } else {
+                pins.remove(image);
+            }
+        }
+        self.write_index(&self.pinned_images_path(), &pins).await
+    }
+
+    fn user_namespace_slots_path(&self) -> PathBuf {
+        self.data_dir.join("userns-slots.json")
+    }
+
+    /// The user namespace id range of a server, as an index into the configured pool.
+    /// Allocated on first use and kept, so a volume never has to be shifted twice. Slots
+    /// are never freed because the agent is not told when a server is deleted; remove a
+    /// server's entry from `userns-slots.json` by hand together with its volume.
+    pub async fn user_namespace_slot(&self, server_uuid: &str) -> AgentResult<u32> {
+        let _guard = self.index_lock.lock().await;
+        let path = self.user_namespace_slots_path();
+        let mut slots: BTreeMap<String, u32> = match fs::read_to_string(&path).await {
+            Ok(content) => serde_json::from_str(&content).map_err(|e| {
+                AgentError::InternalError(format!("Unreadable user namespace slots: {}", e))
+            })?,
+            Err(_) => BTreeMap::new(),
+        };
+        if let Some(slot) = slots.get(server_uuid) {
+            return Ok(*slot);
+        }
+        let taken: BTreeSet<u32> = slots.values().copied().collect();
+        let slot = (0..).find(|slot| !taken.contains(slot)).unwrap_or_default();
+        slots.insert(server_uuid.to_string(), slot);
+        self.write_index(&path, &slots).await?;
+        Ok(slot)
+    }
+
+    /// Replace an index file atomically so a crash never leaves it truncated.
+    async fn write_index<T: serde::Serialize>(&self, path: &Path, index: &T) -> AgentResult<()> {
+        fs::create_dir_all(&self.data_dir).await?;
+        let tmp = path.with_extension("json.tmp");
+        fs::write(&tmp, serde_json::to_vec(index)?).await?;
+        fs::rename(&tmp, path).await?;
+        Ok(())
+    }
+
     // --- Metrics buffering helpers ------------------------------------------------
     fn metrics_buffer_path(&self) -> PathBuf {
         self.data_dir.join("metrics_buffer.jsonl")
@@ -317,3 +503,39 @@ fn run(command: &str, args: &[&str]) -> AgentResult<()> {
     }
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn deleted_servers_stop_referencing_images() {
+        let data_dir = std::env::temp_dir().join(format!("catalyst-test-{}", uuid::Uuid::new_v4()));
+        let storage = StorageManager::new(data_dir.clone());
+        for (server_id, server_uuid, image) in [
+            ("srv-1", "uuid-1", "alpine:3.19"),
+            ("srv-2", "uuid-2", "alpine:3.18"),
+        ] {
+            storage.record_server(server_id, server_uuid).await.unwrap();
+            storage
+                .record_server_image(server_id, "runtime", image)
+                .await
+                .unwrap();
+        }
+        storage
+            .record_server_image("srv-3", "runtime", "alpine:3.17")
+            .await
+            .unwrap();
+        tokio::fs::create_dir_all(data_dir.join("uuid-1"))
+            .await
+            .unwrap();
+
+        let live = storage.live_server_images(false).await.unwrap();
+        assert_eq!(live.keys().collect::<Vec<_>>(), vec!["srv-1"]);
+        assert_eq!(storage.server_images().await.len(), 3);
+        storage.live_server_images(true).await.unwrap();
+        assert_eq!(storage.server_images().await, live);
+
+        let _ = tokio::fs::remove_dir_all(&data_dir).await;
+    }
+}
@@ -0,0 +1,167 @@
+use std::os::unix::fs::{MetadataExt, PermissionsExt};
+use std::path::Path;
+
+use serde_json::{json, Value};
+use tokio::task::spawn_blocking;
+use tracing::{info, warn};
+
+use crate::config::UserNamespaceConfig;
+use crate::{AgentError, AgentResult};
+
+/// Container ids `0..size` of one server, mapped to host ids `host_base..host_base + size`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct IdMapping {
+    pub host_base: u32,
+    pub size: u32,
+}
+
+impl IdMapping {
+    /// Host id of a container uid or gid. Ids past the end of the range have no host id.
+    pub fn host_id(&self, id: u32) -> AgentResult<u32> {
+        if id >= self.size {
+            return Err(AgentError::ConfigError(format!(
+                "Container id {} is outside the user namespace range of {} ids; raise \
+                 security.user_namespaces.range_size or use a lower uid/gid",
+                id, self.size
+            )));
+        }
+        Ok(self.host_base + id)
+    }
+
+    /// `linux.uidMappings` / `linux.gidMappings` entries of the OCI spec.
+    pub fn oci_mappings(&self) -> Value {
+        json!([{"containerID": 0, "hostID": self.host_base, "size": self.size}])
+    }
+}
+
+impl UserNamespaceConfig {
+    /// Host range of the server holding `slot`.
+    pub fn mapping(&self, slot: u32) -> AgentResult<IdMapping> {
+        if self.range_size == 0 {
+            return Err(AgentError::ConfigError(
+                "security.user_namespaces.range_size must be positive".to_string(),
+            ));
+        }
+        let host_base = slot
+            .checked_mul(self.range_size)
+            .and_then(|offset| self.uid_base.checked_add(offset))
+            .filter(|base| base.checked_add(self.range_size).is_some())
+            .ok_or_else(|| {
+                AgentError::ConfigError(format!(
+                    "No host id range left for user namespace slot {}",
+                    slot
+                ))
+            })?;
+        Ok(IdMapping {
+            host_base,
+            size: self.range_size,
+        })
+    }
+
+    /// Where `id` belongs in `mapping`: ids of another server's range keep their offset,
+    /// and unshifted ids from before user namespaces were enabled are taken as container
+    /// ids. Ids outside both stay as they are.
+    fn shifted_id(&self, id: u32, mapping: IdMapping) -> u32 {
+        let offset = if id >= self.uid_base && self.range_size > 0 {
+            (id - self.uid_base) % self.range_size
+        } else if id < mapping.size {
+            id
+        } else {
+            return id;
+        };
+        mapping.host_base + offset
+    }
+}
+
+/// Move the ownership of every file under `dir` into the server's host range. Does
+/// nothing when `dir` itself is already inside it, so it only walks the volume the first
+/// time a server starts with user namespaces or after its range changed. `dir` is shifted
+/// last, so a walk that fails partway is repeated on the next start.
+pub async fn shift_ownership(
+    dir: &Path,
+    mapping: IdMapping,
+    config: &UserNamespaceConfig,
+) -> AgentResult<()> {
+    let root = std::fs::symlink_metadata(dir)?;
+    let in_range = |id: u32| (mapping.host_base..mapping.host_base + mapping.size).contains(&id);
+    if in_range(root.uid()) && in_range(root.gid()) {
+        return Ok(());
+    }
+
+    info!(
+        "Shifting ownership of {} into host ids {}..{}",
+        dir.display(),
+        mapping.host_base,
+        mapping.host_base + mapping.size
+    );
+    let dir = dir.to_path_buf();
+    let config = config.clone();
+    spawn_blocking(move || {
+        let shift = |path: &Path, metadata: &std::fs::Metadata| -> std::io::Result<()> {
+            let uid = config.shifted_id(metadata.uid(), mapping);
+            let gid = config.shifted_id(metadata.gid(), mapping);
+            if (uid, gid) != (metadata.uid(), metadata.gid()) {
+                std::os::unix::fs::lchown(path, Some(uid), Some(gid))?;
+                // chown clears setuid and setgid on regular files.
+                if metadata.is_file() && metadata.mode() & 0o6000 != 0 {
+                    std::fs::set_permissions(
+                        path,
+                        std::fs::Permissions::from_mode(metadata.mode() & 0o7777),
+                    )?;
+                }
+            }
+            Ok(())
+        };
+        let mut pending = vec![dir.clone()];
+        while let Some(path) = pending.pop() {
+            let metadata = std::fs::symlink_metadata(&path)?;
+            // Stay on the server's volume.
+            if metadata.dev() != root.dev() {
+                continue;
+            }
+            if path != dir {
+                shift(&path, &metadata)?;
+            }
+            if metadata.is_dir() {
+                for entry in std::fs::read_dir(&path)? {
+                    pending.push(entry?.path());
+                }
+            }
+        }
+        shift(&dir, &root)
+    })
+    .await
+    .map_err(|e| AgentError::InternalError(format!("Ownership shift task failed: {}", e)))?
+    .map_err(|e| {
+        warn!("Failed to shift ownership: {}", e);
+        AgentError::FileSystemError(format!("Failed to shift file ownership: {}", e))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn maps_and_shifts_ids() {
+        let config = UserNamespaceConfig {
+            enabled: true,
+            uid_base: 1_000_000,
+            range_size: 65536,
+        };
+        let mapping = config.mapping(2).unwrap();
+        assert_eq!(mapping.host_base, 1_131_072);
+        assert_eq!(mapping.host_id(1000).unwrap(), 1_132_072);
+        assert_eq!(mapping.host_id(65535).unwrap(), 1_196_607);
+        assert!(mapping.host_id(65536).is_err());
+        assert!(config.mapping(u32::MAX / 65536).is_err());
+
+        // Unshifted, from another server's range, and already in place.
+        assert_eq!(config.shifted_id(1000, mapping), 1_132_072);
+        assert_eq!(config.shifted_id(1_001_000, mapping), 1_132_072);
+        assert_eq!(config.shifted_id(1_132_072, mapping), 1_132_072);
+        assert_eq!(config.shifted_id(0, mapping), 1_131_072);
+        // Host ids below the pool that no container can own are left alone.
+        assert_eq!(config.shifted_id(70_000, mapping), 70_000);
+    }
+}
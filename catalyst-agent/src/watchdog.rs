@@ -0,0 +1,307 @@
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+use serde_json::Value;
+use tokio::sync::RwLock;
+
+use crate::game_query::QueryStatus;
+
+const DEFAULT_GRACE_SECS: u64 = 300;
+const DEFAULT_QUERY_FAILURES: u32 = 4;
+const DEFAULT_CPU_PINNED_PERCENT: f64 = 98.0;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum WatchdogAction {
+    /// Only report the problem to the backend.
+    Alert,
+    /// Run the server's graceful stop sequence.
+    Stop,
+    /// Kill the server and start it again.
+    Restart,
+}
+
+impl WatchdogAction {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Alert => "alert",
+            Self::Stop => "stop",
+            Self::Restart => "restart",
+        }
+    }
+}
+
+/// Hung-server detection settings from the template's `features.watchdog` object:
+/// `action`, `queryFailures`, `silentMinutes`, `cpuPinnedMinutes`, `cpuPinnedPercent`
+/// and `graceSeconds`. `true` enables alert-only query checks with defaults.
+#[derive(Clone, Debug)]
+pub struct WatchdogConfig {
+    pub action: WatchdogAction,
+    query_failures: Option<u32>,
+    silent_for: Option<Duration>,
+    cpu_pinned_for: Option<Duration>,
+    cpu_pinned_percent: f64,
+    grace: Duration,
+    cpu_cores: f64,
+}
+
+impl WatchdogConfig {
+    pub fn from_template(template: &Value, cpu_millicores: u64) -> Option<Self> {
+        let settings = template.get("features")?.get("watchdog")?;
+        let settings = match settings {
+            Value::Bool(true) => &Value::Null,
+            Value::Object(_) if settings["enabled"].as_bool() != Some(false) => settings,
+            _ => return None,
+        };
+        let minutes = |key: &str| {
+            settings
+                .get(key)
+                .and_then(Value::as_u64)
+                .filter(|value| *value > 0)
+                .map(|value| Duration::from_secs(value * 60))
+        };
+
+        Some(Self {
+            action: match settings.get("action").and_then(Value::as_str) {
+                Some("stop") => WatchdogAction::Stop,
+                Some("restart") => WatchdogAction::Restart,
+                _ => WatchdogAction::Alert,
+            },
+            query_failures: match settings.get("queryFailures").and_then(Value::as_u64) {
+                Some(0) => None,
+                Some(value) => Some(value.min(u32::MAX as u64) as u32),
+                None => Some(DEFAULT_QUERY_FAILURES),
+            },
+            silent_for: minutes("silentMinutes"),
+            cpu_pinned_for: minutes("cpuPinnedMinutes"),
+            cpu_pinned_percent: settings
+                .get("cpuPinnedPercent")
+                .and_then(Value::as_f64)
+                .filter(|value| *value > 0.0)
+                .unwrap_or(DEFAULT_CPU_PINNED_PERCENT),
+            grace: Duration::from_secs(
+                settings
+                    .get("graceSeconds")
+                    .and_then(Value::as_u64)
+                    .unwrap_or(DEFAULT_GRACE_SECS),
+            ),
+            cpu_cores: cpu_millicores.max(1) as f64 / 1000.0,
+        })
+    }
+}
+
+struct ServerWatch {
+    config: WatchdogConfig,
+    armed_at: Instant,
+    last_output: Instant,
+    cpu_sample: Option<(Instant, u64)>,
+    pinned_since: Option<Instant>,
+    /// Players seen by the last successful query; a hung server stops answering queries.
+    last_players: u32,
+    /// Set once a trip was reported, until every signal clears again.
+    tripped: bool,
+}
+
+/// Per-server hung detection. Signals are evaluated periodically by the handler.
+#[derive(Default)]
+pub struct Watchdog {
+    configs: RwLock<HashMap<String, WatchdogConfig>>,
+    watches: RwLock<HashMap<String, ServerWatch>>,
+}
+
+impl Watchdog {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub async fn remember(&self, server_id: &str, config: Option<WatchdogConfig>) {
+        let mut configs = self.configs.write().await;
+        match config {
+            Some(config) => {
+                configs.insert(server_id.to_string(), config);
+            }
+            None => {
+                configs.remove(server_id);
+            }
+        }
+    }
+
+    /// Begin watching a freshly started server with its remembered settings.
+    pub async fn arm(&self, server_id: &str) {
+        let Some(config) = self.configs.read().await.get(server_id).cloned() else {
+            return;
+        };
+        let now = Instant::now();
+        self.watches.write().await.insert(
+            server_id.to_string(),
+            ServerWatch {
+                config,
+                armed_at: now,
+                last_output: now,
+                cpu_sample: None,
+                pinned_since: None,
+                last_players: 0,
+                tripped: false,
+            },
+        );
+    }
+
+    pub async fn disarm(&self, server_id: &str) {
+        self.watches.write().await.remove(server_id);
+    }
+
+    pub async fn note_output(&self, server_id: &str) {
+        if let Some(watch) = self.watches.write().await.get_mut(server_id) {
+            watch.last_output = Instant::now();
+        }
+    }
+
+    pub async fn armed_servers(&self) -> Vec<String> {
+        self.watches.read().await.keys().cloned().collect()
+    }
+
+    /// Check the server's signals. Returns the action and a description the first time
+    /// a signal trips; further calls stay quiet until all signals have cleared.
+    pub async fn evaluate(
+        &self,
+        server_id: &str,
+        query: Option<&QueryStatus>,
+        cpu_usage_usec: Option<u64>,
+        now: Instant,
+    ) -> Option<(WatchdogAction, String)> {
+        let mut watches = self.watches.write().await;
+        let watch = watches.get_mut(server_id)?;
+        let config = &watch.config;
+
+        // CPU is sampled even during the grace period so the first window is complete.
+        if let Some(usage) = cpu_usage_usec {
+            if let Some((sampled_at, previous)) = watch.cpu_sample {
+                let wall_usec = now.duration_since(sampled_at).as_micros() as f64;
+                if wall_usec > 0.0 && usage >= previous {
+                    let percent =
+                        (usage - previous) as f64 / (wall_usec * config.cpu_cores) * 100.0;
+                    if percent >= config.cpu_pinned_percent {
+                        watch.pinned_since.get_or_insert(sampled_at);
+                    } else {
+                        watch.pinned_since = None;
+                    }
+                }
+            }
+            watch.cpu_sample = Some((now, usage));
+        }
+
+        if now.duration_since(watch.armed_at) < config.grace {
+            return None;
+        }
+
+        let mut reasons = Vec::new();
+        if let (Some(limit), Some(query)) = (config.query_failures, query) {
+            if query.consecutive_failures >= limit {
+                reasons.push(format!(
+                    "{} consecutive query probes failed ({})",
+                    query.consecutive_failures,
+                    query.error.as_deref().unwrap_or("no response")
+                ));
+            }
+        }
+        if let Some(query) = query.filter(|query| query.online) {
+            watch.last_players = query.info.players.unwrap_or(0);
+        }
+        let config = &watch.config;
+        if let Some(limit) = config.silent_for {
+            let players = watch.last_players;
+            let silent = now.duration_since(watch.last_output);
+            if players > 0 && silent >= limit {
+                reasons.push(format!(
+                    "no console output for {}m with {} player(s) online",
+                    silent.as_secs() / 60,
+                    players
+                ));
+            }
+        }
+        if let (Some(limit), Some(since)) = (config.cpu_pinned_for, watch.pinned_since) {
+            let pinned = now.duration_since(since);
+            if pinned >= limit {
+                reasons.push(format!(
+                    "CPU above {:.0}% for {}m",
+                    config.cpu_pinned_percent,
+                    pinned.as_secs() / 60
+                ));
+            }
+        }
+
+        if reasons.is_empty() {
+            watch.tripped = false;
+            return None;
+        }
+        if watch.tripped {
+            return None;
+        }
+        watch.tripped = true;
+        Some((config.action, reasons.join("; ")))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    async fn armed(settings: Value, cpu_millicores: u64) -> Watchdog {
+        let template = json!({ "features": { "watchdog": settings } });
+        let watchdog = Watchdog::new();
+        watchdog
+            .remember(
+                "srv",
+                WatchdogConfig::from_template(&template, cpu_millicores),
+            )
+            .await;
+        watchdog.arm("srv").await;
+        watchdog
+    }
+
+    #[tokio::test]
+    async fn query_failures_trip_once() {
+        let watchdog = armed(json!({ "action": "restart", "graceSeconds": 0 }), 1000).await;
+        let failing = QueryStatus {
+            consecutive_failures: DEFAULT_QUERY_FAILURES,
+            ..QueryStatus::default()
+        };
+        let now = Instant::now();
+
+        let (action, _) = watchdog
+            .evaluate("srv", Some(&failing), None, now)
+            .await
+            .unwrap();
+        assert_eq!(action, WatchdogAction::Restart);
+        assert!(watchdog
+            .evaluate("srv", Some(&failing), None, now)
+            .await
+            .is_none());
+        assert!(watchdog
+            .evaluate("srv", Some(&QueryStatus::default()), None, now)
+            .await
+            .is_none());
+        assert!(watchdog
+            .evaluate("srv", Some(&failing), None, now)
+            .await
+            .is_some());
+    }
+
+    #[tokio::test]
+    async fn cpu_pinned_trips_after_window() {
+        let watchdog = armed(
+            json!({ "cpuPinnedMinutes": 1, "queryFailures": 0, "graceSeconds": 0 }),
+            2000,
+        )
+        .await;
+        let start = Instant::now();
+        let mut usage = 0u64;
+        for step in 0..=2u64 {
+            let now = start + Duration::from_secs(30 * step);
+            let tripped = watchdog.evaluate("srv", None, Some(usage), now).await;
+            assert_eq!(tripped.is_some(), step == 2);
+            // Two full cores busy for the next 30s
+            usage += 2 * 30_000_000;
+        }
+    }
+}
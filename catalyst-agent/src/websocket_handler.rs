@@ -11,16 +11,26 @@ use std::sync::Arc;
 use std::sync::OnceLock;
 use std::time::Duration;
 use sysinfo::{Disks, System};
-use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::io::{AsyncReadExt, AsyncSeekExt, AsyncWriteExt};
 use tokio::sync::RwLock;
 use tokio_tungstenite::connect_async;
 use tokio_tungstenite::tungstenite::Message;
 use tracing::{debug, error, info, warn};
 
-use crate::config::CniNetworkConfig;
+use crate::config::{
+    CniNetworkConfig, PullPolicy, RegistryCredentials, SecurityConfig, SecurityProfile,
+};
+use crate::exec_session::ExecSessionRequest;
+use crate::game_query::{GameQueryManager, QueryTarget};
+use crate::image_gc::ImageGc;
+use crate::rcon::{RconCredentials, RconError, RconManager};
+use crate::runtime_manager::{ContainerStatus, ImagePullProgress, CONSOLE_BASE_DIR};
+use crate::startup_detector::{StartupDetector, StartupPatterns};
+use crate::user_namespace::{shift_ownership, IdMapping};
+use crate::watchdog::{Watchdog, WatchdogAction, WatchdogConfig};
 use crate::{
-    AgentConfig, AgentError, AgentResult, ContainerdRuntime, FileManager, NetworkManager,
-    StorageManager,
+    AgentConfig, AgentError, AgentResult, ContainerRuntime, ContainerdRuntime, ExecSessionManager,
+    FileManager, NetworkManager, StorageManager,
 };
 
 type WsStream =
@@ -29,6 +39,10 @@ type WsWrite = SplitSink<WsStream, Message>;
 const CONTAINER_SERVER_DIR: &str = "/data";
 const MAX_BACKUP_UPLOAD_BYTES: u64 = 10 * 1024 * 1024 * 1024; // 10GB
 const BACKUP_UPLOAD_INACTIVITY_TIMEOUT: Duration = Duration::from_secs(600); // 10 minutes
+const STATS_CONCURRENCY: usize = 16;
+const PREFETCH_CONCURRENCY: usize = 2;
+/// Minimum spacing of `image_pull_progress` events for one pull.
+const IMAGE_PULL_PROGRESS_INTERVAL: Duration = Duration::from_millis(500);
 
 /// Shell-escape a value for safe interpolation into a bash script.
 /// Wraps the value in single quotes and escapes any embedded single quotes.
@@ -125,6 +139,58 @@ fn parse_stop_policy(msg: &Value) -> StopPolicy {
     policy
 }
 
+/// Read a boolean flag from the template's `features` object.
+fn template_feature_flag(msg: &Value, key: &str) -> bool {
+    msg.get("template")
+        .and_then(|template| template.get("features"))
+        .and_then(|features| features.get(key))
+        .and_then(Value::as_bool)
+        .unwrap_or(false)
+}
+
+/// Registry credentials pushed by the backend as `registryAuth` (one object or a list), on
+/// the request itself or on its template.
+fn registry_credentials(msg: &Value) -> AgentResult<Vec<RegistryCredentials>> {
+    let value = msg
+        .get("registryAuth")
+        .or_else(|| msg.get("template").and_then(|t| t.get("registryAuth")));
+    let credentials = match value {
+        None | Some(Value::Null) => return Ok(Vec::new()),
+        Some(list @ Value::Array(_)) => serde_json::from_value(list.clone()),
+        Some(single) => serde_json::from_value(single.clone()).map(|c| vec![c]),
+    };
+    credentials.map_err(|e| AgentError::InvalidRequest(format!("Invalid registryAuth: {}", e)))
+}
+
+/// `imagePullPolicy` (`if-not-present` or `always`) from the request or its template,
+/// else the node default.
+fn image_pull_policy(msg: &Value, default: PullPolicy) -> AgentResult<PullPolicy> {
+    let value = msg
+        .get("imagePullPolicy")
+        .or_else(|| msg.get("template").and_then(|t| t.get("imagePullPolicy")));
+    match value {
+        None | Some(Value::Null) => Ok(default),
+        Some(value) => serde_json::from_value(value.clone())
+            .map_err(|e| AgentError::InvalidRequest(format!("Invalid imagePullPolicy: {}", e))),
+    }
+}
+
+/// The security profile a template selects with `securityProfile`, or the node default.
+fn security_profile(msg: &Value, security: &SecurityConfig) -> AgentResult<SecurityProfile> {
+    let name = msg
+        .get("securityProfile")
+        .or_else(|| msg.get("template").and_then(|t| t.get("securityProfile")))
+        .and_then(Value::as_str)
+        .map(str::trim)
+        .filter(|name| !name.is_empty());
+    match name {
+        None => Ok(SecurityProfile::default()),
+        Some(name) => security.profiles.get(name).cloned().ok_or_else(|| {
+            AgentError::InvalidRequest(format!("Unknown security profile '{}'", name))
+        }),
+    }
+}
+
 struct BackupUploadSession {
     file: tokio::fs::File,
     path: PathBuf,
@@ -134,7 +200,7 @@ struct BackupUploadSession {
 
 pub struct WebSocketHandler {
     config: Arc<AgentConfig>,
-    runtime: Arc<ContainerdRuntime>,
+    runtime: Arc<dyn ContainerRuntime>,
     file_manager: Arc<FileManager>,
     storage_manager: Arc<StorageManager>,
     backend_connected: Arc<RwLock<bool>>,
@@ -142,6 +208,18 @@ pub struct WebSocketHandler {
     active_log_streams: Arc<RwLock<HashSet<String>>>,
     monitor_tasks: Arc<RwLock<HashMap<String, tokio::task::JoinHandle<()>>>>,
     active_uploads: Arc<RwLock<HashMap<String, BackupUploadSession>>>,
+    exec_sessions: Arc<ExecSessionManager>,
+    startup: Arc<StartupDetector>,
+    rcon: Arc<RconManager>,
+    game_query: Arc<GameQueryManager>,
+    watchdog: Arc<Watchdog>,
+    image_gc: Arc<ImageGc>,
+    /// Last `start_server` message per server, used when the agent restarts it on its own.
+    start_messages: Arc<RwLock<HashMap<String, Value>>>,
+    /// Exit reasons set by the agent before it kills a server, read by the exit monitor.
+    exit_reasons: Arc<RwLock<HashMap<String, String>>>,
+    /// Previous (total, steal) jiffies from `/proc/stat`, for node steal time.
+    node_cpu_sample: Arc<RwLock<Option<(u64, u64)>>>,
 }
 
 impl Clone for WebSocketHandler {
@@ -156,6 +234,15 @@ impl Clone for WebSocketHandler {
             active_log_streams: self.active_log_streams.clone(),
             monitor_tasks: self.monitor_tasks.clone(),
             active_uploads: self.active_uploads.clone(),
+            exec_sessions: self.exec_sessions.clone(),
+            startup: self.startup.clone(),
+            rcon: self.rcon.clone(),
+            game_query: self.game_query.clone(),
+            watchdog: self.watchdog.clone(),
+            image_gc: self.image_gc.clone(),
+            start_messages: self.start_messages.clone(),
+            exit_reasons: self.exit_reasons.clone(),
+            node_cpu_sample: self.node_cpu_sample.clone(),
         }
     }
 }
@@ -173,11 +260,22 @@ impl WebSocketHandler {
 
     pub fn new(
         config: Arc<AgentConfig>,
-        runtime: Arc<ContainerdRuntime>,
+        runtime: Arc<dyn ContainerRuntime>,
         file_manager: Arc<FileManager>,
         storage_manager: Arc<StorageManager>,
         backend_connected: Arc<RwLock<bool>>,
     ) -> Self {
+        let exec_sessions = Arc::new(ExecSessionManager::new(
+            runtime.clone(),
+            config.server.data_dir.clone(),
+        ));
+        let rcon = Arc::new(RconManager::new(runtime.clone()));
+        let game_query = Arc::new(GameQueryManager::new(runtime.clone()));
+        let image_gc = Arc::new(ImageGc::new(
+            runtime.clone(),
+            storage_manager.clone(),
+            Duration::from_secs(config.images.gc_min_age_secs),
+        ));
         Self {
             config,
             runtime,
@@ -188,6 +286,15 @@ impl WebSocketHandler {
             active_log_streams: Arc::new(RwLock::new(HashSet::new())),
             monitor_tasks: Arc::new(RwLock::new(HashMap::new())),
             active_uploads: Arc::new(RwLock::new(HashMap::new())),
+            exec_sessions,
+            startup: Arc::new(StartupDetector::new()),
+            rcon,
+            game_query,
+            watchdog: Arc::new(Watchdog::new()),
+            image_gc,
+            start_messages: Arc::new(RwLock::new(HashMap::new())),
+            exit_reasons: Arc::new(RwLock::new(HashMap::new())),
+            node_cpu_sample: Arc::new(RwLock::new(None)),
         }
     }
 
@@ -368,6 +475,17 @@ impl WebSocketHandler {
             }
         }));
 
+        // Probe game ports so stats can report players and catch servers that stopped answering.
+        let handler_clone = self.clone();
+        connection_tasks.push(tokio::spawn(async move {
+            let mut interval = tokio::time::interval(Duration::from_secs(30));
+            loop {
+                interval.tick().await;
+                handler_clone.game_query.probe_all().await;
+                handler_clone.run_watchdog_checks().await;
+            }
+        }));
+
         // Garbage-collect stale backup upload sessions to avoid disk/fd leaks on partial uploads.
         let handler_clone = self.clone();
         connection_tasks.push(tokio::spawn(async move {
@@ -406,6 +524,9 @@ impl WebSocketHandler {
         // reconnects and to release file descriptors.
         self.cleanup_all_uploads().await;
 
+        // Interactive shells cannot outlive the connection that streams them.
+        self.exec_sessions.terminate_all().await;
+
         {
             let mut guard = self.write.write().await;
             *guard = None;
@@ -475,6 +596,23 @@ impl WebSocketHandler {
                 self.stop_server(server_id, container_id, &stop_policy)
                     .await?;
             }
+            Some("pause_server") => {
+                let server_uuid = msg["serverUuid"]
+                    .as_str()
+                    .ok_or_else(|| AgentError::InvalidRequest("Missing serverUuid".to_string()))?;
+                let server_id = msg["serverId"].as_str().unwrap_or(server_uuid);
+                let container_id = self.resolve_container_id(server_id, server_uuid).await;
+                let reason = msg["reason"].as_str().map(str::to_string);
+                self.pause_server(server_id, &container_id, reason).await?;
+            }
+            Some("resume_server") => {
+                let server_uuid = msg["serverUuid"]
+                    .as_str()
+                    .ok_or_else(|| AgentError::InvalidRequest("Missing serverUuid".to_string()))?;
+                let server_id = msg["serverId"].as_str().unwrap_or(server_uuid);
+                let container_id = self.resolve_container_id(server_id, server_uuid).await;
+                self.resume_server(server_id, &container_id).await?;
+            }
             Some("kill_server") => {
                 let server_uuid = msg["serverUuid"]
                     .as_str()
@@ -496,7 +634,14 @@ impl WebSocketHandler {
                 self.start_server_with_details(&msg).await?;
             }
             Some("console_input") => self.handle_console_input(&msg).await?,
+            Some("console_resize") => self.handle_console_resize(&msg).await?,
+            Some("rcon_command") => self.handle_rcon_command(&msg, write).await?,
+            Some("exec_session_start") => self.handle_exec_session_start(&msg).await?,
+            Some("exec_session_input") => self.handle_exec_session_input(&msg).await?,
+            Some("exec_session_resize") => self.handle_exec_session_resize(&msg).await?,
+            Some("exec_session_close") => self.handle_exec_session_close(&msg).await?,
             Some("file_operation") => self.handle_file_operation(&msg).await?,
+            Some("fix_permissions") => self.handle_fix_permissions(&msg, write).await?,
             Some("create_backup") => self.handle_create_backup(&msg, write).await?,
             Some("restore_backup") => self.handle_restore_backup(&msg, write).await?,
             Some("delete_backup") => self.handle_delete_backup(&msg, write).await?,
@@ -508,6 +653,9 @@ impl WebSocketHandler {
                 self.handle_upload_backup_complete(&msg, write).await?
             }
             Some("resize_storage") => self.handle_resize_storage(&msg, write).await?,
+            Some("update_resources") => self.handle_update_resources(&msg, write).await?,
+            Some("prune_images") => self.handle_prune_images(&msg, write).await?,
+            Some("prefetch_images") => self.handle_prefetch_images(&msg).await?,
             Some("resume_console") => self.resume_console(&msg).await?,
             Some("request_immediate_stats") => {
                 info!("Received immediate stats request from backend");
@@ -772,6 +920,62 @@ impl WebSocketHandler {
         streams.retain(|key| !key.starts_with(&format!("{}:", server_id)));
     }
 
+    /// Allocate the host id range of a server's user namespace and move its volume's
+    /// ownership into it. With user namespaces disabled, a volume shifted earlier is moved
+    /// back to plain container ids. Either way, the file manager learns the files' owner.
+    async fn prepare_user_namespace(
+        &self,
+        server_uuid: &str,
+        server_dir: &Path,
+        security: &SecurityProfile,
+    ) -> AgentResult<Option<IdMapping>> {
+        let config = &self.config.security.user_namespaces;
+        // Fails before any file is touched when the profile's user is outside the range.
+        let owner = self.server_owner(server_uuid, security).await?;
+        let mapping = if config.enabled {
+            let slot = self
+                .storage_manager
+                .user_namespace_slot(server_uuid)
+                .await?;
+            let mapping = config.mapping(slot)?;
+            shift_ownership(server_dir, mapping, config).await?;
+            Some(mapping)
+        } else {
+            if config.range_size > 0 {
+                let identity = IdMapping {
+                    host_base: 0,
+                    size: config.range_size,
+                };
+                shift_ownership(server_dir, identity, config).await?;
+            }
+            None
+        };
+        self.file_manager.set_owner(server_uuid, owner);
+        Ok(mapping)
+    }
+
+    /// Host uid and gid that own a server's files: the user of its security profile,
+    /// mapped into the server's range when it runs in a user namespace.
+    async fn server_owner(
+        &self,
+        server_uuid: &str,
+        security: &SecurityProfile,
+    ) -> AgentResult<(u32, u32)> {
+        let config = &self.config.security.user_namespaces;
+        if !config.enabled {
+            return Ok((security.uid(), security.gid()));
+        }
+        let slot = self
+            .storage_manager
+            .user_namespace_slot(server_uuid)
+            .await?;
+        let mapping = config.mapping(slot)?;
+        Ok((
+            mapping.host_id(security.uid())?,
+            mapping.host_id(security.gid())?,
+        ))
+    }
+
     fn spawn_exit_monitor(&self, server_id: &str, container_id: &str) {
         let handler = self.clone();
         let server_id = server_id.to_string();
@@ -790,7 +994,7 @@ impl WebSocketHandler {
             // This replaces polling and provides instant notification when containers exit
             let monitor = tokio::spawn(async move {
                 // Subscribe to container events
-                let event_stream = match monitor_handler
+                let mut events = match monitor_handler
                     .runtime
                     .subscribe_to_container_events(&monitor_container_id)
                     .await
@@ -803,28 +1007,25 @@ impl WebSocketHandler {
                         );
                         // Fallback to polling if event stream fails
                         loop {
-                            let running = monitor_handler
+                            // Paused tasks are still alive.
+                            let alive = monitor_handler
                                 .runtime
-                                .is_container_running(&monitor_container_id)
+                                .container_status(&monitor_container_id)
                                 .await
+                                .map(|status| status != ContainerStatus::Stopped)
                                 .unwrap_or(false);
-                            if !running {
+                            if !alive {
                                 let exit_code = monitor_handler
                                     .runtime
                                     .get_container_exit_code(&monitor_container_id)
                                     .await
                                     .unwrap_or(None);
-                                let reason = match exit_code {
-                                    Some(code) => format!("Container exited with code {}", code),
-                                    None => "Container exited".to_string(),
-                                };
-                                let _ = monitor_handler
-                                    .emit_server_state_update(
+                                monitor_handler
+                                    .report_exit(
                                         &monitor_server_id,
-                                        "crashed",
-                                        Some(reason),
-                                        None,
+                                        &monitor_container_id,
                                         exit_code,
+                                        false,
                                     )
                                     .await;
                                 break;
@@ -835,14 +1036,17 @@ impl WebSocketHandler {
                     }
                 };
 
-                // Take the event receiver from the containerd stream
-                let mut receiver = event_stream.receiver;
-
-                // Read events from containerd gRPC streaming
-                while let Ok(Some(envelope)) = receiver.message().await {
-                    let topic = &envelope.topic;
+                let mut oom_seen = false;
+                while let Some(event) = events.next().await {
+                    let topic = &event.topic;
                     debug!("Container {} event topic: {}", monitor_container_id, topic);
 
+                    if topic.contains("/tasks/oom") {
+                        warn!("Container {} hit its memory limit", monitor_container_id);
+                        oom_seen = true;
+                        continue;
+                    }
+
                     // Check for exit-related events
                     if topic.contains("/tasks/exit") || topic.contains("/tasks/delete") {
                         // Container has stopped, get exit code
@@ -851,31 +1055,72 @@ impl WebSocketHandler {
                             .get_container_exit_code(&monitor_container_id)
                             .await
                             .unwrap_or(None);
-                        let reason = match exit_code {
-                            Some(code) => format!("Container exited with code {}", code),
-                            None => "Container exited".to_string(),
-                        };
-                        let _ = monitor_handler
-                            .emit_server_state_update(
+                        monitor_handler
+                            .report_exit(
                                 &monitor_server_id,
-                                "crashed",
-                                Some(reason),
-                                None,
+                                &monitor_container_id,
                                 exit_code,
+                                oom_seen,
                             )
                             .await;
                         break;
                     }
                 }
-
-                // Clean up
-                drop(receiver);
             });
             tasks.insert(server_id, monitor);
             // Lock is held until end of scope, ensuring atomic operation
         });
     }
 
+    /// Report an exit seen by the exit monitor. The reason is the one the agent recorded
+    /// before killing the server, `oom_killed` when the kernel OOM killer ended it, or a
+    /// generic description of the exit code.
+    async fn report_exit(
+        &self,
+        server_id: &str,
+        container_id: &str,
+        exit_code: Option<i32>,
+        oom_event: bool,
+    ) {
+        let recorded = self.exit_reasons.write().await.remove(server_id);
+        let mut details = None;
+        let reason = match recorded {
+            Some(reason) => reason,
+            None => {
+                let memory = self.runtime.memory_events(container_id).await;
+                let oom_killed =
+                    oom_event || memory.as_ref().is_some_and(|memory| memory.oom_kills > 0);
+                if oom_killed {
+                    let memory = memory.unwrap_or_default();
+                    warn!(
+                        "Server {} was killed for exceeding its memory limit (peak {:?} bytes)",
+                        server_id, memory.peak_bytes
+                    );
+                    details = Some(json!({
+                        "memoryPeakMb": memory.peak_bytes.map(|bytes| bytes / (1024 * 1024)),
+                        "memoryLimitMb": memory.limit_bytes.map(|bytes| bytes / (1024 * 1024)),
+                    }));
+                    "oom_killed".to_string()
+                } else {
+                    match exit_code {
+                        Some(code) => format!("Container exited with code {}", code),
+                        None => "Container exited".to_string(),
+                    }
+                }
+            }
+        };
+        let _ = self
+            .emit_server_state_update_with_details(
+                server_id,
+                "crashed",
+                Some(reason),
+                None,
+                exit_code,
+                details,
+            )
+            .await;
+    }
+
     async fn install_server(&self, msg: &Value) -> AgentResult<()> {
         let server_uuid = msg["serverUuid"]
             .as_str()
@@ -926,6 +1171,13 @@ impl WebSocketHandler {
         self.storage_manager
             .ensure_mounted(server_uuid, &server_dir_path, disk_mb)
             .await?;
+        if let Err(e) = self
+            .storage_manager
+            .record_server(server_id, server_uuid)
+            .await
+        {
+            warn!("Failed to record server {} in index: {}", server_id, e);
+        }
 
         let server_dir_path = std::path::PathBuf::from(&host_server_dir);
 
@@ -976,11 +1228,32 @@ impl WebSocketHandler {
         self.emit_console_output(server_id, "system", "[Catalyst] Starting installation...\n")
             .await?;
 
+        self.pull_image_with_progress(
+            Some(server_id),
+            install_image,
+            &registry_credentials(msg)?,
+            image_pull_policy(msg, self.config.images.pull_policy)?,
+        )
+        .await?;
+        self.record_server_image(server_id, "installer", install_image)
+            .await;
+
         // Execute the install script in an ephemeral container for complete isolation
         // The container mounts the server directory at /data and runs the script there
+        let security = security_profile(msg, &self.config.security)?;
+        self.prepare_user_namespace(server_uuid, &server_dir_path, &security)
+            .await?;
+        let (uid, gid) = self.server_owner(server_uuid, &security).await?;
         let installer = self
             .runtime
-            .spawn_installer_container(install_image, &final_script, &env_map, &host_server_dir)
+            .spawn_installer_container(
+                install_image,
+                &final_script,
+                &env_map,
+                &host_server_dir,
+                uid,
+                gid,
+            )
             .await
             .map_err(|e| {
                 AgentError::IoError(format!("Failed to spawn installer container: {}", e))
@@ -994,7 +1267,7 @@ impl WebSocketHandler {
 
         loop {
             // Read new stdout content
-            if let Ok(content) = tokio::fs::read_to_string(&installer.stdout_path).await {
+            if let Ok(content) = tokio::fs::read_to_string(&installer.stdout_path()).await {
                 if (stdout_pos as usize) < content.len() {
                     for line in content[stdout_pos as usize..].lines() {
                         let payload = format!("{}\n", line);
@@ -1006,7 +1279,7 @@ impl WebSocketHandler {
                 }
             }
             // Read new stderr content
-            if let Ok(content) = tokio::fs::read_to_string(&installer.stderr_path).await {
+            if let Ok(content) = tokio::fs::read_to_string(&installer.stderr_path()).await {
                 if (stderr_pos as usize) < content.len() {
                     for line in content[stderr_pos as usize..].lines() {
                         let payload = format!("{}\n", line);
@@ -1021,7 +1294,7 @@ impl WebSocketHandler {
             match tokio::time::timeout(Duration::from_millis(200), installer.wait()).await {
                 Ok(Ok(exit_code)) => {
                     // Read any remaining output
-                    if let Ok(content) = tokio::fs::read_to_string(&installer.stdout_path).await {
+                    if let Ok(content) = tokio::fs::read_to_string(&installer.stdout_path()).await {
                         if (stdout_pos as usize) < content.len() {
                             for line in content[stdout_pos as usize..].lines() {
                                 let payload = format!("{}\n", line);
@@ -1031,7 +1304,7 @@ impl WebSocketHandler {
                             }
                         }
                     }
-                    if let Ok(content) = tokio::fs::read_to_string(&installer.stderr_path).await {
+                    if let Ok(content) = tokio::fs::read_to_string(&installer.stderr_path()).await {
                         if (stderr_pos as usize) < content.len() {
                             for line in content[stderr_pos as usize..].lines() {
                                 let payload = format!("{}\n", line);
@@ -1143,8 +1416,11 @@ impl WebSocketHandler {
     }
 
     async fn stream_container_logs(&self, server_id: &str, container_id: &str) -> AgentResult<()> {
+        if self.runtime.is_tty(container_id) {
+            return self.stream_pty_output(server_id, container_id).await;
+        }
         let _log_stream = self.runtime.spawn_log_stream(container_id).await?;
-        let base = std::path::PathBuf::from("/tmp/catalyst-console").join(container_id);
+        let base = std::path::PathBuf::from(CONSOLE_BASE_DIR).join(container_id);
         let stdout_path = base.join("stdout");
         let stderr_path = base.join("stderr");
 
@@ -1153,14 +1429,19 @@ impl WebSocketHandler {
 
         // Tail the stdout/stderr files
         loop {
+            // A paused server keeps its stream so the console works again after resume.
             let running = self
                 .runtime
-                .is_container_running(container_id)
+                .container_status(container_id)
                 .await
-                .unwrap_or(false);
+                .is_ok_and(|status| status != ContainerStatus::Stopped);
             let mut had_data = false;
 
             if let Ok(content) = tokio::fs::read_to_string(&stdout_path).await {
+                if content.len() < stdout_pos as usize {
+                    // Truncated by a restart of the container.
+                    stdout_pos = 0;
+                }
                 if (stdout_pos as usize) < content.len() {
                     for line in content[stdout_pos as usize..].lines() {
                         let payload = format!("{}\n", line);
@@ -1172,6 +1453,10 @@ impl WebSocketHandler {
                 }
             }
             if let Ok(content) = tokio::fs::read_to_string(&stderr_path).await {
+                if content.len() < stderr_pos as usize {
+                    // Truncated by a restart of the container.
+                    stderr_pos = 0;
+                }
                 if (stderr_pos as usize) < content.len() {
                     for line in content[stderr_pos as usize..].lines() {
                         let payload = format!("{}\n", line);
@@ -1211,6 +1496,63 @@ impl WebSocketHandler {
         Ok(())
     }
 
+    /// Forward PTY output as raw chunks rather than lines so carriage returns, cursor
+    /// movement and colour sequences reach the browser terminal unchanged.
+    async fn stream_pty_output(&self, server_id: &str, container_id: &str) -> AgentResult<()> {
+        let log_stream = self.runtime.spawn_log_stream(container_id).await?;
+        let Some(mut stdout) = log_stream.stdout else {
+            return Ok(());
+        };
+        let mut buffer = vec![0u8; 16 * 1024];
+        let mut pending: Vec<u8> = Vec::new();
+        let mut position = 0u64;
+
+        loop {
+            // A paused server keeps its stream so the console works again after resume.
+            let running = self
+                .runtime
+                .container_status(container_id)
+                .await
+                .is_ok_and(|status| status != ContainerStatus::Stopped);
+            let mut had_data = false;
+
+            if stdout
+                .metadata()
+                .await
+                .is_ok_and(|metadata| metadata.len() < position)
+            {
+                // Truncated by a restart of the container.
+                stdout.seek(std::io::SeekFrom::Start(0)).await?;
+                position = 0;
+                pending.clear();
+            }
+            loop {
+                let read = stdout.read(&mut buffer).await?;
+                if read == 0 {
+                    break;
+                }
+                position += read as u64;
+                had_data = true;
+                pending.extend_from_slice(&buffer[..read]);
+                let chunk = take_complete_utf8(&mut pending);
+                self.emit_console_output(server_id, "stdout", &chunk)
+                    .await?;
+            }
+
+            if !running {
+                if !pending.is_empty() {
+                    let rest = String::from_utf8_lossy(&pending).into_owned();
+                    self.emit_console_output(server_id, "stdout", &rest).await?;
+                }
+                break;
+            }
+
+            tokio::time::sleep(Duration::from_millis(if had_data { 20 } else { 100 })).await;
+        }
+
+        Ok(())
+    }
+
     async fn start_server_with_details(&self, msg: &Value) -> AgentResult<()> {
         let server_id = msg["serverId"]
             .as_str()
@@ -1224,6 +1566,7 @@ impl WebSocketHandler {
             let template = msg["template"]
                 .as_object()
                 .ok_or_else(|| AgentError::InvalidRequest("Missing template".to_string()))?;
+            let startup_patterns = StartupPatterns::from_template(&msg["template"])?;
 
             let docker_image = msg
                 .get("environment")
@@ -1245,11 +1588,11 @@ impl WebSocketHandler {
                 AgentError::InvalidRequest("Missing allocatedMemoryMb".to_string())
             })?;
 
-            let cpu_cores = msg["allocatedCpuCores"].as_u64().ok_or_else(|| {
-                AgentError::InvalidRequest("Missing allocatedCpuCores".to_string())
-            })?;
+            let cpu_millicores = crate::runtime_manager::parse_cpu_millicores(msg)?;
 
             let disk_mb = msg["allocatedDiskMb"].as_u64().unwrap_or(10240);
+            let limits = crate::runtime_manager::ResourceLimits::from_message(msg)?;
+            let security = security_profile(msg, &self.config.security)?;
 
             let primary_port = msg["primaryPort"]
                 .as_u64()
@@ -1265,6 +1608,7 @@ impl WebSocketHandler {
                     "Invalid primaryPort".to_string(),
                 ));
             }
+            let query_target = QueryTarget::from_template(&msg["template"], primary_port)?;
 
             let network_mode = msg.get("networkMode").and_then(|v| v.as_str());
             let port_bindings_value = msg.get("portBindings");
@@ -1301,13 +1645,23 @@ impl WebSocketHandler {
             self.storage_manager
                 .ensure_mounted(server_uuid, &server_dir_path, disk_mb)
                 .await?;
+            if let Err(e) = self
+                .storage_manager
+                .record_server(server_id, server_uuid)
+                .await
+            {
+                warn!("Failed to record server {} in index: {}", server_id, e);
+            }
+            let user_namespace = self
+                .prepare_user_namespace(server_uuid, &server_dir_path, &security)
+                .await?;
             env_map.insert("HOST_SERVER_DIR".to_string(), host_server_dir.clone());
             env_map.insert("SERVER_DIR".to_string(), CONTAINER_SERVER_DIR.to_string());
 
             info!("Starting server: {} (UUID: {})", server_id, server_uuid);
             info!(
-                "Image: {}, Port: {}, Memory: {}MB, CPU: {}",
-                docker_image, primary_port, memory_mb, cpu_cores
+                "Image: {}, Port: {}, Memory: {}MB, CPU: {}m",
+                docker_image, primary_port, memory_mb, cpu_millicores
             );
             self.emit_console_output(server_id, "system", "[Catalyst] Starting server...\n")
                 .await?;
@@ -1379,6 +1733,35 @@ impl WebSocketHandler {
 
             self.cleanup_all_server_containers(server_id, server_uuid)
                 .await?;
+            self.startup.remember(server_id, startup_patterns).await;
+            self.rcon
+                .register(
+                    server_id,
+                    RconCredentials::from_env(&env_map),
+                    template_feature_flag(msg, "rcon"),
+                )
+                .await;
+            self.game_query.register(server_id, query_target).await;
+            self.watchdog
+                .remember(
+                    server_id,
+                    WatchdogConfig::from_template(&msg["template"], cpu_millicores),
+                )
+                .await;
+            self.start_messages
+                .write()
+                .await
+                .insert(server_id.to_string(), msg.clone());
+
+            self.pull_image_with_progress(
+                Some(server_id),
+                docker_image,
+                &registry_credentials(msg)?,
+                image_pull_policy(msg, self.config.images.pull_policy)?,
+            )
+            .await?;
+            self.record_server_image(server_id, "runtime", docker_image)
+                .await;
 
             // Create and start container
             self.runtime
@@ -1388,12 +1771,16 @@ impl WebSocketHandler {
                     startup_command: &final_startup_command,
                     env: &env_map,
                     memory_mb,
-                    cpu_cores,
+                    cpu_millicores,
                     data_dir: &host_server_dir,
                     port: primary_port,
                     port_bindings: &port_bindings,
                     network_mode,
                     network_ip,
+                    tty: template_feature_flag(msg, "pty"),
+                    limits,
+                    security,
+                    user_namespace,
                 })
                 .await?;
 
@@ -1422,6 +1809,8 @@ impl WebSocketHandler {
                 return Err(AgentError::ContainerError(reason));
             }
 
+            // Arm startup detection before the log stream exists so no early line is missed
+            let startup_watch = self.startup.arm(server_id).await;
             let container_id = self.resolve_container_id(server_id, server_uuid).await;
             if !container_id.is_empty() {
                 // Stop any existing log streams for this server before starting new one
@@ -1432,14 +1821,8 @@ impl WebSocketHandler {
             }
 
             // Emit state update
-            self.emit_server_state_update(
-                server_id,
-                "running",
-                None,
-                Some(port_bindings.clone()),
-                None,
-            )
-            .await?;
+            self.report_server_started(server_id, Some(port_bindings.clone()), startup_watch)
+                .await?;
 
             info!("Server started successfully: {}", server_id);
             Ok(())
@@ -1474,9 +1857,10 @@ impl WebSocketHandler {
         // In production, fetch server config from database or local cache
         match self.runtime.start_container(&container_id).await {
             Ok(()) => {
+                let startup_watch = self.startup.arm(server_id).await;
                 self.spawn_log_stream(server_id, &container_id);
                 self.spawn_exit_monitor(server_id, &container_id);
-                self.emit_server_state_update(server_id, "running", None, None, None)
+                self.report_server_started(server_id, None, startup_watch)
                     .await?;
                 Ok(())
             }
@@ -1493,6 +1877,56 @@ impl WebSocketHandler {
         }
     }
 
+    /// Report a freshly started server. Without startup patterns it is `running` right away;
+    /// otherwise it stays `starting` until a console line matches or the deadline passes.
+    async fn report_server_started(
+        &self,
+        server_id: &str,
+        port_bindings: Option<HashMap<u16, u16>>,
+        startup_watch: Option<(u64, Duration)>,
+    ) -> AgentResult<()> {
+        self.watchdog.arm(server_id).await;
+        let Some((generation, timeout)) = startup_watch else {
+            return self
+                .emit_server_state_update(server_id, "running", None, port_bindings, None)
+                .await;
+        };
+
+        self.emit_server_state_update(server_id, "starting", None, port_bindings, None)
+            .await?;
+
+        let handler = self.clone();
+        let server_id = server_id.to_string();
+        tokio::spawn(async move {
+            tokio::time::sleep(timeout).await;
+            if !handler.startup.expire(&server_id, generation).await {
+                return;
+            }
+            let reason = format!(
+                "Startup not detected within {}s; no console line matched the template's startup patterns",
+                timeout.as_secs()
+            );
+            warn!("Server {}: {}", server_id, reason);
+            handler
+                .send_event(&json!({
+                    "type": "startup_warning",
+                    "serverId": server_id,
+                    "reason": reason,
+                    "timeoutSeconds": timeout.as_secs(),
+                    "timestamp": chrono::Utc::now().timestamp_millis(),
+                }))
+                .await;
+            let _ = handler
+                .emit_console_output(&server_id, "system", &format!("[Catalyst] {}\n", reason))
+                .await;
+            // The process is up, so stop holding it in `starting` and let players in.
+            let _ = handler
+                .emit_server_state_update(&server_id, "running", Some(reason), None, None)
+                .await;
+        });
+        Ok(())
+    }
+
     async fn wait_for_container_shutdown(&self, container_id: &str, timeout: Duration) -> bool {
         let deadline = tokio::time::Instant::now() + timeout;
         loop {
@@ -1534,6 +1968,12 @@ impl WebSocketHandler {
 
         self.stop_monitor_task(server_id).await;
 
+        // A frozen server cannot handle a graceful stop; thaw it first.
+        if self.runtime.container_status(&container_id).await.ok() == Some(ContainerStatus::Paused)
+        {
+            self.runtime.resume_container(&container_id).await?;
+        }
+
         if self
             .runtime
             .is_container_running(&container_id)
@@ -1542,11 +1982,6 @@ impl WebSocketHandler {
         {
             let mut stopped_gracefully = false;
             if let Some(command) = stop_policy.stop_command.as_deref() {
-                let payload = if command.ends_with('\n') {
-                    command.to_string()
-                } else {
-                    format!("{}\n", command)
-                };
                 let _ = self
                     .emit_console_output(
                         server_id,
@@ -1555,8 +1990,11 @@ impl WebSocketHandler {
                     )
                     .await;
 
-                match self.runtime.send_input(&container_id, &payload).await {
-                    Ok(()) => {
+                match self
+                    .send_server_command(server_id, &container_id, command)
+                    .await
+                {
+                    Ok(_) => {
                         if self
                             .wait_for_container_shutdown(&container_id, Duration::from_secs(20))
                             .await
@@ -1621,98 +2059,368 @@ impl WebSocketHandler {
         Ok(())
     }
 
-    async fn kill_server(&self, server_id: &str, container_id: String) -> AgentResult<()> {
-        if container_id.is_empty() {
-            info!(
-                "No container found for server {}, marking as killed",
-                server_id
-            );
-            self.stop_monitor_task(server_id).await;
-            self.emit_server_state_update(
-                server_id,
-                "crashed",
-                Some("Killed by agent".to_string()),
-                None,
-                Some(137),
-            )
-            .await?;
-            return Ok(());
-        }
-        info!(
-            "Force killing server: {} (container {})",
-            server_id, container_id
-        );
-
-        // Stop monitoring first - we don't want monitor interfering
-        self.stop_monitor_task(server_id).await;
-
-        let _ = self
-            .emit_console_output(
-                server_id,
-                "system",
-                "[Catalyst] Force killing server with SIGKILL...\n",
-            )
-            .await;
-
-        // Force kill the container - this method never fails and always attempts cleanup
-        if let Err(e) = self.runtime.force_kill_container(&container_id).await {
-            warn!(
-                "Force kill had issues for {}: {}, continuing with cleanup",
-                container_id, e
-            );
+    /// Deliver a console command. RCON is tried first when the template prefers it and
+    /// otherwise serves as a fallback when stdin cannot be written. Returns the RCON
+    /// response when the command went over RCON.
+    async fn send_server_command(
+        &self,
+        server_id: &str,
+        container_id: &str,
+        command: &str,
+    ) -> AgentResult<Option<String>> {
+        let command = command.trim_end_matches(['\r', '\n']);
+        if self.rcon.is_preferred(server_id).await {
+            match self.rcon.execute(server_id, container_id, command).await {
+                Ok(response) => return Ok(Some(response)),
+                // The server may already have run it; sending it again could repeat it.
+                Err(RconError::Sent(err)) => return Err(err),
+                Err(RconError::NotSent(err)) => warn!(
+                    "RCON command failed for server {}, falling back to stdin: {}",
+                    server_id, err
+                ),
+            }
         }
 
-        // Always attempt to remove the container regardless of what happened above
-        // remove_container also sends SIGKILL, so this is a safety net
-        if self.runtime.container_exists(&container_id).await {
-            if let Err(e) = self.runtime.remove_container(&container_id).await {
+        match self
+            .runtime
+            .send_input(container_id, &format!("{}\n", command))
+            .await
+        {
+            Ok(()) => Ok(None),
+            Err(err) if self.rcon.is_configured(server_id).await => {
                 warn!(
-                    "Failed to remove container {}: {}, server state still updated",
-                    container_id, e
+                    "Stdin unavailable for server {} ({}), sending over RCON",
+                    server_id, err
                 );
+                self.rcon
+                    .execute(server_id, container_id, command)
+                    .await
+                    .map(Some)
+                    .map_err(AgentError::from)
             }
+            Err(err) => Err(err),
         }
-
-        // Always update state to crashed - this must happen no matter what
-        self.emit_server_state_update(
-            server_id,
-            "crashed",
-            Some("Killed by agent".to_string()),
-            None,
-            Some(137), // 128 + 9 (SIGKILL exit code)
-        )
-        .await?;
-
-        Ok(())
     }
 
-    async fn handle_console_input(&self, msg: &Value) -> AgentResult<()> {
+    async fn handle_rcon_command(
+        &self,
+        msg: &Value,
+        write: &Arc<tokio::sync::Mutex<WsWrite>>,
+    ) -> AgentResult<()> {
         let server_id = msg["serverId"]
             .as_str()
             .ok_or_else(|| AgentError::InvalidRequest("Missing serverId".to_string()))?;
-
-        let data = msg["data"]
+        let command = msg["command"]
             .as_str()
-            .ok_or_else(|| AgentError::InvalidRequest("Missing data".to_string()))?;
-
+            .ok_or_else(|| AgentError::InvalidRequest("Missing command".to_string()))?;
         let server_uuid = msg
             .get("serverUuid")
             .and_then(|value| value.as_str())
             .unwrap_or(server_id);
-        info!(
-            "Received console input for server {} (uuid {}), bytes={}",
-            server_id,
-            server_uuid,
-            data.len()
-        );
+        let request_id = msg.get("requestId").cloned().unwrap_or(Value::Null);
+
         let container_id = self.resolve_container_id(server_id, server_uuid).await;
-        if container_id.is_empty() {
-            let err =
-                AgentError::ContainerError(format!("Container not found for server {}", server_id));
-            let _ = self
-                .emit_console_output(
-                    server_id,
-                    "stderr",
+        let result = if container_id.is_empty() {
+            Err(AgentError::ContainerError(format!(
+                "Container not found for server {}",
+                server_id
+            )))
+        } else {
+            self.rcon
+                .execute(server_id, &container_id, command)
+                .await
+                .map_err(AgentError::from)
+        };
+
+        let response = match result {
+            Ok(output) => json!({
+                "type": "rcon_response",
+                "requestId": request_id,
+                "serverId": server_id,
+                "success": true,
+                "response": output,
+            }),
+            Err(err) => json!({
+                "type": "rcon_response",
+                "requestId": request_id,
+                "serverId": server_id,
+                "success": false,
+                "error": err.to_string(),
+            }),
+        };
+        let mut w = write.lock().await;
+        w.send(Message::Text(response.to_string().into()))
+            .await
+            .map_err(|e| AgentError::NetworkError(e.to_string()))?;
+        Ok(())
+    }
+
+    async fn run_watchdog_checks(&self) {
+        for server_id in self.watchdog.armed_servers().await {
+            // A server still loading its world is not hung.
+            if self.startup.is_pending(&server_id).await {
+                continue;
+            }
+            let query = self.game_query.status(&server_id).await;
+            let cpu_usage = self.runtime.cpu_usage_usec(&server_id).await;
+            let Some((action, reason)) = self
+                .watchdog
+                .evaluate(
+                    &server_id,
+                    query.as_ref(),
+                    cpu_usage,
+                    std::time::Instant::now(),
+                )
+                .await
+            else {
+                continue;
+            };
+            let handler = self.clone();
+            tokio::spawn(async move {
+                if let Err(err) = handler
+                    .handle_watchdog_trip(&server_id, action, &reason)
+                    .await
+                {
+                    error!("Watchdog action for server {} failed: {}", server_id, err);
+                }
+            });
+        }
+    }
+
+    async fn handle_watchdog_trip(
+        &self,
+        server_id: &str,
+        action: WatchdogAction,
+        reason: &str,
+    ) -> AgentResult<()> {
+        warn!(
+            "Watchdog tripped for server {}: {} (action: {})",
+            server_id,
+            reason,
+            action.as_str()
+        );
+        self.send_event(&json!({
+            "type": "watchdog_alert",
+            "serverId": server_id,
+            "action": action.as_str(),
+            "reason": reason,
+            "timestamp": chrono::Utc::now().timestamp_millis(),
+        }))
+        .await;
+        let _ = self
+            .emit_console_output(
+                server_id,
+                "system",
+                &format!(
+                    "[Catalyst] Watchdog: server appears hung ({}), action: {}\n",
+                    reason,
+                    action.as_str()
+                ),
+            )
+            .await;
+
+        let start_msg = self.start_messages.read().await.get(server_id).cloned();
+        let server_uuid = start_msg
+            .as_ref()
+            .and_then(|msg| msg["serverUuid"].as_str())
+            .unwrap_or(server_id)
+            .to_string();
+        let container_id = self.resolve_container_id(server_id, &server_uuid).await;
+
+        match action {
+            WatchdogAction::Alert => Ok(()),
+            WatchdogAction::Stop => {
+                let stop_policy = start_msg
+                    .as_ref()
+                    .map(parse_stop_policy)
+                    .unwrap_or_default();
+                self.stop_server(server_id, container_id, &stop_policy)
+                    .await
+            }
+            WatchdogAction::Restart => {
+                if container_id.is_empty() {
+                    return Ok(());
+                }
+                // The exit monitor reports the kill with this reason instead of a plain crash.
+                self.exit_reasons.write().await.insert(
+                    server_id.to_string(),
+                    format!("Watchdog restart: {}", reason),
+                );
+                self.runtime.force_kill_container(&container_id).await?;
+                self.wait_for_container_shutdown(&container_id, Duration::from_secs(10))
+                    .await;
+
+                // Give the exit monitor a moment to report; report it here if it never does.
+                let deadline = tokio::time::Instant::now() + Duration::from_secs(5);
+                while self.exit_reasons.read().await.contains_key(server_id)
+                    && tokio::time::Instant::now() < deadline
+                {
+                    tokio::time::sleep(Duration::from_millis(100)).await;
+                }
+                if let Some(reason) = self.exit_reasons.write().await.remove(server_id) {
+                    self.emit_server_state_update(
+                        server_id,
+                        "crashed",
+                        Some(reason),
+                        None,
+                        Some(137),
+                    )
+                    .await?;
+                }
+
+                match start_msg {
+                    Some(msg) => self.start_server_with_details(&msg).await,
+                    None => self.start_server(server_id, container_id).await,
+                }
+            }
+        }
+    }
+
+    /// Freeze a running server in place; its memory and game state are kept.
+    async fn pause_server(
+        &self,
+        server_id: &str,
+        container_id: &str,
+        reason: Option<String>,
+    ) -> AgentResult<()> {
+        if container_id.is_empty()
+            || !self
+                .runtime
+                .container_status(container_id)
+                .await?
+                .is_running()
+        {
+            return Err(AgentError::InvalidRequest(format!(
+                "Server {} is not running",
+                server_id
+            )));
+        }
+        self.runtime.pause_container(container_id).await?;
+        let _ = self
+            .emit_console_output(server_id, "system", "[Catalyst] Server paused.\n")
+            .await;
+        self.emit_server_state_update(server_id, "paused", reason, None, None)
+            .await
+    }
+
+    async fn resume_server(&self, server_id: &str, container_id: &str) -> AgentResult<()> {
+        if container_id.is_empty()
+            || self.runtime.container_status(container_id).await? != ContainerStatus::Paused
+        {
+            return Err(AgentError::InvalidRequest(format!(
+                "Server {} is not paused",
+                server_id
+            )));
+        }
+        self.runtime.resume_container(container_id).await?;
+        // The watchdog was disarmed while frozen; restart its grace period.
+        self.watchdog.arm(server_id).await;
+        let _ = self
+            .emit_console_output(server_id, "system", "[Catalyst] Server resumed.\n")
+            .await;
+        self.emit_server_state_update(server_id, "running", None, None, None)
+            .await
+    }
+
+    async fn kill_server(&self, server_id: &str, container_id: String) -> AgentResult<()> {
+        if container_id.is_empty() {
+            info!(
+                "No container found for server {}, marking as killed",
+                server_id
+            );
+            self.stop_monitor_task(server_id).await;
+            self.emit_server_state_update(
+                server_id,
+                "crashed",
+                Some("Killed by agent".to_string()),
+                None,
+                Some(137),
+            )
+            .await?;
+            return Ok(());
+        }
+        info!(
+            "Force killing server: {} (container {})",
+            server_id, container_id
+        );
+
+        // Stop monitoring first - we don't want monitor interfering
+        self.stop_monitor_task(server_id).await;
+
+        let _ = self
+            .emit_console_output(
+                server_id,
+                "system",
+                "[Catalyst] Force killing server with SIGKILL...\n",
+            )
+            .await;
+
+        // Force kill the container - this method never fails and always attempts cleanup
+        if let Err(e) = self.runtime.force_kill_container(&container_id).await {
+            warn!(
+                "Force kill had issues for {}: {}, continuing with cleanup",
+                container_id, e
+            );
+        }
+
+        // Always attempt to remove the container regardless of what happened above
+        // remove_container also sends SIGKILL, so this is a safety net
+        if self.runtime.container_exists(&container_id).await {
+            if let Err(e) = self.runtime.remove_container(&container_id).await {
+                warn!(
+                    "Failed to remove container {}: {}, server state still updated",
+                    container_id, e
+                );
+            }
+        }
+
+        // Always update state to crashed - this must happen no matter what
+        self.emit_server_state_update(
+            server_id,
+            "crashed",
+            Some("Killed by agent".to_string()),
+            None,
+            Some(137), // 128 + 9 (SIGKILL exit code)
+        )
+        .await?;
+
+        Ok(())
+    }
+
+    async fn handle_console_input(&self, msg: &Value) -> AgentResult<()> {
+        let server_id = msg["serverId"]
+            .as_str()
+            .ok_or_else(|| AgentError::InvalidRequest("Missing serverId".to_string()))?;
+
+        let data = msg["data"]
+            .as_str()
+            .ok_or_else(|| AgentError::InvalidRequest("Missing data".to_string()))?;
+        // Raw keystrokes (Ctrl+C, arrow keys) for PTY consoles arrive base64-encoded.
+        let bytes = match msg.get("encoding").and_then(Value::as_str) {
+            Some("base64") => base64::engine::general_purpose::STANDARD
+                .decode(data)
+                .map_err(|_| AgentError::InvalidRequest("Invalid base64 data".to_string()))?,
+            _ => data.as_bytes().to_vec(),
+        };
+
+        let server_uuid = msg
+            .get("serverUuid")
+            .and_then(|value| value.as_str())
+            .unwrap_or(server_id);
+        info!(
+            "Received console input for server {} (uuid {}), bytes={}",
+            server_id,
+            server_uuid,
+            bytes.len()
+        );
+        let container_id = self.resolve_container_id(server_id, server_uuid).await;
+        if container_id.is_empty() {
+            let err =
+                AgentError::ContainerError(format!("Container not found for server {}", server_id));
+            let _ = self
+                .emit_console_output(
+                    server_id,
+                    "stderr",
                     &format!("[Catalyst] Console input failed: {}\n", err),
                 )
                 .await;
@@ -1726,8 +2434,53 @@ impl WebSocketHandler {
 
         self.spawn_log_stream(server_id, &container_id);
 
+        // Line commands may go over RCON; raw keystrokes and PTY input always use stdin.
+        let is_raw = msg.get("encoding").and_then(Value::as_str) == Some("base64")
+            || self.runtime.is_tty(&container_id);
+        let force_rcon = msg.get("transport").and_then(Value::as_str) == Some("rcon");
+        if !is_raw && (force_rcon || self.rcon.is_configured(server_id).await) {
+            let result = if force_rcon {
+                self.rcon
+                    .execute(
+                        server_id,
+                        &container_id,
+                        data.trim_end_matches(['\r', '\n']),
+                    )
+                    .await
+                    .map(Some)
+                    .map_err(AgentError::from)
+            } else {
+                self.send_server_command(server_id, &container_id, data)
+                    .await
+            };
+            return match result {
+                Ok(Some(response)) => {
+                    if !response.trim().is_empty() {
+                        let mut output = response;
+                        if !output.ends_with('\n') {
+                            output.push('\n');
+                        }
+                        self.emit_console_output(server_id, "stdout", &output)
+                            .await?;
+                    }
+                    Ok(())
+                }
+                Ok(None) => Ok(()),
+                Err(err) => {
+                    let _ = self
+                        .emit_console_output(
+                            server_id,
+                            "stderr",
+                            &format!("[Catalyst] Console input failed: {}\n", err),
+                        )
+                        .await;
+                    Err(err)
+                }
+            };
+        }
+
         // Send to container stdin
-        if let Err(err) = self.runtime.send_input(&container_id, data).await {
+        if let Err(err) = self.runtime.send_input_bytes(&container_id, &bytes).await {
             let _ = self
                 .emit_console_output(
                     server_id,
@@ -1746,6 +2499,231 @@ impl WebSocketHandler {
         Ok(())
     }
 
+    async fn handle_console_resize(&self, msg: &Value) -> AgentResult<()> {
+        let server_id = msg["serverId"]
+            .as_str()
+            .ok_or_else(|| AgentError::InvalidRequest("Missing serverId".to_string()))?;
+        let server_uuid = msg
+            .get("serverUuid")
+            .and_then(|value| value.as_str())
+            .unwrap_or(server_id);
+        let cols = msg["cols"]
+            .as_u64()
+            .filter(|value| (1..=u16::MAX as u64).contains(value))
+            .ok_or_else(|| AgentError::InvalidRequest("Missing or invalid cols".to_string()))?;
+        let rows = msg["rows"]
+            .as_u64()
+            .filter(|value| (1..=u16::MAX as u64).contains(value))
+            .ok_or_else(|| AgentError::InvalidRequest("Missing or invalid rows".to_string()))?;
+
+        let container_id = self.resolve_container_id(server_id, server_uuid).await;
+        if container_id.is_empty() {
+            return Err(AgentError::ContainerError(format!(
+                "Container not found for server {}",
+                server_id
+            )));
+        }
+        if !self.runtime.is_tty(&container_id) {
+            debug!(
+                "Ignoring console resize for {}: console is not a PTY",
+                server_id
+            );
+            return Ok(());
+        }
+
+        debug!(
+            "Resizing console for {} (container {}) to {}x{}",
+            server_id, container_id, cols, rows
+        );
+        self.runtime
+            .resize_console(&container_id, cols as u32, rows as u32)
+            .await
+    }
+
+    async fn handle_exec_session_start(&self, msg: &Value) -> AgentResult<()> {
+        let session_id = msg["sessionId"]
+            .as_str()
+            .ok_or_else(|| AgentError::InvalidRequest("Missing sessionId".to_string()))?;
+        let server_id = msg["serverId"]
+            .as_str()
+            .ok_or_else(|| AgentError::InvalidRequest("Missing serverId".to_string()))?;
+        let server_uuid = msg
+            .get("serverUuid")
+            .and_then(|value| value.as_str())
+            .unwrap_or(server_id);
+
+        let result: AgentResult<tokio::net::unix::pipe::Receiver> = async {
+            let command = match msg.get("command") {
+                Some(Value::Array(items)) => {
+                    let args: Vec<String> = items
+                        .iter()
+                        .filter_map(|item| item.as_str().map(str::to_string))
+                        .collect();
+                    if args.is_empty() || args.len() != items.len() {
+                        return Err(AgentError::InvalidRequest(
+                            "command must be a non-empty array of strings".to_string(),
+                        ));
+                    }
+                    args
+                }
+                Some(Value::String(command)) if !command.trim().is_empty() => {
+                    vec!["/bin/sh".to_string(), "-c".to_string(), command.to_string()]
+                }
+                _ => vec!["/bin/sh".to_string()],
+            };
+            let cols = msg["cols"].as_u64().unwrap_or(80).clamp(1, u16::MAX as u64) as u32;
+            let rows = msg["rows"].as_u64().unwrap_or(24).clamp(1, u16::MAX as u64) as u32;
+            let actor = msg
+                .get("username")
+                .or_else(|| msg.get("userId"))
+                .and_then(Value::as_str);
+
+            let container_id = self.resolve_container_id(server_id, server_uuid).await;
+            if container_id.is_empty()
+                || !self
+                    .runtime
+                    .is_container_running(&container_id)
+                    .await
+                    .unwrap_or(false)
+            {
+                return Err(AgentError::ContainerError(format!(
+                    "Server {} is not running",
+                    server_id
+                )));
+            }
+
+            self.exec_sessions
+                .open(ExecSessionRequest {
+                    session_id,
+                    server_id,
+                    container_id: &container_id,
+                    command,
+                    cols,
+                    rows,
+                    actor,
+                })
+                .await
+        }
+        .await;
+
+        let event = match &result {
+            Ok(_) => json!({
+                "type": "exec_session_started",
+                "sessionId": session_id,
+                "serverId": server_id,
+                "success": true,
+            }),
+            Err(err) => json!({
+                "type": "exec_session_started",
+                "sessionId": session_id,
+                "serverId": server_id,
+                "success": false,
+                "error": err.to_string(),
+            }),
+        };
+        self.send_event(&event).await;
+
+        let stdout = result?;
+        self.spawn_exec_session_pump(session_id, server_id, stdout);
+        Ok(())
+    }
+
+    /// Stream PTY output for an exec session until its process exits, then report the
+    /// exit status and release the session.
+    fn spawn_exec_session_pump(
+        &self,
+        session_id: &str,
+        server_id: &str,
+        mut stdout: tokio::net::unix::pipe::Receiver,
+    ) {
+        let handler = self.clone();
+        let session_id = session_id.to_string();
+        let server_id = server_id.to_string();
+        tokio::spawn(async move {
+            let exit = handler.exec_sessions.wait(&session_id);
+            tokio::pin!(exit);
+            let mut buffer = vec![0u8; 16 * 1024];
+            let mut bytes_out = 0u64;
+
+            let exit_code = loop {
+                tokio::select! {
+                    read = stdout.read(&mut buffer) => match read {
+                        Ok(0) | Err(_) => break (&mut exit).await.ok(),
+                        Ok(read) => {
+                            bytes_out += read as u64;
+                            handler
+                                .emit_exec_session_output(&session_id, &server_id, &buffer[..read])
+                                .await;
+                        }
+                    },
+                    result = &mut exit => {
+                        // Drain whatever the process wrote right before exiting.
+                        while let Ok(read) = stdout.try_read(&mut buffer) {
+                            if read == 0 {
+                                break;
+                            }
+                            bytes_out += read as u64;
+                            handler
+                                .emit_exec_session_output(&session_id, &server_id, &buffer[..read])
+                                .await;
+                        }
+                        break result.ok();
+                    }
+                }
+            };
+
+            handler
+                .exec_sessions
+                .finish(&session_id, exit_code, bytes_out)
+                .await;
+            handler
+                .send_event(&json!({
+                    "type": "exec_session_exit",
+                    "sessionId": session_id,
+                    "serverId": server_id,
+                    "exitCode": exit_code,
+                }))
+                .await;
+        });
+    }
+
+    async fn handle_exec_session_input(&self, msg: &Value) -> AgentResult<()> {
+        let session_id = msg["sessionId"]
+            .as_str()
+            .ok_or_else(|| AgentError::InvalidRequest("Missing sessionId".to_string()))?;
+        let data = msg["data"]
+            .as_str()
+            .ok_or_else(|| AgentError::InvalidRequest("Missing data".to_string()))?;
+        let bytes = base64::engine::general_purpose::STANDARD
+            .decode(data)
+            .map_err(|_| AgentError::InvalidRequest("Invalid base64 data".to_string()))?;
+        self.exec_sessions.write_input(session_id, &bytes).await
+    }
+
+    async fn handle_exec_session_resize(&self, msg: &Value) -> AgentResult<()> {
+        let session_id = msg["sessionId"]
+            .as_str()
+            .ok_or_else(|| AgentError::InvalidRequest("Missing sessionId".to_string()))?;
+        let cols = msg["cols"]
+            .as_u64()
+            .filter(|value| (1..=u16::MAX as u64).contains(value))
+            .ok_or_else(|| AgentError::InvalidRequest("Missing or invalid cols".to_string()))?;
+        let rows = msg["rows"]
+            .as_u64()
+            .filter(|value| (1..=u16::MAX as u64).contains(value))
+            .ok_or_else(|| AgentError::InvalidRequest("Missing or invalid rows".to_string()))?;
+        self.exec_sessions
+            .resize(session_id, cols as u32, rows as u32)
+            .await
+    }
+
+    async fn handle_exec_session_close(&self, msg: &Value) -> AgentResult<()> {
+        let session_id = msg["sessionId"]
+            .as_str()
+            .ok_or_else(|| AgentError::InvalidRequest("Missing sessionId".to_string()))?;
+        self.exec_sessions.terminate(session_id).await
+    }
+
     async fn handle_file_operation(&self, msg: &Value) -> AgentResult<()> {
         let op_type = msg
             .get("operation")
@@ -1841,6 +2819,54 @@ impl WebSocketHandler {
         result.map(|_| ())
     }
 
+    /// Recursively repair the ownership and modes of a server's files, for the owner
+    /// given by the template's security profile. Replies with `fix_permissions_result`.
+    async fn handle_fix_permissions(
+        &self,
+        msg: &Value,
+        write: &Arc<tokio::sync::Mutex<WsWrite>>,
+    ) -> AgentResult<()> {
+        let server_id = msg["serverId"]
+            .as_str()
+            .ok_or_else(|| AgentError::InvalidRequest("Missing serverId".to_string()))?;
+        let server_uuid = msg["serverUuid"].as_str().unwrap_or(server_id);
+        validate_safe_path_segment(server_uuid, "serverUuid")?;
+
+        let result = match security_profile(msg, &self.config.security) {
+            Ok(security) => match self.server_owner(server_uuid, &security).await {
+                Ok(owner) => self.file_manager.fix_permissions(server_uuid, owner).await,
+                Err(err) => Err(err),
+            },
+            Err(err) => Err(err),
+        };
+
+        let event = match &result {
+            Ok(changed) => json!({
+                "type": "fix_permissions_result",
+                "requestId": msg.get("requestId"),
+                "serverId": server_id,
+                "success": true,
+                "changed": changed,
+            }),
+            Err(err) => json!({
+                "type": "fix_permissions_result",
+                "requestId": msg.get("requestId"),
+                "serverId": server_id,
+                "success": false,
+                "error": err.to_string(),
+            }),
+        };
+
+        let mut w = write.lock().await;
+        w.send(Message::Text(event.to_string().into()))
+            .await
+            .map_err(|e| AgentError::NetworkError(e.to_string()))?;
+
+        result?;
+
+        Ok(())
+    }
+
     async fn handle_create_backup(
         &self,
         msg: &Value,
@@ -2015,6 +3041,9 @@ impl WebSocketHandler {
                 stderr
             )));
         }
+        self.file_manager
+            .apply_owner_recursive(server_uuid, &server_dir)
+            .await?;
 
         let event = json!({
             "type": "backup_restore_complete",
@@ -2564,6 +3593,213 @@ impl WebSocketHandler {
         Ok(())
     }
 
+    /// Prune unreferenced images and orphaned snapshots, or with `dryRun` only report
+    /// what would be reclaimed.
+    async fn handle_prune_images(
+        &self,
+        msg: &Value,
+        write: &Arc<tokio::sync::Mutex<WsWrite>>,
+    ) -> AgentResult<()> {
+        let dry_run = msg["dryRun"].as_bool().unwrap_or(false);
+        let result = self.image_gc.prune(dry_run).await;
+
+        let event = match &result {
+            Ok(report) => json!({
+                "type": "prune_images_result",
+                "requestId": msg.get("requestId"),
+                "success": true,
+                "report": report,
+            }),
+            Err(err) => json!({
+                "type": "prune_images_result",
+                "requestId": msg.get("requestId"),
+                "success": false,
+                "error": err.to_string(),
+            }),
+        };
+
+        let mut w = write.lock().await;
+        w.send(Message::Text(event.to_string().into()))
+            .await
+            .map_err(|e| AgentError::NetworkError(e.to_string()))?;
+
+        result?;
+
+        Ok(())
+    }
+
+    /// Pull images ahead of the first start of a server, in the background. Prefetched
+    /// images are pinned against image GC; with `release` the listed images are unpinned
+    /// instead. Completion is reported as `prefetch_images_complete`.
+    async fn handle_prefetch_images(&self, msg: &Value) -> AgentResult<()> {
+        let images: Vec<String> = msg["images"]
+            .as_array()
+            .ok_or_else(|| AgentError::InvalidRequest("Missing images".to_string()))?
+            .iter()
+            .filter_map(Value::as_str)
+            .filter(|image| !image.trim().is_empty())
+            .map(str::to_string)
+            .collect();
+        let qualified: Vec<String> = images
+            .iter()
+            .map(|image| ContainerdRuntime::qualify_image_ref(image))
+            .collect();
+        if msg["release"].as_bool() == Some(true) {
+            return self.storage_manager.pin_images(&qualified, false).await;
+        }
+        let credentials = registry_credentials(msg)?;
+        let pull_policy = image_pull_policy(msg, self.config.images.pull_policy)?;
+        // Pinned first so a GC run cannot remove an image right after it was pulled.
+        self.storage_manager.pin_images(&qualified, true).await?;
+
+        let handler = self.clone();
+        let request_id = msg.get("requestId").cloned();
+        tokio::spawn(async move {
+            let results: Vec<Value> = futures::stream::iter(images)
+                .map(|image| {
+                    let handler = handler.clone();
+                    let credentials = credentials.clone();
+                    async move {
+                        let result = handler
+                            .pull_image_with_progress(None, &image, &credentials, pull_policy)
+                            .await;
+                        if let Err(e) = &result {
+                            warn!("Prefetch of {} failed: {}", image, e);
+                        }
+                        json!({
+                            "image": image,
+                            "success": result.is_ok(),
+                            "error": result.err().map(|e| e.to_string()),
+                        })
+                    }
+                })
+                .buffer_unordered(PREFETCH_CONCURRENCY)
+                .collect()
+                .await;
+            handler
+                .send_event(&json!({
+                    "type": "prefetch_images_complete",
+                    "requestId": request_id,
+                    "images": results,
+                }))
+                .await;
+        });
+        Ok(())
+    }
+
+    /// Scheduled image GC; the report is sent to the backend when anything was removed.
+    pub async fn run_image_gc(&self) {
+        match self.image_gc.prune(false).await {
+            Ok(report) if report.images.is_empty() && report.snapshots.is_empty() => {
+                debug!("Image GC found nothing to prune");
+            }
+            Ok(report) => {
+                info!(
+                    "Image GC pruned {} image(s) and {} snapshot(s), ~{} MB",
+                    report.images.len(),
+                    report.snapshots.len(),
+                    report.reclaimable_bytes / (1024 * 1024)
+                );
+                self.send_event(&json!({ "type": "images_pruned", "report": report }))
+                    .await;
+            }
+            Err(e) => warn!("Image GC failed: {}", e),
+        }
+    }
+
+    /// Remember the image a server uses so image GC keeps it while the server is stopped.
+    async fn record_server_image(&self, server_id: &str, role: &str, image: &str) {
+        let image = ContainerdRuntime::qualify_image_ref(image);
+        if let Err(e) = self
+            .storage_manager
+            .record_server_image(server_id, role, &image)
+            .await
+        {
+            warn!("Failed to record image for server {}: {}", server_id, e);
+        }
+    }
+
+    /// Apply a new resource plan to a running server without restarting it.
+    async fn handle_update_resources(
+        &self,
+        msg: &Value,
+        write: &Arc<tokio::sync::Mutex<WsWrite>>,
+    ) -> AgentResult<()> {
+        let server_id = msg["serverId"]
+            .as_str()
+            .ok_or_else(|| AgentError::InvalidRequest("Missing serverId".to_string()))?;
+        let server_uuid = msg["serverUuid"]
+            .as_str()
+            .ok_or_else(|| AgentError::InvalidRequest("Missing serverUuid".to_string()))?;
+        validate_safe_path_segment(server_uuid, "serverUuid")?;
+
+        let result = async {
+            let memory_mb = msg["allocatedMemoryMb"].as_u64().ok_or_else(|| {
+                AgentError::InvalidRequest("Missing allocatedMemoryMb".to_string())
+            })?;
+            let cpu_millicores = crate::runtime_manager::parse_cpu_millicores(msg)?;
+            let limits = crate::runtime_manager::ResourceLimits::from_message(msg)?;
+            let data_dir = self.config.server.data_dir.join(server_uuid);
+            self.runtime
+                .update_resources(server_id, memory_mb, cpu_millicores, &limits, &data_dir)
+                .await?;
+            Ok::<u64, AgentError>(cpu_millicores)
+        }
+        .await;
+
+        if let Ok(cpu_millicores) = &result {
+            // Restarts by the agent itself should come back with the new plan.
+            if let Some(start_msg) = self.start_messages.write().await.get_mut(server_id) {
+                // Store the CPU plan in one field so a stale one cannot take precedence.
+                start_msg["allocatedCpuMillicores"] = json!(cpu_millicores);
+                if let Some(start_msg) = start_msg.as_object_mut() {
+                    start_msg.remove("allocatedCpuCores");
+                }
+                for key in [
+                    "allocatedMemoryMb",
+                    "pidsLimit",
+                    "ioReadBps",
+                    "ioWriteBps",
+                    "ioReadIops",
+                    "ioWriteIops",
+                    "cpusetCpus",
+                    "swapMb",
+                    "memoryReservationMb",
+                    "cpuWeight",
+                ] {
+                    if let Some(value) = msg.get(key) {
+                        start_msg[key] = value.clone();
+                    }
+                }
+            }
+        }
+
+        let event = match &result {
+            Ok(cpu_millicores) => json!({
+                "type": "resources_updated",
+                "serverId": server_id,
+                "serverUuid": server_uuid,
+                "allocatedMemoryMb": msg["allocatedMemoryMb"],
+                "allocatedCpuMillicores": cpu_millicores,
+                "success": true,
+            }),
+            Err(err) => json!({
+                "type": "resources_updated",
+                "serverId": server_id,
+                "serverUuid": server_uuid,
+                "success": false,
+                "error": err.to_string(),
+            }),
+        };
+
+        let mut w = write.lock().await;
+        w.send(Message::Text(event.to_string().into()))
+            .await
+            .map_err(|e| AgentError::NetworkError(e.to_string()))?;
+
+        result.map(|_| ())
+    }
+
     /// Handle create_network message
     async fn handle_create_network(
         &self,
@@ -2697,7 +3933,37 @@ impl WebSocketHandler {
         port_bindings: Option<HashMap<u16, u16>>,
         exit_code: Option<i32>,
     ) -> AgentResult<()> {
-        let msg = json!({
+        self.emit_server_state_update_with_details(
+            server_id,
+            state,
+            reason,
+            port_bindings,
+            exit_code,
+            None,
+        )
+        .await
+    }
+
+    /// Like `emit_server_state_update`, merging the fields of `details` into the message.
+    async fn emit_server_state_update_with_details(
+        &self,
+        server_id: &str,
+        state: &str,
+        reason: Option<String>,
+        port_bindings: Option<HashMap<u16, u16>>,
+        exit_code: Option<i32>,
+        details: Option<Value>,
+    ) -> AgentResult<()> {
+        if state != "starting" {
+            self.startup.clear(server_id).await;
+        }
+        if !matches!(state, "starting" | "running") {
+            self.rcon.disconnect(server_id).await;
+            self.game_query.reset(server_id).await;
+            self.watchdog.disarm(server_id).await;
+        }
+
+        let mut msg = json!({
             "type": "server_state_update",
             "serverId": server_id,
             "state": state,
@@ -2706,18 +3972,106 @@ impl WebSocketHandler {
             "portBindings": port_bindings,
             "exitCode": exit_code,
         });
+        if let (Some(Value::Object(details)), Some(fields)) = (details, msg.as_object_mut()) {
+            fields.extend(details);
+        }
+
+        debug!("Emitting state update: {}", msg);
+
+        let writer = { self.write.read().await.clone() };
+        if let Some(ws) = writer {
+            let mut w = ws.lock().await;
+            if let Err(err) = w.send(Message::Text(msg.to_string().into())).await {
+                error!("Failed to send state update: {}", err);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Pull an image, relaying progress as `image_pull_progress` events and, for a server,
+    /// a console line. Nothing is reported when the image is already present.
+    async fn pull_image_with_progress(
+        &self,
+        server_id: Option<&str>,
+        image: &str,
+        credentials: &[RegistryCredentials],
+        pull_policy: PullPolicy,
+    ) -> AgentResult<()> {
+        let (progress_tx, mut progress_rx) =
+            tokio::sync::mpsc::unbounded_channel::<ImagePullProgress>();
+        let handler = self.clone();
+        let event_server_id = server_id.map(str::to_string);
+        let event_image = image.to_string();
+        let forwarder = tokio::spawn(async move {
+            let mut blobs: std::collections::BTreeMap<String, ImagePullProgress> =
+                std::collections::BTreeMap::new();
+            let mut last_sent: Option<std::time::Instant> = None;
+            while let Some(update) = progress_rx.recv().await {
+                if let (true, Some(server_id)) = (blobs.is_empty(), &event_server_id) {
+                    let _ = handler
+                        .emit_console_output(
+                            server_id,
+                            "system",
+                            &format!("[Catalyst] Pulling image {}...\n", event_image),
+                        )
+                        .await;
+                }
+                blobs.insert(update.name.clone(), update);
+                if last_sent.is_some_and(|sent| sent.elapsed() < IMAGE_PULL_PROGRESS_INTERVAL) {
+                    continue;
+                }
+                last_sent = Some(std::time::Instant::now());
+                handler
+                    .send_event(&image_pull_progress_event(
+                        event_server_id.as_deref(),
+                        &event_image,
+                        &blobs,
+                        None,
+                    ))
+                    .await;
+            }
+            blobs
+        });
+
+        let result = self
+            .runtime
+            .pull_image(image, credentials, pull_policy, Some(progress_tx))
+            .await;
+        // The last event carries the outcome, so a failed pull never shows as complete.
+        if let Ok(blobs) = forwarder.await {
+            if !blobs.is_empty() {
+                self.send_event(&image_pull_progress_event(
+                    server_id,
+                    image,
+                    &blobs,
+                    Some(&result),
+                ))
+                .await;
+            }
+        }
+        result
+    }
 
-        debug!("Emitting state update: {}", msg);
+    async fn emit_exec_session_output(&self, session_id: &str, server_id: &str, data: &[u8]) {
+        self.send_event(&json!({
+            "type": "exec_session_output",
+            "sessionId": session_id,
+            "serverId": server_id,
+            "data": base64::engine::general_purpose::STANDARD.encode(data),
+        }))
+        .await;
+    }
 
+    /// Best-effort send of an event to the backend; dropped when disconnected.
+    async fn send_event(&self, event: &Value) {
         let writer = { self.write.read().await.clone() };
         if let Some(ws) = writer {
             let mut w = ws.lock().await;
-            if let Err(err) = w.send(Message::Text(msg.to_string().into())).await {
-                error!("Failed to send state update: {}", err);
+            if let Err(err) = w.send(Message::Text(event.to_string().into())).await {
+                error!("Failed to send {} event: {}", event["type"], err);
             }
         }
-
-        Ok(())
     }
 
     async fn emit_console_output(
@@ -2746,6 +4100,15 @@ impl WebSocketHandler {
             }
         }
 
+        if stream != "system" {
+            self.watchdog.note_output(server_id).await;
+        }
+        if stream != "system" && self.startup.observe(server_id, data).await {
+            info!("Server {} finished starting", server_id);
+            self.emit_server_state_update(server_id, "running", None, None, None)
+                .await?;
+        }
+
         Ok(())
     }
 
@@ -2756,6 +4119,7 @@ impl WebSocketHandler {
         system.refresh_cpu_all();
         system.refresh_memory();
         let cpu_percent = system.global_cpu_usage();
+        let load = System::load_average();
         let memory_usage_mb = system.used_memory() / 1024;
         let memory_total_mb = system.total_memory() / 1024;
         let mut disks = Disks::new_with_refreshed_list();
@@ -2779,6 +4143,14 @@ impl WebSocketHandler {
             "diskTotalMb": disk_total_mb,
             "containerCount": containers.iter().filter(|c| c.managed).count(),
             "uptimeSeconds": get_uptime(),
+            "loadAverage": [load.one, load.five, load.fifteen],
+            "cpuStealPercent": self.node_steal_percent().await,
+            "images": self.runtime.image_names().await.unwrap_or_default(),
+            "pressure": {
+                "cpu": node_pressure("cpu").await,
+                "memory": node_pressure("memory").await,
+                "io": node_pressure("io").await,
+            },
         });
 
         debug!("Health report: {}", health);
@@ -2794,6 +4166,18 @@ impl WebSocketHandler {
         Ok(())
     }
 
+    /// Share of CPU time stolen by the hypervisor since the previous health report.
+    async fn node_steal_percent(&self) -> Option<f64> {
+        let stat = tokio::fs::read_to_string("/proc/stat").await.ok()?;
+        let (total, steal) = crate::cgroup::parse_cpu_steal(&stat)?;
+        let previous = self.node_cpu_sample.write().await.replace((total, steal));
+        let (previous_total, previous_steal) = previous?;
+        let elapsed = total
+            .checked_sub(previous_total)
+            .filter(|delta| *delta > 0)?;
+        Some(steal.saturating_sub(previous_steal) as f64 / elapsed as f64 * 100.0)
+    }
+
     /// Reconcile server states by checking actual container status and updating backend
     /// This prevents status drift when containers exit unexpectedly or agent reconnects
     pub async fn reconcile_server_states(&self) -> AgentResult<()> {
@@ -2820,7 +4204,7 @@ impl WebSocketHandler {
                 continue;
             }
             found_uuids.push(container_name.clone());
-            if container.status.contains("Up") {
+            if container.status.is_running() {
                 running_containers.insert(container_name);
             }
         }
@@ -2835,23 +4219,23 @@ impl WebSocketHandler {
                 continue;
             }
 
-            let is_running = container.status.contains("Up");
-            let state = if is_running { "running" } else { "stopped" };
-
-            // If container is stopped, try to get exit code
-            let exit_code = if !is_running {
-                self.runtime
-                    .get_container_exit_code(&container.id)
-                    .await
-                    .ok()
-                    .flatten()
-            } else {
-                None
+            let state = match container.status {
+                ContainerStatus::Paused => "paused",
+                ContainerStatus::Running if self.startup.is_pending(&server_uuid).await => {
+                    "starting"
+                }
+                ContainerStatus::Running => "running",
+                ContainerStatus::Created | ContainerStatus::Stopped => "stopped",
             };
 
+            let exit_code = container.exit_code;
+
             info!(
                 "Reconciling container: name='{}', uuid='{}', status='{}', state='{}'",
-                container.names, server_uuid, container.status, state
+                container.names,
+                server_uuid,
+                container.status.as_str(),
+                state
             );
 
             let msg = json!({
@@ -2860,6 +4244,12 @@ impl WebSocketHandler {
                 "containerId": server_uuid,  // Use container name (CUID), not internal container ID
                 "state": state,
                 "exitCode": exit_code,
+                "startedAt": container.started_at.and_then(|started| {
+                    started
+                        .duration_since(std::time::UNIX_EPOCH)
+                        .ok()
+                        .map(|since| since.as_millis() as u64)
+                }),
                 "timestamp": chrono::Utc::now().timestamp_millis(),
             });
 
@@ -2897,7 +4287,7 @@ impl WebSocketHandler {
 
         loop {
             // Subscribe to all events
-            let event_stream = match self.runtime.subscribe_to_all_events().await {
+            let mut events = match self.runtime.subscribe_to_all_events().await {
                 Ok(stream) => stream,
                 Err(e) => {
                     error!(
@@ -2909,24 +4299,9 @@ impl WebSocketHandler {
                 }
             };
 
-            let mut receiver = event_stream.receiver;
-
-            // Read events from containerd gRPC streaming
-            while let Ok(Some(envelope)) = receiver.message().await {
-                let topic = &envelope.topic;
-
-                if topic.is_empty() {
-                    continue;
-                }
-
-                // Extract container ID from the event envelope
-                // containerd events include the container ID in the event payload
-                let container_name = if let Some(ref event) = envelope.event {
-                    // Try to parse the container_id from the protobuf Any
-                    extract_container_id_from_event(event).unwrap_or_default()
-                } else {
-                    String::new()
-                };
+            while let Some(event) = events.next().await {
+                let topic = &event.topic;
+                let container_name = event.container_id;
 
                 if container_name.is_empty() {
                     continue;
@@ -2939,7 +4314,7 @@ impl WebSocketHandler {
 
                 // Map containerd event topics to state-changing events
                 match topic.as_str() {
-                    "/tasks/start" | "/tasks/exit" | "/tasks/paused" => {
+                    "/tasks/start" | "/tasks/exit" | "/tasks/paused" | "/tasks/resumed" => {
                         debug!("Container {} event: {}", container_name, topic);
 
                         // Give the container a moment to stabilize state
@@ -2965,7 +4340,7 @@ impl WebSocketHandler {
 
             // Stream ended, restart
             warn!("Global event stream ended, restarting in 5s...");
-            drop(receiver);
+            drop(events);
             tokio::time::sleep(Duration::from_secs(5)).await;
         }
     }
@@ -2984,14 +4359,19 @@ impl WebSocketHandler {
         }
 
         // Check if container is running and get its state
-        let is_running = self
+        let status = self
             .runtime
-            .is_container_running(container_name)
+            .container_status(container_name)
             .await
-            .unwrap_or(false);
-        let state = if is_running { "running" } else { "stopped" };
+            .unwrap_or(ContainerStatus::Stopped);
+        let state = match status {
+            ContainerStatus::Paused => "paused",
+            ContainerStatus::Running if self.startup.is_pending(container_name).await => "starting",
+            ContainerStatus::Running => "running",
+            ContainerStatus::Created | ContainerStatus::Stopped => "stopped",
+        };
 
-        let exit_code = if !is_running {
+        let exit_code = if status == ContainerStatus::Stopped {
             self.runtime
                 .get_container_exit_code(container_name)
                 .await
@@ -3043,73 +4423,176 @@ impl WebSocketHandler {
         Ok(())
     }
 
-    pub async fn send_resource_stats(&self) -> AgentResult<()> {
-        let containers = self.runtime.list_containers().await?;
-        if containers.is_empty() {
+    /// Report volume usage for every server with data on this node, running or stopped.
+    async fn send_storage_stats(&self) -> AgentResult<()> {
+        let writer = { self.write.read().await.clone() };
+        let Some(ws) = writer else {
+            return Ok(());
+        };
+
+        let mut servers = Vec::new();
+        for (server_id, server_uuid) in self.storage_manager.server_index().await {
+            let disk = match self.storage_manager.disk_usage(&server_uuid).await {
+                Ok(Some(disk)) => disk,
+                Ok(None) => continue,
+                Err(e) => {
+                    warn!("Failed to read disk usage for {}: {}", server_id, e);
+                    continue;
+                }
+            };
+            servers.push(json!({
+                "serverId": server_id,
+                "serverUuid": server_uuid,
+                "diskUsageMb": disk.used_bytes / (1024 * 1024),
+                "diskTotalMb": disk.total_bytes / (1024 * 1024),
+                "diskAvailableMb": disk.available_bytes / (1024 * 1024),
+                "inodesUsed": disk.inodes_used,
+                "inodesTotal": disk.inodes_total,
+            }));
+        }
+        if servers.is_empty() {
             return Ok(());
         }
 
-        let writer_opt = { self.write.read().await.clone() };
-        // writer_opt may be None if we're not connected; we will buffer metrics to disk in that case;
+        let msg = json!({
+            "type": "storage_stats",
+            "servers": servers,
+            "timestamp": chrono::Utc::now().timestamp_millis(),
+        });
+        let mut w = ws.lock().await;
+        w.send(Message::Text(msg.to_string().into()))
+            .await
+            .map_err(|e| AgentError::NetworkError(e.to_string()))?;
+        Ok(())
+    }
 
-        for container in containers {
-            if !container.status.contains("Up") || !container.managed {
-                continue;
-            }
+    /// Build the `resource_stats` payload for one running managed container.
+    async fn collect_resource_stats(
+        &self,
+        container: &crate::runtime_manager::ContainerInfo,
+        server_index: &HashMap<String, String>,
+    ) -> Option<Value> {
+        // Paused servers still hold memory and disk, so they keep reporting.
+        if !matches!(
+            container.status,
+            ContainerStatus::Running | ContainerStatus::Paused
+        ) || !container.managed
+        {
+            return None;
+        }
 
-            let server_uuid = normalize_container_name(&container.names);
-            if server_uuid.is_empty() {
-                continue;
+        let server_uuid = normalize_container_name(&container.names);
+        if server_uuid.is_empty() {
+            return None;
+        }
+
+        let stats = match self.runtime.get_stats(&container.id).await {
+            Ok(stats) => stats,
+            Err(err) => {
+                warn!(
+                    "Failed to fetch stats for container {}: {}",
+                    container.id, err
+                );
+                return None;
             }
+        };
 
-            let stats = match self.runtime.get_stats(&container.id).await {
-                Ok(stats) => stats,
+        let memory_usage_mb = stats.memory_usage_bytes / (1024 * 1024);
+        let disk_io_mb = (stats.block_read_bytes + stats.block_write_bytes) / (1024 * 1024);
+        let disk = match server_index.get(&server_uuid) {
+            Some(volume_uuid) => match self.storage_manager.disk_usage(volume_uuid).await {
+                Ok(disk) => disk,
                 Err(err) => {
-                    warn!(
-                        "Failed to fetch stats for container {}: {}",
-                        container.id, err
-                    );
-                    continue;
+                    warn!("Failed to read disk usage for {}: {}", server_uuid, err);
+                    None
                 }
-            };
+            },
+            None => None,
+        };
+        // Servers started before the index existed fall back to block IO until restarted.
+        let (disk_usage_mb, disk_total_mb) = disk
+            .map(|disk| {
+                (
+                    disk.used_bytes / (1024 * 1024),
+                    disk.total_bytes / (1024 * 1024),
+                )
+            })
+            .unwrap_or((disk_io_mb, 0));
+
+        let mut payload = json!({
+            "type": "resource_stats",
+            "serverUuid": server_uuid,
+            "paused": container.status == ContainerStatus::Paused,
+            "cpuPercent": stats.cpu_percent,
+            "memoryUsageMb": memory_usage_mb,
+            "memoryLimitMb": stats.memory_limit_bytes.map(|bytes| bytes / (1024 * 1024)),
+            "memoryCacheMb": stats.memory_cache_bytes / (1024 * 1024),
+            "networkRxBytes": stats.net_rx_bytes,
+            "networkTxBytes": stats.net_tx_bytes,
+            "diskReadBytes": stats.block_read_bytes,
+            "diskWriteBytes": stats.block_write_bytes,
+            "diskIoMb": disk_io_mb,
+            "diskUsageMb": disk_usage_mb,
+            "diskTotalMb": disk_total_mb,
+            "diskInodesUsed": disk.map(|disk| disk.inodes_used),
+            "diskInodesTotal": disk.map(|disk| disk.inodes_total),
+            "cpuThrottledPeriods": stats.cpu_nr_throttled,
+            "cpuThrottledUsec": stats.cpu_throttled_usec,
+            "pressure": {
+                "cpu": stats.cpu_pressure,
+                "memory": stats.memory_pressure,
+                "io": stats.io_pressure,
+            },
+            "timestamp": chrono::Utc::now().timestamp_millis(),
+        });
+        if let Some(query) = self.game_query.status(&server_uuid).await {
+            payload["query"] = json!({
+                "online": query.online,
+                "players": query.info.players,
+                "maxPlayers": query.info.max_players,
+                "motd": query.info.motd,
+                "map": query.info.map,
+                "latencyMs": query.info.latency_ms,
+                "error": query.error,
+                "consecutiveFailures": query.consecutive_failures,
+                "checkedAt": query.checked_at,
+            });
+        }
 
-            let cpu_percent = parse_percent(&stats.cpu_percent).unwrap_or(0.0);
-            let memory_usage_mb = parse_memory_usage_mb(&stats.memory_usage).unwrap_or(0);
-            let (network_rx_bytes, network_tx_bytes) =
-                parse_io_pair_bytes(&stats.net_io).unwrap_or((0, 0));
-            let (disk_read_bytes, disk_write_bytes) =
-                parse_io_pair_bytes(&stats.block_io).unwrap_or((0, 0));
-            let disk_io_mb = (disk_read_bytes + disk_write_bytes) / (1024 * 1024);
-            let (disk_usage_mb, disk_total_mb) = match self
-                .runtime
-                .exec(&container.id, vec!["df", "-m", "/data"])
-                .await
-                .ok()
-                .and_then(|output| parse_df_output_mb(&output))
-            {
-                Some(value) => value,
-                None => {
-                    warn!(
-                        "Failed to read filesystem usage for container {}. Falling back to block IO stats.",
-                        container.id
-                    );
-                    (disk_io_mb, 0)
-                }
-            };
+        Some(payload)
+    }
 
-            let payload = json!({
-                "type": "resource_stats",
-                "serverUuid": server_uuid,
-                "cpuPercent": cpu_percent,
-                "memoryUsageMb": memory_usage_mb,
-                "networkRxBytes": network_rx_bytes,
-                "networkTxBytes": network_tx_bytes,
-                "diskIoMb": disk_io_mb,
-                "diskUsageMb": disk_usage_mb,
-                "diskTotalMb": disk_total_mb,
-                "timestamp": chrono::Utc::now().timestamp_millis(),
-            });
+    pub async fn send_resource_stats(&self) -> AgentResult<()> {
+        if let Err(e) = self.send_storage_stats().await {
+            warn!("Failed to send storage stats: {}", e);
+        }
+
+        let containers = self.runtime.list_containers().await?;
+        if containers.is_empty() {
+            return Ok(());
+        }
+
+        let writer_opt = { self.write.read().await.clone() };
+        // writer_opt may be None if we're not connected; we will buffer metrics to disk in that case;
+
+        // Collect concurrently so a node with hundreds of servers finishes within the tick.
+        let server_index = Arc::new(self.storage_manager.server_index().await);
+        let payloads: Vec<Value> = futures::stream::iter(containers)
+            .map(|container| {
+                let handler = self.clone();
+                let server_index = server_index.clone();
+                async move {
+                    handler
+                        .collect_resource_stats(&container, &server_index)
+                        .await
+                }
+            })
+            .buffer_unordered(STATS_CONCURRENCY)
+            .filter_map(|payload| async move { payload })
+            .collect()
+            .await;
 
+        for payload in payloads {
             // If we have a live write handle, send; otherwise buffer to disk immediately
             match &writer_opt {
                 Some(ws) => {
@@ -3153,6 +4636,50 @@ fn get_uptime() -> u64 {
         .unwrap_or(0)
 }
 
+/// Node-wide pressure stall information from `/proc/pressure/<resource>`.
+async fn node_pressure(resource: &str) -> Option<crate::cgroup::Pressure> {
+    let content = tokio::fs::read_to_string(format!("/proc/pressure/{}", resource))
+        .await
+        .ok()?;
+    crate::cgroup::parse_pressure(&content)
+}
+
+/// Progress of a pull summed over its blobs. `outcome` is set on the final event.
+fn image_pull_progress_event(
+    server_id: Option<&str>,
+    image: &str,
+    blobs: &std::collections::BTreeMap<String, ImagePullProgress>,
+    outcome: Option<&AgentResult<()>>,
+) -> Value {
+    let layers: Vec<Value> = blobs
+        .values()
+        .map(|blob| {
+            json!({
+                "name": blob.name,
+                "status": blob.event,
+                "progress": blob.progress,
+                "total": blob.total,
+            })
+        })
+        .collect();
+    let mut event = json!({
+        "type": "image_pull_progress",
+        "serverId": server_id,
+        "image": image,
+        "layers": layers,
+        "downloadedBytes": blobs.values().map(|blob| blob.progress).sum::<u64>(),
+        "totalBytes": blobs.values().map(|blob| blob.total).sum::<u64>(),
+        "done": outcome.is_some(),
+    });
+    if let Some(outcome) = outcome {
+        event["success"] = json!(outcome.is_ok());
+        if let Err(err) = outcome {
+            event["error"] = json!(err.to_string());
+        }
+    }
+    event
+}
+
 fn normalize_container_name(name: &str) -> String {
     name.split(|c: char| c == ',' || c.is_whitespace())
         .find(|part| !part.trim().is_empty())
@@ -3162,105 +4689,523 @@ fn normalize_container_name(name: &str) -> String {
         .to_string()
 }
 
-/// Extract container_id from a containerd event's protobuf Any payload
-fn extract_container_id_from_event(event: &prost_types::Any) -> Option<String> {
-    // containerd task events encode container_id as a field in the protobuf message
-    // The value bytes contain the serialized protobuf; container_id is typically field 1 (tag 0x0a)
-    let data = &event.value;
-    let mut i = 0;
-    while i < data.len() {
-        let tag_byte = data[i];
-        let field_number = tag_byte >> 3;
-        let wire_type = tag_byte & 0x07;
-        i += 1;
-        if wire_type == 2 {
-            // Length-delimited field
-            if i >= data.len() {
-                break;
+/// Drain the longest valid UTF-8 prefix from `pending`, leaving an incomplete trailing
+/// sequence in place for the next read. Invalid bytes are replaced rather than retained.
+fn take_complete_utf8(pending: &mut Vec<u8>) -> String {
+    let mut checked = 0;
+    let complete = loop {
+        match std::str::from_utf8(&pending[checked..]) {
+            Ok(_) => break pending.len(),
+            Err(err) => match err.error_len() {
+                Some(invalid) => checked += err.valid_up_to() + invalid,
+                None => break checked + err.valid_up_to(),
+            },
+        }
+    };
+    let chunk: Vec<u8> = pending.drain(..complete).collect();
+    String::from_utf8_lossy(&chunk).into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fake_runtime::FakeRuntime;
+    use tokio::net::TcpListener;
+    use tokio::sync::mpsc;
+
+    fn test_config(data_dir: &Path) -> AgentConfig {
+        toml::from_str(&format!(
+            r#"
+            [server]
+            backend_url = "ws://127.0.0.1:1"
+            node_id = "node-1"
+            api_key = "key"
+            hostname = "node-1"
+            data_dir = "{}"
+            max_connections = 10
+
+            [containerd]
+            socket_path = "/run/containerd/containerd.sock"
+            namespace = "catalyst-test"
+
+            [logging]
+            level = "debug"
+            format = "text"
+            "#,
+            data_dir.display()
+        ))
+        .unwrap()
+    }
+
+    /// A handler on the fake runtime, connected to a backend that forwards every message
+    /// the agent sends.
+    async fn connected_handler(
+        runtime: Arc<FakeRuntime>,
+    ) -> (
+        WebSocketHandler,
+        Arc<tokio::sync::Mutex<WsWrite>>,
+        mpsc::UnboundedReceiver<Value>,
+    ) {
+        let data_dir = std::env::temp_dir().join(format!("catalyst-test-{}", uuid::Uuid::new_v4()));
+        let config = Arc::new(test_config(&data_dir));
+        let handler = WebSocketHandler::new(
+            config,
+            runtime,
+            Arc::new(FileManager::new(data_dir.clone())),
+            Arc::new(StorageManager::new(data_dir)),
+            Arc::new(RwLock::new(true)),
+        );
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let (sender, receiver) = mpsc::unbounded_channel();
+        tokio::spawn(async move {
+            let (stream, _) = listener.accept().await.unwrap();
+            let mut backend = tokio_tungstenite::accept_async(stream).await.unwrap();
+            while let Some(Ok(Message::Text(text))) = backend.next().await {
+                let _ = sender.send(serde_json::from_str(&text).unwrap());
             }
-            let len = data[i] as usize;
-            i += 1;
-            if field_number == 1 && i + len <= data.len() {
-                if let Ok(s) = std::str::from_utf8(&data[i..i + len]) {
-                    return Some(s.to_string());
-                }
+        });
+        let (stream, _) = connect_async(format!("ws://{}", addr)).await.unwrap();
+        let write = Arc::new(tokio::sync::Mutex::new(stream.split().0));
+        *handler.write.write().await = Some(write.clone());
+        (handler, write, receiver)
+    }
+
+    async fn next_state_update(messages: &mut mpsc::UnboundedReceiver<Value>) -> Value {
+        loop {
+            let msg = tokio::time::timeout(Duration::from_secs(5), messages.recv())
+                .await
+                .expect("no state update from the agent")
+                .unwrap();
+            if msg["type"] == "server_state_update" {
+                return msg;
             }
-            i += len;
-        } else if wire_type == 0 {
-            // Varint
-            while i < data.len() && data[i] & 0x80 != 0 {
-                i += 1;
+        }
+    }
+
+    #[tokio::test]
+    async fn stop_server_sends_stop_command_before_signalling() {
+        let runtime = Arc::new(FakeRuntime::new());
+        runtime.insert_container("srv-1", "docker.io/library/alpine:3.19");
+        runtime.exit_on_input("stop", 0);
+        let (handler, write, mut messages) = connected_handler(runtime.clone()).await;
+
+        let msg = json!({
+            "type": "stop_server",
+            "serverId": "srv-1",
+            "serverUuid": "srv-1",
+            "template": { "stopCommand": "stop" },
+        });
+        handler
+            .handle_message(&msg.to_string(), &write)
+            .await
+            .unwrap();
+
+        let update = next_state_update(&mut messages).await;
+        assert_eq!(update["state"], "stopped");
+        assert_eq!(runtime.calls(), vec!["remove_container srv-1"]);
+        assert!(!runtime.container_exists("srv-1").await);
+    }
+
+    #[tokio::test]
+    async fn console_input_reaches_the_server_process() {
+        let runtime = Arc::new(FakeRuntime::new());
+        runtime.insert_container("srv-1", "docker.io/library/alpine:3.19");
+        let (handler, write, _messages) = connected_handler(runtime.clone()).await;
+
+        let msg = json!({ "type": "console_input", "serverId": "srv-1", "data": "say hi\n" });
+        handler
+            .handle_message(&msg.to_string(), &write)
+            .await
+            .unwrap();
+        let msg = json!({
+            "type": "console_input",
+            "serverId": "srv-1",
+            "data": base64::engine::general_purpose::STANDARD.encode([3u8]),
+            "encoding": "base64",
+        });
+        handler
+            .handle_message(&msg.to_string(), &write)
+            .await
+            .unwrap();
+
+        assert_eq!(runtime.stdin("srv-1"), "say hi\n\u{3}");
+    }
+
+    #[tokio::test]
+    async fn raw_keystrokes_reach_the_pty_unchanged() {
+        let runtime = Arc::new(FakeRuntime::new());
+        runtime.insert_tty_container("srv-1", "docker.io/library/alpine:3.19");
+        let (handler, write, _messages) = connected_handler(runtime.clone()).await;
+
+        // Ctrl+C, Up arrow, Tab and Backspace.
+        let keys = b"\x03\x1b[A\t\x7f";
+        let msg = json!({
+            "type": "console_input",
+            "serverId": "srv-1",
+            "data": base64::engine::general_purpose::STANDARD.encode(keys),
+            "encoding": "base64",
+        });
+        handler
+            .handle_message(&msg.to_string(), &write)
+            .await
+            .unwrap();
+        assert_eq!(runtime.stdin("srv-1").as_bytes(), keys);
+
+        let msg = json!({ "type": "console_resize", "serverId": "srv-1", "cols": 132, "rows": 43 });
+        handler
+            .handle_message(&msg.to_string(), &write)
+            .await
+            .unwrap();
+        assert!(runtime
+            .calls()
+            .contains(&"resize_console 132x43 srv-1".to_string()));
+    }
+
+    #[test]
+    fn holds_back_split_utf8_sequences() {
+        let mut pending = "h\u{e9}".as_bytes()[..2].to_vec();
+        assert_eq!(take_complete_utf8(&mut pending), "h");
+        assert_eq!(pending, [0xc3]);
+        pending.extend_from_slice(&[0xa9, b'!']);
+        assert_eq!(take_complete_utf8(&mut pending), "\u{e9}!");
+        assert!(pending.is_empty());
+
+        // Invalid bytes are replaced instead of stalling the stream.
+        pending.extend_from_slice(&[b'a', 0xff, b'b', 0xe2, 0x82]);
+        assert_eq!(take_complete_utf8(&mut pending), "a\u{fffd}b");
+        assert_eq!(pending, [0xe2, 0x82]);
+        pending.push(0xac);
+        assert_eq!(take_complete_utf8(&mut pending), "\u{20ac}");
+    }
+
+    async fn next_console_line(messages: &mut mpsc::UnboundedReceiver<Value>) -> String {
+        loop {
+            let msg = tokio::time::timeout(Duration::from_secs(5), messages.recv())
+                .await
+                .expect("no console output from the agent")
+                .unwrap();
+            if msg["type"] == "console_output" && msg["stream"] == "stdout" {
+                return msg["data"].as_str().unwrap().to_string();
             }
-            i += 1;
-        } else {
-            break;
         }
     }
-    None
-}
 
-fn parse_percent(value: &str) -> Option<f64> {
-    let trimmed = value.trim().trim_end_matches('%').trim();
-    trimmed.parse::<f64>().ok()
-}
+    #[tokio::test]
+    async fn console_stream_survives_pause_and_resume() {
+        let runtime = Arc::new(FakeRuntime::new());
+        let container_id = format!("srv-{}", uuid::Uuid::new_v4());
+        runtime.insert_container(&container_id, "docker.io/library/alpine:3.19");
+        let (handler, write, mut messages) = connected_handler(runtime.clone()).await;
 
-fn parse_memory_usage_mb(value: &str) -> Option<u64> {
-    let first = value.split('/').next()?.trim();
-    parse_size_to_bytes(first).map(|bytes| bytes / (1024 * 1024))
-}
+        handler.spawn_log_stream(&container_id, &container_id);
+        runtime.print(&container_id, "before pause").await;
+        assert_eq!(next_console_line(&mut messages).await, "before pause\n");
 
-fn parse_io_pair_bytes(value: &str) -> Option<(u64, u64)> {
-    let mut parts = value.split('/');
-    let left = parts.next()?.trim();
-    let right = parts.next()?.trim();
-    let left_bytes = parse_size_to_bytes(left)?;
-    let right_bytes = parse_size_to_bytes(right)?;
-    Some((left_bytes, right_bytes))
-}
+        let msg = json!({ "type": "pause_server", "serverUuid": container_id });
+        handler
+            .handle_message(&msg.to_string(), &write)
+            .await
+            .unwrap();
+        assert_eq!(next_state_update(&mut messages).await["state"], "paused");
+        // Give the stream a few polls while the server is frozen.
+        tokio::time::sleep(Duration::from_millis(500)).await;
+
+        let msg = json!({ "type": "resume_server", "serverUuid": container_id });
+        handler
+            .handle_message(&msg.to_string(), &write)
+            .await
+            .unwrap();
+        assert_eq!(next_state_update(&mut messages).await["state"], "running");
+        runtime.print(&container_id, "after resume").await;
+        assert_eq!(next_console_line(&mut messages).await, "after resume\n");
+
+        runtime.exit(&container_id, 0);
+        let _ =
+            tokio::fs::remove_dir_all(PathBuf::from(CONSOLE_BASE_DIR).join(&container_id)).await;
+    }
 
-fn parse_size_to_bytes(value: &str) -> Option<u64> {
-    let trimmed = value.trim();
-    if trimmed.is_empty() {
-        return None;
+    #[tokio::test]
+    async fn console_stream_follows_a_restart() {
+        for tty in [false, true] {
+            let runtime = Arc::new(FakeRuntime::new());
+            let container_id = format!("srv-{}", uuid::Uuid::new_v4());
+            if tty {
+                runtime.insert_tty_container(&container_id, "docker.io/library/alpine:3.19");
+            } else {
+                runtime.insert_container(&container_id, "docker.io/library/alpine:3.19");
+            }
+            let (handler, _write, mut messages) = connected_handler(runtime.clone()).await;
+
+            runtime
+                .print(&container_id, "a long line from the first run")
+                .await;
+            handler.spawn_log_stream(&container_id, &container_id);
+            assert_eq!(
+                next_console_line(&mut messages).await,
+                "a long line from the first run\n"
+            );
+
+            runtime.restart(&container_id);
+            runtime.print(&container_id, "second run").await;
+            assert_eq!(next_console_line(&mut messages).await, "second run\n");
+
+            runtime.exit(&container_id, 0);
+            let _ = tokio::fs::remove_dir_all(PathBuf::from(CONSOLE_BASE_DIR).join(&container_id))
+                .await;
+        }
     }
-    static SIZE_RE: OnceLock<Regex> = OnceLock::new();
-    let re = SIZE_RE.get_or_init(|| {
-        Regex::new(r"(?i)^\s*([0-9]+(?:\.[0-9]+)?)\s*([kmgtp]?i?b?)?\s*$")
-            .expect("valid size regex")
-    });
-    let caps = re.captures(trimmed)?;
-    let number = caps.get(1)?.as_str().parse::<f64>().ok()?;
-    let unit = caps
-        .get(2)
-        .map(|m| m.as_str().to_lowercase())
-        .unwrap_or_default();
-    let multiplier = match unit.as_str() {
-        "" | "b" => 1f64,
-        "k" | "kb" => 1_000f64,
-        "ki" | "kib" => 1_024f64,
-        "m" | "mb" => 1_000_000f64,
-        "mi" | "mib" => 1_048_576f64,
-        "g" | "gb" => 1_000_000_000f64,
-        "gi" | "gib" => 1_073_741_824f64,
-        "t" | "tb" => 1_000_000_000_000f64,
-        "ti" | "tib" => 1_099_511_627_776f64,
-        _ => return None,
-    };
-    Some((number * multiplier).round() as u64)
-}
 
-fn parse_df_output_mb(output: &str) -> Option<(u64, u64)> {
-    let mut lines = output.lines().filter(|line| !line.trim().is_empty());
-    let header = lines.next()?;
-    if !header.to_lowercase().contains("filesystem") {
-        return None;
+    #[tokio::test]
+    async fn failed_pulls_end_with_an_unsuccessful_progress_event() {
+        let runtime = Arc::new(FakeRuntime::new());
+        let blob = |name: &str, event: &str, progress: u64, total: u64| ImagePullProgress {
+            name: name.to_string(),
+            event: event.to_string(),
+            progress,
+            total,
+        };
+        runtime.script_pull(
+            vec![
+                blob("layer-a", "downloading", 10, 100),
+                blob("layer-b", "waiting", 0, 50),
+                blob("layer-a", "complete", 100, 100),
+            ],
+            Some("registry unreachable"),
+        );
+        let (handler, _write, mut messages) = connected_handler(runtime.clone()).await;
+
+        let result = handler
+            .pull_image_with_progress(None, "alpine:3.19", &[], PullPolicy::IfNotPresent)
+            .await;
+        assert!(result.is_err());
+
+        let last = loop {
+            let msg = tokio::time::timeout(Duration::from_secs(5), messages.recv())
+                .await
+                .expect("no final pull event")
+                .unwrap();
+            if msg["type"] == "image_pull_progress" && msg["done"] == true {
+                break msg;
+            }
+        };
+        assert_eq!(last["success"], false);
+        assert!(last["error"]
+            .as_str()
+            .unwrap()
+            .contains("registry unreachable"));
+        // Each blob counts once, with its latest progress.
+        assert_eq!(last["layers"].as_array().unwrap().len(), 2);
+        assert_eq!(last["downloadedBytes"], 100);
+        assert_eq!(last["totalBytes"], 150);
+    }
+
+    #[tokio::test]
+    async fn resource_updates_replace_the_stored_cpu_plan() {
+        let runtime = Arc::new(FakeRuntime::new());
+        runtime.insert_container("srv-1", "docker.io/library/alpine:3.19");
+        let (handler, write, _messages) = connected_handler(runtime.clone()).await;
+        handler.start_messages.write().await.insert(
+            "srv-1".to_string(),
+            json!({ "serverId": "srv-1", "allocatedMemoryMb": 1024, "allocatedCpuMillicores": 1000 }),
+        );
+
+        let msg = json!({
+            "type": "update_resources",
+            "serverId": "srv-1",
+            "serverUuid": "srv-1",
+            "allocatedMemoryMb": 2048,
+            "allocatedCpuCores": 2.5,
+        });
+        handler
+            .handle_message(&msg.to_string(), &write)
+            .await
+            .unwrap();
+
+        assert_eq!(runtime.calls(), vec!["update_resources 2048MB 2500m srv-1"]);
+        let start_msg = handler.start_messages.read().await["srv-1"].clone();
+        assert_eq!(
+            crate::runtime_manager::parse_cpu_millicores(&start_msg).unwrap(),
+            2500
+        );
+        assert_eq!(start_msg["allocatedMemoryMb"], 2048);
+    }
+
+    #[tokio::test]
+    async fn exit_monitor_reports_crashes_and_oom_kills() {
+        let runtime = Arc::new(FakeRuntime::new());
+        runtime.insert_container("srv-1", "docker.io/library/alpine:3.19");
+        runtime.insert_container("srv-2", "docker.io/library/alpine:3.19");
+        let (handler, _write, mut messages) = connected_handler(runtime.clone()).await;
+
+        handler.spawn_exit_monitor("srv-1", "srv-1");
+        runtime.wait_for_subscriber("srv-1").await;
+        runtime.exit("srv-1", 1);
+        let update = next_state_update(&mut messages).await;
+        assert_eq!(update["serverId"], "srv-1");
+        assert_eq!(update["state"], "crashed");
+        assert_eq!(update["exitCode"], 1);
+        assert_eq!(update["reason"], "Container exited with code 1");
+
+        handler.spawn_exit_monitor("srv-2", "srv-2");
+        runtime.wait_for_subscriber("srv-2").await;
+        runtime.oom_kill("srv-2");
+        let update = next_state_update(&mut messages).await;
+        assert_eq!(update["serverId"], "srv-2");
+        assert_eq!(update["reason"], "oom_killed");
+        assert_eq!(update["exitCode"], 137);
+    }
+
+    async fn next_event(messages: &mut mpsc::UnboundedReceiver<Value>, kind: &str) -> Value {
+        loop {
+            let msg = tokio::time::timeout(Duration::from_secs(5), messages.recv())
+                .await
+                .unwrap_or_else(|_| panic!("no {} from the agent", kind))
+                .unwrap();
+            if msg["type"] == kind {
+                return msg;
+            }
+        }
+    }
+
+    async fn start_exec_session(
+        handler: &WebSocketHandler,
+        write: &Arc<tokio::sync::Mutex<WsWrite>>,
+        messages: &mut mpsc::UnboundedReceiver<Value>,
+        session_id: &str,
+    ) -> Value {
+        let msg = json!({
+            "type": "exec_session_start",
+            "sessionId": session_id,
+            "serverId": "srv-1",
+            "username": "admin",
+            "cols": 80,
+            "rows": 24,
+        });
+        let _ = handler.handle_message(&msg.to_string(), write).await;
+        next_event(messages, "exec_session_started").await
+    }
+
+    #[tokio::test]
+    async fn exec_sessions_relay_io_and_leave_an_audit_trail() {
+        let runtime = Arc::new(FakeRuntime::new());
+        runtime.insert_container("srv-1", "docker.io/library/alpine:3.19");
+        let (handler, write, mut messages) = connected_handler(runtime.clone()).await;
+
+        let started = start_exec_session(&handler, &write, &mut messages, "session-1").await;
+        assert_eq!(started["success"], true);
+
+        let msg = json!({
+            "type": "exec_session_input",
+            "sessionId": "session-1",
+            "data": base64::engine::general_purpose::STANDARD.encode("ls /\n"),
+        });
+        handler
+            .handle_message(&msg.to_string(), &write)
+            .await
+            .unwrap();
+        let mut echoed = Vec::new();
+        while echoed.len() < 5 {
+            let output = next_event(&mut messages, "exec_session_output").await;
+            assert_eq!(output["sessionId"], "session-1");
+            echoed.extend(
+                base64::engine::general_purpose::STANDARD
+                    .decode(output["data"].as_str().unwrap())
+                    .unwrap(),
+            );
+        }
+        assert_eq!(echoed, b"ls /\n");
+
+        let msg = json!({
+            "type": "exec_session_resize",
+            "sessionId": "session-1",
+            "cols": 120,
+            "rows": 40,
+        });
+        handler
+            .handle_message(&msg.to_string(), &write)
+            .await
+            .unwrap();
+        assert!(runtime
+            .calls()
+            .contains(&"resize_exec 120x40 srv-1".to_string()));
+
+        let msg = json!({ "type": "exec_session_close", "sessionId": "session-1" });
+        handler
+            .handle_message(&msg.to_string(), &write)
+            .await
+            .unwrap();
+        let exit = next_event(&mut messages, "exec_session_exit").await;
+        assert_eq!(exit["sessionId"], "session-1");
+        assert_eq!(exit["exitCode"], 137);
+
+        let audit = tokio::fs::read_to_string(
+            handler
+                .config
+                .server
+                .data_dir
+                .join("audit")
+                .join("exec_sessions.jsonl"),
+        )
+        .await
+        .unwrap();
+        let records: Vec<Value> = audit
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0]["event"], "exec_session_start");
+        assert_eq!(records[0]["actor"], "admin");
+        assert_eq!(records[0]["command"], json!(["/bin/sh"]));
+        assert_eq!(records[1]["event"], "exec_session_end");
+        assert_eq!(records[1]["sessionId"], "session-1");
+        assert_eq!(records[1]["exitCode"], 137);
+        assert_eq!(records[1]["bytesIn"], 5);
+        assert_eq!(records[1]["bytesOut"], 5);
+
+        let msg = json!({
+            "type": "exec_session_input",
+            "sessionId": "session-1",
+            "data": base64::engine::general_purpose::STANDARD.encode("ls\n"),
+        });
+        assert!(handler
+            .handle_message(&msg.to_string(), &write)
+            .await
+            .is_err());
     }
-    let data = lines.next()?;
-    let parts: Vec<&str> = data.split_whitespace().collect();
-    if parts.len() < 6 {
-        return None;
+
+    #[tokio::test]
+    async fn exec_sessions_reject_duplicates_and_excess_sessions() {
+        let runtime = Arc::new(FakeRuntime::new());
+        runtime.insert_container("srv-1", "docker.io/library/alpine:3.19");
+        let (handler, write, mut messages) = connected_handler(runtime.clone()).await;
+
+        for session in 0..4 {
+            let session_id = format!("session-{}", session);
+            let started = start_exec_session(&handler, &write, &mut messages, &session_id).await;
+            assert_eq!(started["success"], true);
+        }
+
+        let duplicate = start_exec_session(&handler, &write, &mut messages, "session-0").await;
+        assert_eq!(duplicate["success"], false);
+        assert!(duplicate["error"]
+            .as_str()
+            .unwrap()
+            .contains("already exists"));
+
+        let excess = start_exec_session(&handler, &write, &mut messages, "session-4").await;
+        assert_eq!(excess["success"], false);
+        assert!(excess["error"]
+            .as_str()
+            .unwrap()
+            .contains("Too many exec sessions"));
+
+        let started_execs = runtime
+            .calls()
+            .iter()
+            .filter(|call| call.starts_with("start_interactive_exec"))
+            .count();
+        assert_eq!(started_execs, 4);
     }
-    let total_mb = parts[1].parse::<u64>().ok()?;
-    let used_mb = parts[2].parse::<u64>().ok()?;
-    Some((used_mb, total_mb))
 }